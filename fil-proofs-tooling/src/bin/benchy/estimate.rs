@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use log::info;
+use merkletree::store::StoreConfig;
+use tempfile::NamedTempFile;
+
+use filecoin_proofs::types::{
+    PaddedBytesAmount, PoRepConfig, PoStConfig, SectorSize, UnpaddedBytesAmount,
+};
+use filecoin_proofs::{add_piece, estimate_resources, seal_pre_commit, ResourceEstimate};
+use storage_proofs::sector::SectorId;
+
+use crate::shared::{create_piece, PROVER_ID, TICKET_BYTES};
+
+/// Values measured while sealing a sector, `None` where they could not be measured.
+struct Measured {
+    sealed_size: Option<u64>,
+    cache_artifacts: Vec<Option<u64>>,
+    cache_size: Option<u64>,
+    peak_memory: Option<u64>,
+    porep_parameters_size: Option<u64>,
+    post_parameters_size: Option<u64>,
+}
+
+pub fn run(sector_size: u64, measure: bool) -> Result<()> {
+    let porep_config = PoRepConfig::new(SectorSize(sector_size))?;
+
+    info!("estimating resources for {} byte sectors", sector_size);
+    let estimate = estimate_resources(porep_config)?;
+
+    let measured = if measure {
+        Some(measure_pre_commit(porep_config, &estimate)?)
+    } else {
+        None
+    };
+
+    print_table(&estimate, measured.as_ref());
+
+    Ok(())
+}
+
+/// Seals one sector of random data and measures the files it writes, the peak memory of this
+/// process and the sizes of the groth parameters in the parameter cache.
+fn measure_pre_commit(porep_config: PoRepConfig, estimate: &ResourceEstimate) -> Result<Measured> {
+    let unpadded = UnpaddedBytesAmount::from(PaddedBytesAmount::from(porep_config));
+
+    let cache_dir = tempfile::tempdir()?;
+    let mut staged_file = NamedTempFile::new()?;
+    let sealed_file = NamedTempFile::new()?;
+
+    let (mut piece_file, piece_info) = create_piece(unpadded);
+    add_piece(&mut piece_file, &mut staged_file, unpadded, &[])?;
+
+    info!("sealing a sector to measure it");
+    seal_pre_commit(
+        porep_config,
+        cache_dir.path(),
+        staged_file.path(),
+        sealed_file.path(),
+        PROVER_ID,
+        SectorId::from(0),
+        TICKET_BYTES,
+        &[piece_info],
+    )?;
+
+    let cache_path = cache_dir.path().to_path_buf();
+    let cache_artifacts: Vec<_> = estimate
+        .cache_artifacts
+        .iter()
+        .map(|(key, _)| file_size(StoreConfig::data_path(&cache_path, key)))
+        .collect();
+    let cache_size = cache_artifacts.iter().cloned().sum();
+
+    let post_config = PoStConfig::new(porep_config.sector_size)?;
+
+    Ok(Measured {
+        sealed_size: file_size(sealed_file.path()),
+        cache_artifacts,
+        cache_size,
+        peak_memory: peak_memory().ok(),
+        porep_parameters_size: file_size(porep_config.get_cache_params_path()?),
+        post_parameters_size: file_size(post_config.get_cache_params_path()?),
+    })
+}
+
+fn file_size<P: AsRef<Path>>(path: P) -> Option<u64> {
+    fs::metadata(path).ok().map(|m| m.len())
+}
+
+/// Returns the peak resident set size of this process, which is only known on Linux.
+fn peak_memory() -> Result<u64> {
+    let status = fs::read_to_string("/proc/self/status")?;
+    let line = status
+        .lines()
+        .find(|line| line.starts_with("VmHWM:"))
+        .context("no VmHWM in /proc/self/status")?;
+    let kib: u64 = line
+        .trim_start_matches("VmHWM:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()?;
+
+    Ok(kib * 1024)
+}
+
+fn print_table(estimate: &ResourceEstimate, measured: Option<&Measured>) {
+    let mut rows = vec![
+        (
+            "sealed sector".to_string(),
+            estimate.sealed_size,
+            measured.and_then(|m| m.sealed_size),
+        ),
+        (
+            "pre-commit memory".to_string(),
+            estimate.pre_commit_memory,
+            measured.and_then(|m| m.peak_memory),
+        ),
+    ];
+    for (i, (key, size)) in estimate.cache_artifacts.iter().enumerate() {
+        rows.push((
+            format!("cache: {}", key),
+            *size,
+            measured.and_then(|m| m.cache_artifacts[i]),
+        ));
+    }
+    rows.push((
+        "cache total".to_string(),
+        estimate.cache_size(),
+        measured.and_then(|m| m.cache_size),
+    ));
+    rows.push((
+        "porep parameters".to_string(),
+        estimate.porep_parameters_size,
+        measured.and_then(|m| m.porep_parameters_size),
+    ));
+    rows.push(("commit memory".to_string(), estimate.commit_memory, None));
+    rows.push((
+        "post parameters".to_string(),
+        estimate.post_parameters_size,
+        measured.and_then(|m| m.post_parameters_size),
+    ));
+    rows.push(("post memory".to_string(), estimate.post_memory, None));
+
+    println!(
+        "porep circuit: {} constraints, post circuit: {} constraints",
+        estimate.porep_circuit.constraints, estimate.post_circuit.constraints
+    );
+    println!("labeling threads: {}", estimate.labeling_threads);
+    println!("{:<24} {:>16} {:>16}", "", "estimated", "measured");
+    for (name, estimated, measured) in rows {
+        let measured = measured
+            .map(|m| m.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        println!("{:<24} {:>16} {:>16}", name, estimated, measured);
+    }
+}
@@ -1,5 +1,3 @@
-use std::sync::atomic::Ordering::Relaxed;
-
 use bellperson::Circuit;
 use log::info;
 use paired::bls12_381::Bls12;
@@ -8,7 +6,6 @@ use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
 
 use fil_proofs_tooling::{measure, Metadata};
-use filecoin_proofs::constants::{SectorInfo, DEFAULT_POREP_PROOF_PARTITIONS};
 use filecoin_proofs::parameters::post_public_params;
 use filecoin_proofs::types::PaddedBytesAmount;
 use filecoin_proofs::types::*;
@@ -27,7 +24,6 @@ use storage_proofs::parameter_cache::CacheableParameters;
 use storage_proofs::proof::ProofScheme;
 
 use crate::shared::{create_replicas, CHALLENGE_COUNT, PROVER_ID, RANDOMNESS, TICKET_BYTES};
-use std::sync::atomic::Ordering;
 
 const SEED: [u8; 16] = [
     0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc, 0xe5,
@@ -177,29 +173,20 @@ fn augment_with_op_measurements(mut output: &mut FlarpOutputs) {
     }
 }
 
-fn configure_global_config(inputs: &FlarpInputs) {
-    let mut x = filecoin_proofs::constants::DEFAULT_WINDOWS
-        .write()
-        .expect("failed to acquire write lock on DEFAULT_WINDOWS");
-
-    x.insert(
-        inputs.sector_size_bytes,
-        SectorInfo {
-            size: inputs.sector_size_bytes,        // 1024
-            window_size: inputs.window_size_bytes, // 512
+fn porep_config(inputs: &FlarpInputs) -> PoRepConfig {
+    PoRepConfig {
+        sector_size: SectorSize(inputs.sector_size_bytes),
+        partitions: PoRepProofPartitions(inputs.porep_partitions),
+        params: ProofParameters {
+            layers: inputs.stacked_layers as usize,
+            window_minimum_challenges: inputs.porep_challenges as usize,
+            wrapper_minimum_challenges: inputs.porep_challenges as usize,
+            window_drg_degree: inputs.drg_parents as usize,
+            window_expansion_degree: inputs.expander_parents as usize,
+            wrapper_expansion_degree: inputs.wrapper_parents_all as usize,
+            window_size: Some(inputs.window_size_bytes),
         },
-    );
-
-    filecoin_proofs::constants::LAYERS.store(inputs.stacked_layers, Relaxed); // 4
-    filecoin_proofs::constants::DEFAULT_POREP_PROOF_PARTITIONS
-        .store(inputs.porep_partitions, Relaxed); // 10
-    filecoin_proofs::constants::WRAPPER_EXP_DEGREE.store(inputs.wrapper_parents_all, Relaxed); // 8
-    filecoin_proofs::constants::WINDOW_EXP_DEGREE.store(inputs.expander_parents, Relaxed); // 8
-    filecoin_proofs::constants::WINDOW_DRG_DEGREE.store(inputs.drg_parents, Relaxed); // 6
-    filecoin_proofs::constants::POREP_WINDOW_MINIMUM_CHALLENGES
-        .store(inputs.porep_challenges, Relaxed); // 50
-    filecoin_proofs::constants::POREP_WRAPPER_MINIMUM_CHALLENGES
-        .store(inputs.porep_challenges, Relaxed); // 50
+    }
 }
 
 pub fn run(
@@ -207,19 +194,19 @@ pub fn run(
     skip_seal_proof: bool,
     skip_post_proof: bool,
 ) -> Metadata<FlarpReport> {
-    configure_global_config(&inputs);
+    let cfg = porep_config(&inputs);
     generate_params(&inputs);
 
     let mut outputs = FlarpOutputs::default();
 
-    let sector_size = SectorSize(inputs.sector_size_bytes);
+    let sector_size = cfg.sector_size;
 
     // One replica for each type of proof as they cannot be shared between several proofs
     let num_replicas = [!skip_seal_proof, !skip_post_proof]
         .iter()
         .filter(|&x| *x)
         .count();
-    let (cfg, mut created) = create_replicas(sector_size, num_replicas);
+    let mut created = create_replicas(cfg, num_replicas);
 
     if !skip_seal_proof {
         let (sector_id, replica_info) = created.pop().expect("no replicas left");
@@ -463,10 +450,7 @@ fn measure_kdf_circuit(i: &FlarpInputs) -> usize {
 
 fn generate_params(i: &FlarpInputs) {
     info!("generating params: porep");
-    cache_porep_params(PoRepConfig {
-        sector_size: SectorSize(i.sector_size_bytes),
-        partitions: PoRepProofPartitions(DEFAULT_POREP_PROOF_PARTITIONS.load(Ordering::Relaxed)),
-    });
+    cache_porep_params(porep_config(i));
 
     info!("generating params: post");
     cache_post_params(PoStConfig {
@@ -484,6 +468,7 @@ fn cache_porep_params(porep_config: PoRepConfig) {
     let public_params = public_params(
         PaddedBytesAmount::from(porep_config),
         usize::from(PoRepProofPartitions::from(porep_config)),
+        porep_config.params,
     )
     .unwrap();
 
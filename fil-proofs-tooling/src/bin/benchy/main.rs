@@ -6,6 +6,7 @@ use clap::{value_t, App, Arg, SubCommand};
 use crate::flarp::FlarpInputs;
 
 mod election_post;
+mod estimate;
 mod flarp;
 mod hash_fns;
 mod merkleproofs;
@@ -119,6 +120,22 @@ fn main() -> Result<()> {
                 .takes_value(true),
         );
 
+    let estimate_cmd = SubCommand::with_name("estimate")
+        .about("Estimate the memory and disk needed to seal and prove a sector")
+        .arg(
+            Arg::with_name("size")
+                .long("size")
+                .required(true)
+                .help("The sector size in KiB")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("measure")
+                .long("measure")
+                .takes_value(false)
+                .help("Seal a sector and compare the estimates to measured values"),
+        );
+
     let hash_cmd = SubCommand::with_name("hash-constraints")
         .about("Benchmark hash function inside of a circuit");
 
@@ -158,6 +175,7 @@ fn main() -> Result<()> {
         .version("0.1")
         .subcommand(stacked_cmd)
         .subcommand(election_post_cmd)
+        .subcommand(estimate_cmd)
         .subcommand(hash_cmd)
         .subcommand(flarp_cmd)
         .subcommand(merkleproof_cmd)
@@ -194,6 +212,10 @@ fn main() -> Result<()> {
             let sector_size = sector_size_kibs * 1024;
             election_post::run(sector_size)?;
         }
+        ("estimate", Some(m)) => {
+            let sector_size_kibs = value_t!(m, "size", u64)?;
+            estimate::run(sector_size_kibs * 1024, m.is_present("measure"))?;
+        }
         ("hash-constraints", Some(_m)) => {
             hash_fns::run()?;
         }
@@ -1,14 +1,12 @@
 use std::io::{Seek, SeekFrom, Write};
-use std::sync::atomic::Ordering;
 
 use tempfile::NamedTempFile;
 
 use fil_proofs_tooling::{measure, FuncMeasurement};
-use filecoin_proofs::constants::DEFAULT_POREP_PROOF_PARTITIONS;
-use filecoin_proofs::types::{PaddedBytesAmount, PoRepConfig, SectorSize, UnpaddedBytesAmount};
+use filecoin_proofs::types::{PaddedBytesAmount, PoRepConfig, UnpaddedBytesAmount};
 use filecoin_proofs::{
-    add_piece, generate_piece_commitment, seal_pre_commit, PieceInfo, PoRepProofPartitions,
-    PrivateReplicaInfo, PublicReplicaInfo, SealPreCommitOutput,
+    add_piece, generate_piece_commitment, seal_pre_commit, PieceInfo, PrivateReplicaInfo,
+    PublicReplicaInfo, SealPreCommitOutput,
 };
 use storage_proofs::sector::SectorId;
 
@@ -53,16 +51,11 @@ pub fn create_piece(piece_bytes: UnpaddedBytesAmount) -> (NamedTempFile, PieceIn
 }
 
 pub fn create_replicas(
-    sector_size: SectorSize,
+    porep_config: PoRepConfig,
     qty_sectors: usize,
-) -> (PoRepConfig, Vec<(SectorId, PreCommitReplicaOutput)>) {
+) -> Vec<(SectorId, PreCommitReplicaOutput)> {
     let sector_size_unpadded_bytes_ammount =
-        UnpaddedBytesAmount::from(PaddedBytesAmount::from(sector_size));
-
-    let porep_config = PoRepConfig {
-        sector_size,
-        partitions: PoRepProofPartitions(DEFAULT_POREP_PROOF_PARTITIONS.load(Ordering::Relaxed)),
-    };
+        UnpaddedBytesAmount::from(PaddedBytesAmount::from(porep_config.sector_size));
 
     let mut out: Vec<(SectorId, PreCommitReplicaOutput)> = Default::default();
 
@@ -83,7 +76,7 @@ pub fn create_replicas(
             .expect("file name is not a UTF-8 string");
 
         let (mut piece_file, piece_info) = create_piece(UnpaddedBytesAmount::from(
-            PaddedBytesAmount::from(sector_size),
+            PaddedBytesAmount::from(porep_config.sector_size),
         ));
 
         add_piece(
@@ -129,5 +122,5 @@ pub fn create_replicas(
         ));
     }
 
-    (porep_config, out)
+    out
 }
@@ -0,0 +1,631 @@
+use std::fs::{create_dir_all, remove_file, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use bellperson::Circuit;
+use clap::{value_t, App, AppSettings, Arg, ArgMatches, SubCommand};
+use log::{info, warn};
+use paired::bls12_381::Bls12;
+use phase2::{verify_contribution, MPCParameters};
+use rand::rngs::OsRng;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
+use tempfile::NamedTempFile;
+
+use filecoin_proofs::constants::{DefaultPieceHasher, DefaultTreeHasher};
+use filecoin_proofs::parameters::{post_public_params, public_params};
+use filecoin_proofs::types::*;
+use storage_proofs::circuit::bench::BenchCS;
+use storage_proofs::circuit::election_post::{ElectionPoStCircuit, ElectionPoStCompound};
+use storage_proofs::circuit::stacked::{StackedCircuit, StackedCompound};
+use storage_proofs::compound_proof::CompoundProof;
+use storage_proofs::election_post::ElectionPoSt;
+use storage_proofs::parameter_cache::{
+    parameter_cache_dir, parameter_cache_metadata_path, parameter_cache_params_path,
+    parameter_cache_verifying_key_path, CacheEntryMetadata,
+};
+use storage_proofs::stacked::StackedDrg;
+
+/// The circuit a set of MPC parameters is being generated for.
+#[derive(Debug, Clone, Copy)]
+enum Proof {
+    Porep(PoRepConfig),
+    Post(PoStConfig),
+}
+
+impl Proof {
+    fn from_matches(m: &ArgMatches) -> Result<Self> {
+        let sector_size = SectorSize(value_t!(m, "sector-size", u64)?);
+
+        match m.value_of("proof") {
+            Some("porep") => {
+                let mut config = PoRepConfig::new(sector_size)?;
+                if m.is_present("partitions") {
+                    config.partitions = PoRepProofPartitions(value_t!(m, "partitions", u8)?);
+                }
+
+                Ok(Proof::Porep(config))
+            }
+            Some("election-post") => Ok(Proof::Post(PoStConfig::new(sector_size)?)),
+            other => Err(anyhow!("unknown proof type: {:?}", other)),
+        }
+    }
+
+    fn sector_size(self) -> u64 {
+        match self {
+            Proof::Porep(config) => u64::from(PaddedBytesAmount::from(config)),
+            Proof::Post(config) => u64::from(PaddedBytesAmount::from(config)),
+        }
+    }
+
+    /// The parameter cache identifier the exported parameters are stored under.
+    fn cache_identifier(self) -> Result<String> {
+        match self {
+            Proof::Porep(config) => config.get_cache_identifier(),
+            Proof::Post(config) => config.get_cache_identifier(),
+        }
+    }
+
+    fn new_parameters(self) -> Result<MPCParameters> {
+        match self {
+            Proof::Porep(config) => initial_parameters(|| blank_porep_circuit(config)),
+            Proof::Post(config) => initial_parameters(|| blank_post_circuit(config)),
+        }
+    }
+
+    /// Replays every contribution in `params` against the blank circuit, returning the
+    /// contribution hashes in order.
+    fn verify_parameters(self, params: &MPCParameters) -> Result<Vec<[u8; 64]>> {
+        match self {
+            Proof::Porep(config) => params.verify(blank_porep_circuit(config)?),
+            Proof::Post(config) => params.verify(blank_post_circuit(config)?),
+        }
+        .map_err(|()| anyhow!("parameters do not match the {:?} circuit", self))
+    }
+}
+
+fn blank_porep_circuit(
+    porep_config: PoRepConfig,
+) -> Result<StackedCircuit<'static, Bls12, DefaultTreeHasher, DefaultPieceHasher>> {
+    let public_params = public_params(
+        PaddedBytesAmount::from(porep_config),
+        usize::from(PoRepProofPartitions::from(porep_config)),
+        porep_config.params,
+    )?;
+
+    Ok(<StackedCompound as CompoundProof<
+        _,
+        StackedDrg<DefaultTreeHasher, DefaultPieceHasher>,
+        _,
+    >>::blank_circuit(&public_params))
+}
+
+fn blank_post_circuit(
+    post_config: PoStConfig,
+) -> Result<ElectionPoStCircuit<'static, Bls12, DefaultTreeHasher>> {
+    let post_public_params = post_public_params(post_config)?;
+
+    Ok(<ElectionPoStCompound<DefaultTreeHasher> as CompoundProof<
+        Bls12,
+        ElectionPoSt<DefaultTreeHasher>,
+        ElectionPoStCircuit<Bls12, DefaultTreeHasher>,
+    >>::blank_circuit(&post_public_params))
+}
+
+/// The phase 1 file which `MPCParameters::new` reads for `circuit`: the powers of tau for an
+/// evaluation domain holding the constraints of the circuit and one per public input.
+fn phase1_path<C: Circuit<Bls12>>(circuit: C) -> Result<PathBuf> {
+    let mut cs = BenchCS::<Bls12>::new();
+    circuit.synthesize(&mut cs)?;
+    let domain_size = (cs.num_constraints() + cs.num_inputs()).next_power_of_two();
+
+    Ok(PathBuf::from(format!(
+        "phase1radix2m{}",
+        domain_size.trailing_zeros()
+    )))
+}
+
+/// Creates the initial MPC parameters for the circuit built by `blank_circuit`, checking first
+/// that its phase 1 file is in the working directory, where `MPCParameters::new` reads it from
+/// (and panics if it is missing).
+fn initial_parameters<C, F>(blank_circuit: F) -> Result<MPCParameters>
+where
+    C: Circuit<Bls12>,
+    F: Fn() -> Result<C>,
+{
+    let phase1 = phase1_path(blank_circuit()?)?;
+    ensure!(
+        phase1.exists(),
+        "the phase 1 file {} is missing from the working directory {:?}, it holds the powers of tau the circuit needs",
+        phase1.display(),
+        std::env::current_dir()?
+    );
+
+    MPCParameters::new(blank_circuit()?).map_err(Into::into)
+}
+
+fn read_parameters(path: &Path) -> Result<MPCParameters> {
+    info!("reading MPC parameters from {:?}", path);
+    let file = File::open(path).with_context(|| format!("could not open {:?}", path))?;
+    MPCParameters::read(BufReader::new(file), true)
+        .with_context(|| format!("could not read MPC parameters from {:?}", path))
+}
+
+fn write_parameters(params: &MPCParameters, path: &Path) -> Result<()> {
+    info!("writing MPC parameters to {:?}", path);
+    let file = write_temp(path, |writer| Ok(params.write(writer)?))?;
+    persist(file, path, true)
+}
+
+/// Writes the future contents of `path` to a temporary file in the same directory, to be moved
+/// into place with `persist` once complete. An interrupted write never leaves a partial file at
+/// `path`.
+fn write_temp<F>(path: &Path, write: F) -> Result<NamedTempFile>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut file = NamedTempFile::new_in(dir)
+        .with_context(|| format!("could not create a temporary file in {:?}", dir))?;
+
+    {
+        let mut writer = BufWriter::new(file.as_file_mut());
+        write(&mut writer).with_context(|| format!("could not write {:?}", path))?;
+        writer.flush()?;
+    }
+    file.as_file().sync_all()?;
+
+    Ok(file)
+}
+
+/// Renames a file written by `write_temp` to `path`. Unless `overwrite` is set this fails if
+/// `path` exists, including when another process creates it concurrently.
+fn persist(file: NamedTempFile, path: &Path, overwrite: bool) -> Result<()> {
+    if overwrite {
+        file.persist(path)
+    } else {
+        file.persist_noclobber(path)
+    }
+    .map_err(|err| err.error)
+    .with_context(|| format!("could not move the written file to {:?}", path))?;
+
+    Ok(())
+}
+
+/// Seeds the contribution RNG from the OS and, optionally, from participant supplied entropy.
+fn contribution_rng(entropy: Option<&str>) -> ChaChaRng {
+    let mut os_seed = [0u8; 32];
+    OsRng.fill_bytes(&mut os_seed);
+
+    let mut hasher = blake2s_simd::State::new();
+    hasher.update(&os_seed);
+    if let Some(entropy) = entropy {
+        hasher.update(entropy.as_bytes());
+    }
+
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(hasher.finalize().as_bytes());
+
+    ChaChaRng::from_seed(seed)
+}
+
+fn new(proof: Proof, out: &Path) -> Result<()> {
+    info!("initializing MPC parameters for {:?}", proof);
+    let params = proof.new_parameters()?;
+    write_parameters(&params, out)?;
+    println!("wrote initial parameters to {}", out.display());
+
+    Ok(())
+}
+
+fn contribute(input: &Path, out: &Path, entropy: Option<&str>) -> Result<()> {
+    let mut params = read_parameters(input)?;
+
+    info!("contributing randomness");
+    let hash = params.contribute(&mut contribution_rng(entropy));
+    write_parameters(&params, out)?;
+
+    println!("contribution hash: {}", hex::encode(&hash[..]));
+
+    Ok(())
+}
+
+fn verify(proof: Proof, input: &Path) -> Result<()> {
+    let params = read_parameters(input)?;
+    let contributions = proof.verify_parameters(&params)?;
+
+    println!("{} contributions verified:", contributions.len());
+    for (i, hash) in contributions.iter().enumerate() {
+        println!("{}: {}", i, hex::encode(&hash[..]));
+    }
+
+    Ok(())
+}
+
+fn verify_step(before: &Path, after: &Path) -> Result<()> {
+    let before = read_parameters(before)?;
+    let after = read_parameters(after)?;
+
+    let hash = verify_contribution(&before, &after)
+        .map_err(|()| anyhow!("contribution is not a valid transformation of its input"))?;
+
+    println!("contribution verified: {}", hex::encode(&hash[..]));
+
+    Ok(())
+}
+
+fn export(proof: Proof, input: &Path, out_dir: Option<&Path>) -> Result<()> {
+    let params = read_parameters(input)?;
+    let contributions = proof.verify_parameters(&params)?;
+    // without a contribution the toxic waste of the initial parameters is public (delta = 1),
+    // so anyone could forge proofs against them
+    ensure!(
+        !contributions.is_empty(),
+        "refusing to export parameters without any contribution"
+    );
+
+    let id = proof.cache_identifier()?;
+    let in_dir = |p: PathBuf| -> Result<PathBuf> {
+        match out_dir {
+            Some(dir) => Ok(dir.join(p.file_name().context("invalid cache path")?)),
+            None => Ok(p),
+        }
+    };
+    let params_path = in_dir(parameter_cache_params_path(&id))?;
+    let vk_path = in_dir(parameter_cache_verifying_key_path(&id))?;
+    let meta_path = in_dir(parameter_cache_metadata_path(&id))?;
+
+    create_dir_all(
+        out_dir
+            .map(Path::to_path_buf)
+            .unwrap_or_else(parameter_cache_dir),
+    )?;
+    export_parameters(
+        &params,
+        proof.sector_size(),
+        &params_path,
+        &vk_path,
+        &meta_path,
+    )?;
+
+    println!("wrote {}", params_path.display());
+    println!("wrote {}", vk_path.display());
+    println!("wrote {}", meta_path.display());
+
+    Ok(())
+}
+
+/// Writes the Groth parameters, verifying key and metadata of `params`, refusing to replace any
+/// existing file. All three files are completely written before any is moved into place, and the
+/// parameters are moved last, so that their presence implies the other two are complete. If a
+/// file appears concurrently, the files already moved into place by this call are removed again.
+fn export_parameters(
+    params: &MPCParameters,
+    sector_size: u64,
+    params_path: &Path,
+    vk_path: &Path,
+    meta_path: &Path,
+) -> Result<()> {
+    for path in &[params_path, vk_path, meta_path] {
+        ensure!(!path.exists(), "refusing to overwrite {}", path.display());
+    }
+
+    let groth_params = params.get_params();
+    let vk_file = write_temp(vk_path, |writer| Ok(groth_params.vk.write(writer)?))?;
+    let meta_file = write_temp(meta_path, |writer| {
+        Ok(serde_json::to_writer(
+            writer,
+            &CacheEntryMetadata { sector_size },
+        )?)
+    })?;
+    let params_file = write_temp(params_path, |writer| Ok(groth_params.write(writer)?))?;
+
+    let mut persisted = Vec::new();
+    for (file, path) in vec![
+        (vk_file, vk_path),
+        (meta_file, meta_path),
+        (params_file, params_path),
+    ] {
+        if let Err(err) = persist(file, path, false) {
+            for path in persisted {
+                if let Err(err) = remove_file(path) {
+                    warn!("could not remove {:?}: {}", path, err);
+                }
+            }
+            return Err(err);
+        }
+        persisted.push(path);
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    fil_logger::init();
+
+    let proof_args = [
+        Arg::with_name("proof")
+            .long("proof")
+            .required(true)
+            .possible_values(&["porep", "election-post"])
+            .takes_value(true)
+            .help("The circuit to generate parameters for"),
+        Arg::with_name("sector-size")
+            .long("sector-size")
+            .required(true)
+            .takes_value(true)
+            .help("The sector size in bytes"),
+        Arg::with_name("partitions")
+            .long("partitions")
+            .takes_value(true)
+            .help("Number of PoRep partitions (defaults to the registered value)"),
+    ];
+
+    let matches = App::new("phase2")
+        .version("0.1")
+        .about("Run the circuit specific (phase 2) part of a multi-party trusted setup")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("new")
+                .about("Create the initial MPC parameters for a circuit, reading the phase 1 file it needs, phase1radix2m<exp>, from the working directory")
+                .args(&proof_args)
+                .arg(Arg::with_name("out").required(true).help("Output file")),
+        )
+        .subcommand(
+            SubCommand::with_name("contribute")
+                .about("Add a contribution to the MPC parameters in <in>, writing them to <out>")
+                .arg(Arg::with_name("in").required(true).help("Input file"))
+                .arg(Arg::with_name("out").required(true).help("Output file"))
+                .arg(
+                    Arg::with_name("entropy")
+                        .long("entropy")
+                        .takes_value(true)
+                        .help("Additional entropy, mixed with randomness from the OS"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Verify every contribution made to the MPC parameters for a circuit")
+                .args(&proof_args)
+                .arg(Arg::with_name("in").required(true).help("Input file")),
+        )
+        .subcommand(
+            SubCommand::with_name("verify-contribution")
+                .about("Verify that <after> is a single valid contribution on top of <before>")
+                .arg(Arg::with_name("before").required(true))
+                .arg(Arg::with_name("after").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Verify the MPC parameters and export them as .params, .vk and .meta files")
+                .args(&proof_args)
+                .arg(Arg::with_name("in").required(true).help("Input file"))
+                .arg(
+                    Arg::with_name("out-dir")
+                        .long("out-dir")
+                        .takes_value(true)
+                        .help("Directory to write to (defaults to the parameter cache)"),
+                ),
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        ("new", Some(m)) => new(
+            Proof::from_matches(m)?,
+            Path::new(m.value_of("out").unwrap()),
+        ),
+        ("contribute", Some(m)) => contribute(
+            Path::new(m.value_of("in").unwrap()),
+            Path::new(m.value_of("out").unwrap()),
+            m.value_of("entropy"),
+        ),
+        ("verify", Some(m)) => verify(
+            Proof::from_matches(m)?,
+            Path::new(m.value_of("in").unwrap()),
+        ),
+        ("verify-contribution", Some(m)) => verify_step(
+            Path::new(m.value_of("before").unwrap()),
+            Path::new(m.value_of("after").unwrap()),
+        ),
+        ("export", Some(m)) => export(
+            Proof::from_matches(m)?,
+            Path::new(m.value_of("in").unwrap()),
+            m.value_of("out-dir").map(Path::new),
+        ),
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use bellperson::groth16::{
+        create_random_proof, prepare_verifying_key, verify_proof, Parameters, VerifyingKey,
+    };
+    use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+    use ff::{Field, PrimeField};
+    use paired::bls12_381::{Fr, G1Affine, G2Affine};
+    use paired::{CurveAffine, CurveProjective};
+    use rand_xorshift::XorShiftRng;
+
+    struct Square(Option<Fr>);
+
+    impl Circuit<Bls12> for Square {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> std::result::Result<(), SynthesisError> {
+            let x_val = self.0;
+            let x = cs.alloc(|| "x", || x_val.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(
+                || "y",
+                || {
+                    let mut y = x_val.ok_or(SynthesisError::AssignmentMissing)?;
+                    y.square();
+                    Ok(y)
+                },
+            )?;
+            cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    /// Writes the `phase1radix2m{exp}` file read by `MPCParameters::new`, as the powers of tau
+    /// ceremony would, but from known randomness.
+    fn write_phase1(dir: &Path, exp: u32, rng: &mut XorShiftRng) {
+        let m = 1u64 << exp;
+        let tau = Fr::random(rng);
+        let alpha = Fr::random(rng);
+        let beta = Fr::random(rng);
+
+        // the generator of the evaluation domain, an m-th root of unity
+        let mut omega = Fr::root_of_unity();
+        for _ in exp..Fr::S {
+            omega.square();
+        }
+
+        // tau^m - 1, which vanishes on the domain
+        let mut z = tau.pow([m]);
+        z.sub_assign(&Fr::one());
+
+        // the Lagrange polynomials at tau: omega^i (tau^m - 1) / (m (tau - omega^i))
+        let m_inv = Fr::from_str(&m.to_string()).unwrap().inverse().unwrap();
+        let mut omega_i = Fr::one();
+        let mut lagrange = Vec::new();
+        for _ in 0..m {
+            let mut l = tau;
+            l.sub_assign(&omega_i);
+            l = l.inverse().unwrap();
+            l.mul_assign(&omega_i);
+            l.mul_assign(&z);
+            l.mul_assign(&m_inv);
+            lagrange.push(l);
+
+            omega_i.mul_assign(&omega);
+        }
+
+        let g1 = |s: Fr| G1Affine::one().mul(s).into_affine().into_uncompressed();
+        let g2 = |s: Fr| G2Affine::one().mul(s).into_affine().into_uncompressed();
+        let times = |a: Fr, b: &Fr| {
+            let mut a = a;
+            a.mul_assign(b);
+            a
+        };
+
+        let mut out = Vec::new();
+        out.extend_from_slice(g1(alpha).as_ref());
+        out.extend_from_slice(g1(beta).as_ref());
+        out.extend_from_slice(g2(beta).as_ref());
+        for l in &lagrange {
+            out.extend_from_slice(g1(*l).as_ref());
+        }
+        for l in &lagrange {
+            out.extend_from_slice(g2(*l).as_ref());
+        }
+        for l in &lagrange {
+            out.extend_from_slice(g1(times(alpha, l)).as_ref());
+        }
+        for l in &lagrange {
+            out.extend_from_slice(g1(times(beta, l)).as_ref());
+        }
+        // tau^i (tau^m - 1)
+        for i in 0..m - 1 {
+            out.extend_from_slice(g1(times(tau.pow([i]), &z)).as_ref());
+        }
+
+        fs::write(dir.join(format!("phase1radix2m{}", exp)), out).unwrap();
+    }
+
+    const INITIAL_PARAMS_ENV: &str = "PHASE2_TEST_INITIAL_PARAMS";
+
+    /// `MPCParameters::new` reads the phase 1 file from the working directory. Instead of
+    /// changing the directory of the whole test process, the initial parameters are written by a
+    /// copy of this test binary started in `dir`.
+    fn write_initial_parameters(dir: &Path, out: &Path) {
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(&["--exact", "tests::initial_parameters", "--ignored"])
+            .current_dir(dir)
+            .env(INITIAL_PARAMS_ENV, out)
+            .status()
+            .unwrap();
+        assert!(status.success(), "writing the initial parameters failed");
+    }
+
+    /// Run by `write_initial_parameters` only.
+    #[test]
+    #[ignore]
+    fn initial_parameters() {
+        if let Some(out) = std::env::var_os(INITIAL_PARAMS_ENV) {
+            let params = MPCParameters::new(Square(None)).unwrap();
+            write_parameters(&params, Path::new(&out)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_contribute_verify_export() {
+        let rng = &mut XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+
+        // one constraint, plus one for each of the two inputs, rounded up to 2^2
+        assert_eq!(
+            phase1_path(Square(None)).unwrap(),
+            Path::new("phase1radix2m2")
+        );
+        write_phase1(dir.path(), 2, rng);
+
+        let initial = dir.path().join("square.0");
+        let contributed = dir.path().join("square.1");
+        write_initial_parameters(dir.path(), &initial);
+
+        let mut params = read_parameters(&initial).unwrap();
+        let hash = params.contribute(&mut contribution_rng(Some("entropy")));
+        write_parameters(&params, &contributed).unwrap();
+
+        let before = read_parameters(&initial).unwrap();
+        let after = read_parameters(&contributed).unwrap();
+        assert_eq!(verify_contribution(&before, &after).unwrap()[..], hash[..]);
+
+        let contributions = after.verify(Square(None)).unwrap();
+        assert_eq!(contributions.len(), 1);
+        assert_eq!(contributions[0][..], hash[..]);
+
+        let out_dir = dir.path().join("out");
+        create_dir_all(&out_dir).unwrap();
+        let params_path = out_dir.join("square.params");
+        let vk_path = out_dir.join("square.vk");
+        let meta_path = out_dir.join("square.meta");
+        export_parameters(&after, 1024, &params_path, &vk_path, &meta_path).unwrap();
+        assert!(export_parameters(&after, 1024, &params_path, &vk_path, &meta_path).is_err());
+        assert_eq!(
+            fs::read_dir(&out_dir).unwrap().count(),
+            3,
+            "no temporary files are left behind"
+        );
+
+        let exported = Parameters::<Bls12>::read(File::open(&params_path).unwrap(), true).unwrap();
+        let vk = VerifyingKey::<Bls12>::read(File::open(&vk_path).unwrap()).unwrap();
+        assert!(exported.vk == vk);
+        let meta: CacheEntryMetadata =
+            serde_json::from_reader(File::open(&meta_path).unwrap()).unwrap();
+        assert_eq!(meta.sector_size, 1024);
+
+        let mut x = Fr::one();
+        x.double();
+        let mut y = x;
+        y.square();
+
+        let proof = create_random_proof(Square(Some(x)), &exported, rng).unwrap();
+        assert!(verify_proof(&prepare_verifying_key(&vk), &proof, &[y]).unwrap());
+    }
+}
@@ -0,0 +1,80 @@
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use blake2b_simd::State as Blake2b;
+use serde_json::Value;
+
+// Must match storage_proofs::parameter_cache, which cannot be used from a build script.
+const PARAMETER_CACHE_ENV_VAR: &str = "FIL_PROOFS_PARAMETER_CACHE";
+const PARAMETER_CACHE_DIR: &str = "/var/tmp/filecoin-proof-parameters/";
+
+/// With the `embedded-verifying-keys` feature, writes `embedded_verifying_keys.rs` to `OUT_DIR`:
+/// a slice of `(file name, bytes)` holding every `.vk` listed in `parameters.json`. The keys are
+/// read from the parameter cache, so `paramfetch` must have been run first.
+fn main() {
+    if env::var_os("CARGO_FEATURE_EMBEDDED_VERIFYING_KEYS").is_none() {
+        return;
+    }
+
+    println!("cargo:rerun-if-changed=parameters.json");
+    println!("cargo:rerun-if-env-changed={}", PARAMETER_CACHE_ENV_VAR);
+
+    let cache_dir = PathBuf::from(
+        env::var(PARAMETER_CACHE_ENV_VAR).unwrap_or_else(|_| PARAMETER_CACHE_DIR.to_string()),
+    );
+
+    let manifest: Value = serde_json::from_reader(
+        File::open("parameters.json").expect("could not open parameters.json"),
+    )
+    .expect("could not parse parameters.json");
+
+    let mut out = String::from("&[\n");
+
+    for (filename, data) in manifest
+        .as_object()
+        .expect("parameters.json is not an object")
+    {
+        if !filename.ends_with(".vk") {
+            continue;
+        }
+
+        let path = cache_dir.join(filename);
+        let expected = data["digest"].as_str().expect("missing digest");
+        let actual = digest(&path).unwrap_or_else(|err| {
+            panic!(
+                "could not read {:?} ({}), run paramfetch before building with embedded-verifying-keys",
+                path, err
+            )
+        });
+        assert_eq!(
+            actual, expected,
+            "{:?} does not match the digest in parameters.json",
+            path
+        );
+
+        println!("cargo:rerun-if-changed={}", path.display());
+        writeln!(
+            out,
+            "    ({:?}, include_bytes!({:?})),",
+            filename,
+            fs::canonicalize(&path).expect("invalid path")
+        )
+        .unwrap();
+    }
+
+    out.push_str("]\n");
+
+    let out_path = Path::new(&env::var("OUT_DIR").unwrap()).join("embedded_verifying_keys.rs");
+    fs::write(&out_path, out).expect("could not write embedded verifying keys");
+}
+
+fn digest(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Blake2b::new();
+    io::copy(&mut file, &mut hasher)?;
+
+    Ok(hasher.finalize().to_hex()[..32].into())
+}
@@ -1,15 +1,9 @@
 use std::collections::BTreeMap;
-use std::fs::File;
-use std::io::prelude::*;
-use std::path::{Path, PathBuf};
 
-use anyhow::{anyhow, ensure, Context, Result};
-use bincode::deserialize;
+use anyhow::{ensure, Context, Result};
 use log::info;
-use merkletree::merkle::{get_merkle_tree_leafs, MerkleTree};
-use merkletree::store::{DiskStore, Store, StoreConfig, DEFAULT_CACHED_ABOVE_BASE_LAYER};
+use num_bigint::BigUint;
 use paired::bls12_381::Bls12;
-use rayon::prelude::*;
 use storage_proofs::circuit::election_post::ElectionPoStCompound;
 use storage_proofs::circuit::multi_proof::MultiProof;
 use storage_proofs::compound_proof::{self, CompoundProof};
@@ -19,103 +13,16 @@ use storage_proofs::fr32::bytes_into_fr;
 use storage_proofs::hasher::Hasher;
 use storage_proofs::proof::NoRequirements;
 use storage_proofs::sector::*;
-use storage_proofs::stacked::CacheKey;
 
 use crate::api::util::as_safe_commitment;
-use crate::caches::{get_post_params, get_post_verifying_key};
+use crate::caches::{get_post_verifying_key, Bls12VerifyingKey};
+use crate::constants::SINGLE_PARTITION_PROOF_LEN;
+use crate::error::Error;
 use crate::parameters::post_setup_params;
-use crate::types::{
-    ChallengeSeed, Commitment, PersistentAux, PoStConfig, ProverId, SectorSize, Tree,
-};
+use crate::types::{ChallengeSeed, Commitment, PoStConfig, ProverId, Ticket};
 
 pub use storage_proofs::election_post::Candidate;
 
-/// The minimal information required about a replica, in order to be able to generate
-/// a PoSt over it.
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct PrivateReplicaInfo {
-    /// Path to the replica.
-    access: String,
-    /// The replica commitment.
-    comm_r: Commitment,
-    /// Persistent Aux.
-    aux: PersistentAux,
-    /// Contains sector-specific (e.g. merkle trees) assets
-    cache_dir: PathBuf,
-}
-
-impl std::cmp::Ord for PrivateReplicaInfo {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.comm_r.as_ref().cmp(other.comm_r.as_ref())
-    }
-}
-
-impl std::cmp::PartialOrd for PrivateReplicaInfo {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl PrivateReplicaInfo {
-    pub fn new(access: String, comm_r: Commitment, cache_dir: PathBuf) -> Result<Self> {
-        ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
-
-        let aux = {
-            let mut aux_bytes = vec![];
-            let f_aux_path = cache_dir.join(CacheKey::PAux.to_string());
-            let mut f_aux = File::open(&f_aux_path)
-                .with_context(|| format!("could not open path={:?}", f_aux_path))?;
-            f_aux
-                .read_to_end(&mut aux_bytes)
-                .with_context(|| format!("could not read from path={:?}", f_aux_path))?;
-
-            deserialize(&aux_bytes)
-        }?;
-
-        Ok(PrivateReplicaInfo {
-            access,
-            comm_r,
-            aux,
-            cache_dir,
-        })
-    }
-
-    pub fn cache_dir_path(&self) -> &Path {
-        self.cache_dir.as_path()
-    }
-
-    pub fn safe_comm_r(&self) -> Result<<DefaultTreeHasher as Hasher>::Domain> {
-        as_safe_commitment(&self.comm_r, "comm_r")
-    }
-
-    pub fn safe_comm_c(&self) -> Result<<DefaultTreeHasher as Hasher>::Domain> {
-        Ok(self.aux.comm_c)
-    }
-
-    pub fn safe_comm_q(&self) -> Result<<DefaultTreeHasher as Hasher>::Domain> {
-        Ok(self.aux.comm_q)
-    }
-
-    pub fn safe_comm_r_last(&self) -> Result<<DefaultTreeHasher as Hasher>::Domain> {
-        Ok(self.aux.comm_r_last)
-    }
-
-    /// Generate the merkle tree of this particular replica.
-    pub fn merkle_tree(&self, tree_size: usize, tree_leafs: usize) -> Result<Tree> {
-        let mut config = StoreConfig::new(
-            &self.cache_dir,
-            CacheKey::CommRLastTree.to_string(),
-            DEFAULT_CACHED_ABOVE_BASE_LAYER,
-        );
-        config.size = Some(tree_size);
-        let tree_r_last_store: DiskStore<<DefaultTreeHasher as Hasher>::Domain> =
-            DiskStore::new_from_disk(tree_size, &config)?;
-        let tree_r_last: Tree = MerkleTree::from_data_store(tree_r_last_store, tree_leafs)?;
-
-        Ok(tree_r_last)
-    }
-}
-
 /// The minimal information required about a replica, in order to be able to verify
 /// a PoSt over it.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -138,7 +45,7 @@ impl std::cmp::PartialOrd for PublicReplicaInfo {
 
 impl PublicReplicaInfo {
     pub fn new(comm_r: Commitment) -> Result<Self> {
-        ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
+        ensure!(comm_r != [0; 32], Error::ZeroCommitment("comm_r"));
         Ok(PublicReplicaInfo { comm_r })
     }
 
@@ -147,203 +54,154 @@ impl PublicReplicaInfo {
     }
 }
 
-fn get_tree_size(sector_size: SectorSize) -> usize {
-    let sector_size = u64::from(sector_size);
-    let elems = sector_size as usize / std::mem::size_of::<<DefaultTreeHasher as Hasher>::Domain>();
+pub type SnarkProof = Vec<u8>;
+
+/// Generates a ticket from a partial_ticket.
+pub fn finalize_ticket(partial_ticket: &[u8; 32]) -> Result<[u8; 32]> {
+    let partial_ticket =
+        bytes_into_fr::<Bls12>(partial_ticket).context("Invalid partial_ticket")?;
+    Ok(election_post::finalize_ticket(&partial_ticket))
+}
+
+/// The parameters of the election deciding which candidates win.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WinnerTargetParams {
+    /// Power of the prover, i.e. the total size in bytes of its proven sectors.
+    pub power: u128,
+    /// Power of all provers in the network, in bytes.
+    pub total_power: u128,
+    /// The number of sector challenges, as passed to `generate_candidates`.
+    pub challenge_count: u64,
+    /// The expected number of winning tickets in the whole network, per round.
+    pub expected_winners: u64,
+    /// Keep at most this many winners, those with the lowest tickets.
+    pub max_winners: Option<usize>,
+}
+
+impl WinnerTargetParams {
+    fn validate(&self) -> Result<()> {
+        ensure!(self.total_power > 0, "total power must not be zero");
+        ensure!(
+            self.power <= self.total_power,
+            "power ({}) must not exceed the total power ({})",
+            self.power,
+            self.total_power
+        );
+        ensure!(self.challenge_count > 0, "challenge count must not be zero");
 
-    2 * elems - 1
+        Ok(())
+    }
 }
 
-/// Generates proof-of-spacetime candidates for ElectionPoSt.
+/// Returns whether `ticket` wins the election described by `params`. The ticket is read as a
+/// 256 bit big-endian unsigned integer, and wins if
 ///
-/// # Arguments
+/// `ticket * total_power * challenge_count < expected_winners * power * 2^256`
 ///
-/// * `post_config` - post config that contains the sector size of each sector that we are
-/// generating this post for.
-/// * `randomness` - randomness used to generate sector challenges.
-/// * `challenge_count` - the number sector challenges in this post.
-/// * `replicas` - each sector's sector-id and associated replica info.
-/// * `prover_id` - the prover-id that is generating this post.
-pub fn generate_candidates(
-    post_config: PoStConfig,
-    randomness: &ChallengeSeed,
-    challenge_count: u64,
-    replicas: &BTreeMap<SectorId, PrivateReplicaInfo>,
-    prover_id: ProverId,
-) -> Result<Vec<Candidate>> {
-    info!("generate_candidates:start");
-
-    let vanilla_params = post_setup_params(post_config);
-    let setup_params = compound_proof::SetupParams {
-        vanilla_params,
-        partitions: None,
-    };
-    let public_params: compound_proof::PublicParams<
-        election_post::ElectionPoSt<DefaultTreeHasher>,
-    > = ElectionPoStCompound::setup(&setup_params)?;
+/// so that each of the `challenge_count` candidates of a prover wins with a probability of
+/// `expected_winners * power / (total_power * challenge_count)`.
+pub fn is_winning_ticket(ticket: &Ticket, params: &WinnerTargetParams) -> Result<bool> {
+    params.validate()?;
 
-    let sector_count = replicas.len() as u64;
-    ensure!(sector_count > 0, "Must supply at least one replica");
+    let lhs = BigUint::from_bytes_be(ticket)
+        * BigUint::from(params.total_power)
+        * BigUint::from(params.challenge_count);
+    let rhs = (BigUint::from(params.expected_winners) * BigUint::from(params.power)) << 256;
 
-    let sectors = replicas.keys().copied().collect();
+    Ok(lhs < rhs)
+}
 
-    let challenged_sectors =
-        election_post::generate_sector_challenges(randomness, challenge_count, &sectors)?;
-
-    // Match the replicas to the challenges, as these are the only ones required.
-    let challenged_replicas: Vec<_> = challenged_sectors
-        .iter()
-        .map(|c| {
-            if let Some(replica) = replicas.get(c) {
-                Ok((c, replica))
-            } else {
-                Err(anyhow!(
-                    "Invalid challenge generated: {}, only {} sectors are being proven",
-                    c,
-                    sector_count
-                ))
-            }
-        })
-        .collect::<Result<_, _>>()?;
-
-    // Generate merkle trees for the challenged replicas.
-    // Merkle trees should be generated only once, not multiple times if the same sector is challenged
-    // multiple times, so we build a HashMap of trees.
-
-    let mut unique_challenged_replicas = challenged_replicas.clone();
-    unique_challenged_replicas.sort_unstable(); // dedup requires a sorted list
-    unique_challenged_replicas.dedup();
-
-    let tree_size = get_tree_size(post_config.sector_size);
-    let tree_leafs = get_merkle_tree_leafs(tree_size);
-
-    let unique_trees_res: Vec<_> = unique_challenged_replicas
-        .into_par_iter()
-        .map(|(id, replica)| {
-            replica
-                .merkle_tree(tree_size, tree_leafs)
-                .map(|tree| (*id, tree))
-        })
-        .collect();
-
-    // resolve results
-    let trees: BTreeMap<SectorId, Tree> = unique_trees_res.into_iter().collect::<Result<_, _>>()?;
-
-    let candidates = election_post::generate_candidates::<DefaultTreeHasher>(
-        &public_params.vanilla_params,
-        &challenged_sectors,
-        &trees,
-        &prover_id,
-        randomness,
-    )?;
+/// Selects the winning candidates, see `is_winning_ticket`. The winners are ordered by ticket,
+/// ties being broken by sector id and then by sector challenge index, so the result does not
+/// depend on the order of `candidates`. With `max_winners` set, only the first winners in this
+/// order are kept.
+pub fn select_winners(
+    candidates: &[Candidate],
+    params: &WinnerTargetParams,
+) -> Result<Vec<Candidate>> {
+    params.validate()?;
+
+    let mut winners = Vec::new();
+    for candidate in candidates {
+        ensure!(
+            candidate.ticket == election_post::finalize_ticket(&candidate.partial_ticket),
+            "ticket of the candidate for sector {} does not match its partial ticket",
+            candidate.sector_id
+        );
 
-    info!("generate_candidates:finish");
+        if is_winning_ticket(&candidate.ticket, params)? {
+            winners.push(candidate.clone());
+        }
+    }
 
-    Ok(candidates)
-}
+    // Big-endian tickets of the same length compare like the numbers they encode.
+    winners.sort_by(|a, b| {
+        a.ticket
+            .cmp(&b.ticket)
+            .then(a.sector_id.cmp(&b.sector_id))
+            .then(a.sector_challenge_index.cmp(&b.sector_challenge_index))
+    });
 
-pub type SnarkProof = Vec<u8>;
+    if let Some(max_winners) = params.max_winners {
+        winners.truncate(max_winners);
+    }
 
-/// Generates a ticket from a partial_ticket.
-pub fn finalize_ticket(partial_ticket: &[u8; 32]) -> Result<[u8; 32]> {
-    let partial_ticket =
-        bytes_into_fr::<Bls12>(partial_ticket).context("Invalid partial_ticket")?;
-    Ok(election_post::finalize_ticket(&partial_ticket))
+    Ok(winners)
 }
 
-/// Generates a proof-of-spacetime.
+/// Verifies a proof-of-spacetime.
 ///
 /// # Arguments
 ///
-/// * `post_config` - post config that contains the sector size of each sector that we are
-/// generating this post for.
-/// * `randomness` - randomness used to generate sector challenges.
+/// * `post_config` - post config that contains the sector size of each sector that this post was
+/// generated for.
+/// * `randomness` - the randomness used to generate the sector challenges.
+/// * `challenge_count` - the number of sector challenges in this post.
+/// * `proofs` - each winning ticket's serialized circuit proof.
 /// * `replicas` - each sector's sector-id and associated replica info.
 /// * `winners` - a vector containing each winning ticket.
-/// * `prover_id` - the prover-id that is generating this post.
-pub fn generate_post(
+/// * `prover_id` - the prover-id that generated this post.
+pub fn verify_post(
     post_config: PoStConfig,
     randomness: &ChallengeSeed,
-    replicas: &BTreeMap<SectorId, PrivateReplicaInfo>,
-    winners: Vec<Candidate>,
+    challenge_count: u64,
+    proofs: &[Vec<u8>],
+    replicas: &BTreeMap<SectorId, PublicReplicaInfo>,
+    winners: &[Candidate],
     prover_id: ProverId,
-) -> Result<Vec<SnarkProof>> {
-    info!("generate_post:start");
-
-    let sector_count = replicas.len() as u64;
-    ensure!(sector_count > 0, "Must supply at least one replica");
-
-    let vanilla_params = post_setup_params(post_config);
-    let setup_params = compound_proof::SetupParams {
-        vanilla_params,
-        partitions: None,
-    };
-    let pub_params: compound_proof::PublicParams<election_post::ElectionPoSt<DefaultTreeHasher>> =
-        ElectionPoStCompound::setup(&setup_params)?;
-    let groth_params = get_post_params(post_config)?;
-
-    let tree_size = get_tree_size(post_config.sector_size);
-    let tree_leafs = get_merkle_tree_leafs(tree_size);
-
-    let mut proofs = Vec::with_capacity(winners.len());
-
-    let inputs: Vec<_> = winners
-        .par_iter()
-        .map(|winner| {
-            let replica = replicas
-                .get(&winner.sector_id)
-                .with_context(|| format!("Missing replica for sector: {}", winner.sector_id))?;
-            let tree = replica.merkle_tree(tree_size, tree_leafs)?;
-
-            let comm_r = replica.safe_comm_r()?;
-            let pub_inputs = election_post::PublicInputs {
-                randomness: *randomness,
-                comm_r,
-                sector_id: winner.sector_id,
-                partial_ticket: winner.partial_ticket,
-                sector_challenge_index: winner.sector_challenge_index,
-                prover_id,
-            };
-
-            let comm_c = replica.safe_comm_c()?;
-            let comm_q = replica.safe_comm_q()?;
-            let comm_r_last = replica.safe_comm_r_last()?;
-            let priv_inputs = election_post::PrivateInputs::<DefaultTreeHasher> {
-                tree,
-                comm_c,
-                comm_q,
-                comm_r_last,
-            };
-
-            Ok((pub_inputs, priv_inputs))
-        })
-        .collect::<Result<_>>()?;
-
-    for (pub_inputs, priv_inputs) in &inputs {
-        let proof =
-            ElectionPoStCompound::prove(&pub_params, &pub_inputs, &priv_inputs, &groth_params)?;
-        proofs.push(proof.to_vec()?);
-    }
-
-    info!("generate_post:finish");
+) -> Result<bool> {
+    let verifying_key = get_post_verifying_key(post_config)?;
 
-    Ok(proofs)
+    verify_post_with_verifying_key(
+        post_config,
+        &verifying_key,
+        randomness,
+        challenge_count,
+        proofs,
+        replicas,
+        winners,
+        prover_id,
+    )
 }
 
-/// Verifies a proof-of-spacetime.
+/// Verifies a proof-of-spacetime against the given verifying key, rather than the one found in
+/// the parameter cache.
 ///
 /// # Arguments
 ///
 /// * `post_config` - post config that contains the sector size of each sector that this post was
 /// generated for.
+/// * `verifying_key` - the verifying key of the election post circuit for `post_config`.
 /// * `randomness` - the randomness used to generate the sector challenges.
 /// * `challenge_count` - the number of sector challenges in this post.
 /// * `proofs` - each winning ticket's serialized circuit proof.
 /// * `replicas` - each sector's sector-id and associated replica info.
 /// * `winners` - a vector containing each winning ticket.
 /// * `prover_id` - the prover-id that generated this post.
-pub fn verify_post(
+#[allow(clippy::too_many_arguments)]
+pub fn verify_post_with_verifying_key(
     post_config: PoStConfig,
+    verifying_key: &Bls12VerifyingKey,
     randomness: &ChallengeSeed,
     challenge_count: u64,
     proofs: &[Vec<u8>],
@@ -354,7 +212,7 @@ pub fn verify_post(
     info!("verify_post:start");
 
     let sector_count = replicas.len() as u64;
-    ensure!(sector_count > 0, "Must supply at least one replica");
+    ensure!(sector_count > 0, Error::NoReplicas);
     ensure!(
         winners.len() == proofs.len(),
         "Missmatch between winners and proofs"
@@ -369,11 +227,18 @@ pub fn verify_post(
     let pub_params: compound_proof::PublicParams<election_post::ElectionPoSt<DefaultTreeHasher>> =
         ElectionPoStCompound::setup(&setup_params)?;
 
-    let verifying_key = get_post_verifying_key(post_config)?;
     for (proof, winner) in proofs.iter().zip(winners.iter()) {
+        ensure!(
+            proof.len() == SINGLE_PARTITION_PROOF_LEN,
+            Error::InvalidProofLength {
+                expected: SINGLE_PARTITION_PROOF_LEN,
+                actual: proof.len(),
+            }
+        );
+
         let replica = replicas
             .get(&winner.sector_id)
-            .with_context(|| format!("Missing replica for sector: {}", winner.sector_id))?;
+            .ok_or_else(|| Error::MissingReplica(winner.sector_id))?;
         let comm_r = replica.safe_comm_r()?;
 
         if !election_post::is_valid_sector_challenge_index(
@@ -392,7 +257,7 @@ pub fn verify_post(
             return Ok(false);
         }
 
-        let proof = MultiProof::new_from_reader(None, &proof[..], &verifying_key)?;
+        let proof = MultiProof::new_from_reader(None, &proof[..], verifying_key)?;
         let pub_inputs = election_post::PublicInputs {
             randomness: *randomness,
             comm_r,
@@ -413,3 +278,172 @@ pub fn verify_post(
 
     Ok(true)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ff::Field;
+    use paired::bls12_381::Fr;
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use crate::TEST_SEED;
+
+    // Each candidate wins with a probability of 5 * 100 / (1000 * 10) = 1 / 20, that is if its
+    // ticket is below 2^256 / 20.
+    const PARAMS: WinnerTargetParams = WinnerTargetParams {
+        power: 100,
+        total_power: 1000,
+        challenge_count: 10,
+        expected_winners: 5,
+        max_winners: None,
+    };
+
+    fn ticket(first: u8, rest: u8) -> Ticket {
+        let mut ticket = [rest; 32];
+        ticket[0] = first;
+        ticket
+    }
+
+    fn candidate(sector_id: u64, sector_challenge_index: u64, partial_ticket: Fr) -> Candidate {
+        Candidate {
+            sector_id: sector_id.into(),
+            partial_ticket,
+            ticket: election_post::finalize_ticket(&partial_ticket),
+            sector_challenge_index,
+        }
+    }
+
+    #[test]
+    fn test_winning_ticket_vectors() {
+        let vectors = [
+            (ticket(0x00, 0x00), true),
+            // floor(2^256 / 20) = 0x0ccc..cc, the largest winning ticket
+            (ticket(0x0c, 0xcc), true),
+            (
+                {
+                    let mut t = ticket(0x0c, 0xcc);
+                    t[31] = 0xcd;
+                    t
+                },
+                false,
+            ),
+            (ticket(0x0d, 0x00), false),
+            (ticket(0xff, 0xff), false),
+        ];
+
+        for (ticket, wins) in vectors.iter() {
+            assert_eq!(
+                is_winning_ticket(ticket, &PARAMS).unwrap(),
+                *wins,
+                "{}",
+                hex::encode(ticket)
+            );
+        }
+
+        // all of the power and one expected winner per challenge: every ticket wins
+        let all = WinnerTargetParams {
+            power: 1000,
+            expected_winners: 10,
+            ..PARAMS
+        };
+        assert!(is_winning_ticket(&ticket(0xff, 0xff), &all).unwrap());
+
+        // no power: no ticket wins
+        let none = WinnerTargetParams { power: 0, ..PARAMS };
+        assert!(!is_winning_ticket(&ticket(0x00, 0x00), &none).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_target_params() {
+        let t = ticket(0, 0);
+
+        for params in &[
+            WinnerTargetParams {
+                total_power: 0,
+                ..PARAMS
+            },
+            WinnerTargetParams {
+                power: 1001,
+                ..PARAMS
+            },
+            WinnerTargetParams {
+                challenge_count: 0,
+                ..PARAMS
+            },
+        ] {
+            assert!(is_winning_ticket(&t, params).is_err());
+            assert!(select_winners(&[], params).is_err());
+        }
+    }
+
+    #[test]
+    fn test_select_winners_is_deterministic() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+
+        let mut candidates: Vec<_> = (0..200)
+            .map(|i| candidate(i % 7, i, Fr::random(rng)))
+            .collect();
+        // the same ticket for two challenges of one sector, and for two sectors
+        let tied = Fr::zero();
+        candidates.push(candidate(3, 500, tied));
+        candidates.push(candidate(3, 400, tied));
+        candidates.push(candidate(1, 600, tied));
+
+        let all = WinnerTargetParams {
+            power: 1000,
+            expected_winners: 10,
+            ..PARAMS
+        };
+        let winners = select_winners(&candidates, &all).unwrap();
+        assert_eq!(winners.len(), candidates.len());
+
+        let tied_winners: Vec<_> = winners
+            .iter()
+            .filter(|w| w.partial_ticket == tied)
+            .map(|w| (u64::from(w.sector_id), w.sector_challenge_index))
+            .collect();
+        assert_eq!(tied_winners, vec![(1, 600), (3, 400), (3, 500)]);
+
+        for pair in winners.windows(2) {
+            assert!(pair[0].ticket <= pair[1].ticket);
+        }
+
+        // the order of the candidates does not matter
+        let mut reversed = candidates.clone();
+        reversed.reverse();
+        let winners = select_winners(&candidates, &PARAMS).unwrap();
+        let reversed_winners = select_winners(&reversed, &PARAMS).unwrap();
+        assert!(!winners.is_empty() && winners.len() < candidates.len());
+        assert_eq!(
+            winners.iter().map(|w| w.ticket).collect::<Vec<_>>(),
+            reversed_winners
+                .iter()
+                .map(|w| w.ticket)
+                .collect::<Vec<_>>()
+        );
+        for winner in &winners {
+            assert!(is_winning_ticket(&winner.ticket, &PARAMS).unwrap());
+        }
+
+        // max_winners keeps the lowest tickets
+        let capped = WinnerTargetParams {
+            max_winners: Some(2),
+            ..PARAMS
+        };
+        let capped_winners = select_winners(&reversed, &capped).unwrap();
+        assert_eq!(
+            capped_winners.iter().map(|w| w.ticket).collect::<Vec<_>>(),
+            winners.iter().take(2).map(|w| w.ticket).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_select_winners_rejects_forged_tickets() {
+        let mut forged = candidate(1, 0, Fr::one());
+        forged.ticket = [0; 32];
+
+        assert!(select_winners(&[forged], &PARAMS).is_err());
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Result};
+use merkletree::store::StoreConfig;
+use storage_proofs::election_post;
+use storage_proofs::sector::*;
+use storage_proofs::stacked::CacheKey;
+use storage_proofs::util::NODE_SIZE;
+
+use crate::error::Error;
+use crate::parameters::post_public_params;
+use crate::types::{ChallengeSeed, PoStConfig};
+
+/// A range of bytes in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteRange {
+    pub offset: u64,
+    pub len: u64,
+}
+
+impl ByteRange {
+    pub fn end(&self) -> u64 {
+        self.offset + self.len
+    }
+}
+
+/// The reads needed for one challenged range of leaves of a sector. Only the `tree-r-last` file
+/// of the sector is read: its leaves hash the replica id, the node index and the expansion
+/// parents of the node, so they are not the replica nodes, and the replica itself is not read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeafChallengePlan {
+    /// Index of the first challenged leaf.
+    pub leaf: u64,
+    /// Number of challenged leaves, starting at `leaf`.
+    pub leaves: u64,
+    /// The challenged leaves in the `tree-r-last` file, read by `generate_candidates`.
+    pub tree_r_last_range: ByteRange,
+    /// The nodes of the `tree-r-last` file read by `generate_post` for the inclusion proofs of
+    /// the challenged leaves, that is their siblings on every level and the root. These are only
+    /// read if the candidate wins.
+    pub tree_r_last_path_ranges: Vec<ByteRange>,
+}
+
+/// The reads needed for one sector challenge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectorChallengePlan {
+    pub sector_challenge_index: u64,
+    pub sector_id: SectorId,
+    pub leaf_challenges: Vec<LeafChallengePlan>,
+}
+
+/// Which sectors and leaves an election PoSt challenges for some randomness, and where they are
+/// stored. Sectors may be challenged more than once.
+///
+/// Besides the ranges planned here, proving reads the whole `p_aux` file in the cache directory
+/// of each challenged sector (see `PrivateReplicaInfo::new`). The replicas are not read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoStPlan {
+    pub sector_challenges: Vec<SectorChallengePlan>,
+}
+
+impl PoStPlan {
+    /// The distinct challenged sectors.
+    pub fn sectors(&self) -> OrderedSectorSet {
+        self.sector_challenges
+            .iter()
+            .map(|challenge| challenge.sector_id)
+            .collect()
+    }
+
+    /// The merged ranges of the `tree-r-last` file of each challenged sector read to generate
+    /// the candidates and, with `include_paths`, to prove them.
+    pub fn tree_r_last_reads(&self, include_paths: bool) -> BTreeMap<SectorId, Vec<ByteRange>> {
+        self.reads(|leaf_challenge| {
+            let mut ranges = vec![leaf_challenge.tree_r_last_range];
+            if include_paths {
+                ranges.extend(leaf_challenge.tree_r_last_path_ranges.iter().copied());
+            }
+            ranges
+        })
+    }
+
+    fn reads<F>(&self, ranges: F) -> BTreeMap<SectorId, Vec<ByteRange>>
+    where
+        F: Fn(&LeafChallengePlan) -> Vec<ByteRange>,
+    {
+        let mut reads: BTreeMap<SectorId, Vec<ByteRange>> = BTreeMap::new();
+        for sector_challenge in &self.sector_challenges {
+            let sector_reads = reads.entry(sector_challenge.sector_id).or_default();
+            for leaf_challenge in &sector_challenge.leaf_challenges {
+                sector_reads.extend(ranges(leaf_challenge));
+            }
+        }
+
+        reads
+            .into_iter()
+            .map(|(sector_id, ranges)| (sector_id, merge_ranges(ranges)))
+            .collect()
+    }
+}
+
+/// Sorts `ranges` and merges those which overlap or are adjacent.
+pub fn merge_ranges(mut ranges: Vec<ByteRange>) -> Vec<ByteRange> {
+    ranges.sort();
+
+    let mut merged: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.offset <= last.end() => {
+                last.len = std::cmp::max(last.end(), range.end()) - last.offset;
+            }
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+}
+
+/// The path of the `tree-r-last` file of a replica, given its cache directory.
+pub fn tree_r_last_path<P: AsRef<Path>>(cache_dir: P) -> PathBuf {
+    StoreConfig::data_path(
+        &cache_dir.as_ref().to_path_buf(),
+        &CacheKey::CommRLastTree.to_string(),
+    )
+}
+
+/// Plans the reads of an election PoSt, without reading anything. The challenges are the same as
+/// those of `generate_candidates` for the same arguments.
+///
+/// # Arguments
+///
+/// * `post_config` - post config that contains the sector size of each sector.
+/// * `randomness` - randomness used to generate sector challenges.
+/// * `challenge_count` - the number sector challenges in this post.
+/// * `sectors` - the sectors being proven.
+pub fn plan_post(
+    post_config: PoStConfig,
+    randomness: &ChallengeSeed,
+    challenge_count: u64,
+    sectors: &OrderedSectorSet,
+) -> Result<PoStPlan> {
+    ensure!(!sectors.is_empty(), Error::NoReplicas);
+
+    let pub_params = post_public_params(post_config)?;
+    let nodes = pub_params.sector_size / NODE_SIZE as u64;
+    ensure!(
+        nodes.is_power_of_two(),
+        "sector size {} is not a power of two",
+        pub_params.sector_size
+    );
+
+    let challenged_sectors =
+        election_post::generate_sector_challenges(randomness, challenge_count, sectors)?;
+
+    let sector_challenges = challenged_sectors
+        .into_iter()
+        .enumerate()
+        .map(|(sector_challenge_index, sector_id)| {
+            let sector_challenge_index = sector_challenge_index as u64;
+            let leaves = pub_params.challenged_nodes as u64;
+
+            let leaf_challenges = election_post::generate_leaf_challenges(
+                &pub_params,
+                randomness,
+                sector_challenge_index,
+                pub_params.challenge_count,
+            )?
+            .into_iter()
+            .map(|leaf| {
+                let range = ByteRange {
+                    offset: leaf * NODE_SIZE as u64,
+                    len: leaves * NODE_SIZE as u64,
+                };
+                let tree_r_last_path_ranges = merge_ranges(
+                    (leaf..leaf + leaves)
+                        .flat_map(|leaf| tree_path_ranges(nodes, leaf))
+                        .collect(),
+                );
+
+                LeafChallengePlan {
+                    leaf,
+                    leaves,
+                    tree_r_last_range: range,
+                    tree_r_last_path_ranges,
+                }
+            })
+            .collect();
+
+            Ok(SectorChallengePlan {
+                sector_challenge_index,
+                sector_id,
+                leaf_challenges,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(PoStPlan { sector_challenges })
+}
+
+/// The nodes read for the inclusion proof of `leaf` in a tree over `nodes` leaves, stored level
+/// by level starting with the leaves: the sibling on every level below the root, and the root.
+fn tree_path_ranges(nodes: u64, leaf: u64) -> Vec<ByteRange> {
+    let node = |index: u64| ByteRange {
+        offset: index * NODE_SIZE as u64,
+        len: NODE_SIZE as u64,
+    };
+
+    let mut ranges = Vec::new();
+    let mut level_start = 0;
+    let mut level_nodes = nodes;
+    let mut index = leaf;
+    while level_nodes > 1 {
+        ranges.push(node(level_start + (index ^ 1)));
+
+        level_start += level_nodes;
+        level_nodes /= 2;
+        index /= 2;
+    }
+    ranges.push(node(level_start));
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ff::Field;
+    use paired::bls12_381::{Bls12, Fr};
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+    use storage_proofs::drgraph::{new_seed, BucketGraph, Graph, BASE_DEGREE};
+    use storage_proofs::fr32::fr_into_bytes;
+    use storage_proofs::hasher::PedersenHasher;
+    use storage_proofs::merkle::MerkleProof;
+
+    use crate::constants::SECTOR_SIZE_ONE_KIB;
+    use crate::types::SectorSize;
+    use crate::TEST_SEED;
+
+    fn range(offset: u64, len: u64) -> ByteRange {
+        ByteRange { offset, len }
+    }
+
+    #[test]
+    fn test_merge_ranges() {
+        assert_eq!(
+            merge_ranges(vec![
+                range(64, 32),
+                range(0, 32),
+                range(32, 16),
+                range(200, 10),
+                range(100, 50),
+                range(120, 10),
+            ]),
+            vec![range(0, 96), range(100, 50), range(200, 10)]
+        );
+        assert!(merge_ranges(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_plan_matches_challenges() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+
+        let post_config = PoStConfig::new(SectorSize(SECTOR_SIZE_ONE_KIB)).unwrap();
+        let randomness: [u8; 32] = rng.gen();
+        let sectors: OrderedSectorSet = vec![3.into(), 10.into(), 42.into()].into_iter().collect();
+
+        let plan = plan_post(post_config, &randomness, 5, &sectors).unwrap();
+        let pub_params = post_public_params(post_config).unwrap();
+        let nodes = SECTOR_SIZE_ONE_KIB / NODE_SIZE as u64;
+        let tree_r_last_size = (2 * nodes - 1) * NODE_SIZE as u64;
+
+        let challenged_sectors =
+            election_post::generate_sector_challenges(&randomness, 5, &sectors).unwrap();
+        assert_eq!(plan.sector_challenges.len(), 5);
+        assert!(plan.sectors().is_subset(&sectors));
+
+        // the leaves of tree-r-last read by `generate_candidates`, and the nodes of their
+        // inclusion proofs read by `generate_post`
+        let mut leaf_reads: BTreeMap<SectorId, Vec<ByteRange>> = BTreeMap::new();
+        let mut path_reads: BTreeMap<SectorId, Vec<ByteRange>> = BTreeMap::new();
+        for (n, sector_id) in challenged_sectors.iter().enumerate() {
+            for c in 0..pub_params.challenge_count {
+                let start = election_post::generate_leaf_challenge(
+                    &pub_params,
+                    &randomness,
+                    n as u64,
+                    c as u64,
+                )
+                .unwrap();
+                let end = start + pub_params.challenged_nodes as u64;
+
+                leaf_reads
+                    .entry(*sector_id)
+                    .or_default()
+                    .push(range(start * 32, (end - start) * 32));
+                path_reads
+                    .entry(*sector_id)
+                    .or_default()
+                    .extend((start..end).flat_map(|leaf| tree_path_ranges(nodes, leaf)));
+            }
+        }
+
+        for (n, (challenge, sector_id)) in plan
+            .sector_challenges
+            .iter()
+            .zip(challenged_sectors.iter())
+            .enumerate()
+        {
+            assert_eq!(challenge.sector_challenge_index, n as u64);
+            assert_eq!(challenge.sector_id, *sector_id);
+        }
+
+        let expected_leaf_reads: BTreeMap<_, _> = leaf_reads
+            .iter()
+            .map(|(sector_id, ranges)| (*sector_id, merge_ranges(ranges.clone())))
+            .collect();
+        assert_eq!(plan.tree_r_last_reads(false), expected_leaf_reads);
+
+        let expected_reads: BTreeMap<_, _> = leaf_reads
+            .into_iter()
+            .map(|(sector_id, mut ranges)| {
+                ranges.extend(path_reads.remove(&sector_id).unwrap());
+                (sector_id, merge_ranges(ranges))
+            })
+            .collect();
+        assert_eq!(plan.tree_r_last_reads(true), expected_reads);
+
+        for reads in expected_reads.values() {
+            // the root is read, nothing past the end of the file
+            assert_eq!(reads.last().unwrap().end(), tree_r_last_size);
+        }
+        for reads in expected_leaf_reads.values() {
+            // the leaves are the first level of the file
+            assert!(reads.last().unwrap().end() <= SECTOR_SIZE_ONE_KIB);
+        }
+    }
+
+    #[test]
+    fn test_tree_path_ranges_match_inclusion_proofs() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+
+        let leaves = 32;
+        let data: Vec<u8> = (0..leaves)
+            .flat_map(|_| fr_into_bytes::<Bls12>(&Fr::random(rng)))
+            .collect();
+        let graph = BucketGraph::<PedersenHasher>::new(leaves, BASE_DEGREE, 0, new_seed()).unwrap();
+        let tree = graph.merkle_tree(data.as_slice()).unwrap();
+
+        for leaf in &[0, 1, 13, 31] {
+            let proof =
+                MerkleProof::<PedersenHasher>::new_from_proof(&tree.gen_proof(*leaf).unwrap());
+            let ranges = tree_path_ranges(leaves as u64, *leaf as u64);
+            let nodes: Vec<_> = ranges
+                .iter()
+                .map(|r| {
+                    tree.read_at((r.offset / NODE_SIZE as u64) as usize)
+                        .unwrap()
+                })
+                .collect();
+
+            let (root, siblings) = nodes.split_last().unwrap();
+            assert_eq!(root, proof.root());
+            assert_eq!(
+                siblings.to_vec(),
+                proof
+                    .path()
+                    .iter()
+                    .map(|(hash, _)| *hash)
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+}
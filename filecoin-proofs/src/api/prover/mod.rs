@@ -0,0 +1,1081 @@
+//! Sealing, unsealing, piece preprocessing and PoSt generation. Only built with the `prover`
+//! feature.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use merkletree::store::{StoreConfig, DEFAULT_CACHED_ABOVE_BASE_LAYER};
+use storage_proofs::drgraph::DefaultTreeHasher;
+use storage_proofs::hasher::Hasher;
+use storage_proofs::pieces::generate_piece_commitment_bytes_from_source;
+use storage_proofs::sector::SectorId;
+use storage_proofs::stacked::{generate_replica_id, CacheKey, StackedDrg};
+use tempfile::tempfile;
+
+use crate::api::util::as_safe_commitment;
+use crate::constants::{
+    DefaultPieceHasher,
+    MINIMUM_RESERVED_BYTES_FOR_PIECE_IN_FULLY_ALIGNED_SECTOR as MINIMUM_PIECE_SIZE,
+};
+use crate::error::{io_error, Error};
+use crate::fr32::{write_padded, write_unpadded};
+use crate::parameters::public_params;
+use crate::pieces::get_aligned_source;
+use crate::types::{
+    Commitment, PaddedBytesAmount, PieceInfo, PoRepConfig, PoRepProofPartitions, ProverId, Ticket,
+    UnpaddedByteIndex, UnpaddedBytesAmount,
+};
+
+mod post;
+mod seal;
+mod window_post;
+
+pub use self::post::*;
+pub use self::seal::*;
+pub use self::window_post::*;
+
+/// Unseals the sector at `sealed_path` and returns the bytes for a piece
+/// whose first (unpadded) byte begins at `offset` and ends at `offset` plus
+/// `num_bytes`, inclusive. Note that the entire sector is unsealed each time
+/// this function is called.
+///
+/// # Arguments
+///
+/// * `porep_config` - porep configuration containing the sector size.
+/// * `cache_path` - path to the directory in which the sector data's Merkle Tree is written.
+/// * `sealed_path` - path to the sealed sector file that we will unseal and read a byte range.
+/// * `output_path` - path to a file that we will write the requested byte range to.
+/// * `prover_id` - the prover-id that sealed the sector.
+/// * `sector_id` - the sector-id of the sealed sector.
+/// * `comm_d` - the commitment to the sector's data.
+/// * `ticket` - the ticket that was used to generate the sector's replica-id.
+/// * `offset` - the byte index in the unsealed sector of the first byte that we want to read.
+/// * `num_bytes` - the number of bytes that we want to read.
+#[allow(clippy::too_many_arguments)]
+pub fn get_unsealed_range<T: Into<PathBuf> + AsRef<Path>>(
+    porep_config: PoRepConfig,
+    cache_path: T,
+    sealed_path: T,
+    output_path: T,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    comm_d: Commitment,
+    ticket: Ticket,
+    offset: UnpaddedByteIndex,
+    num_bytes: UnpaddedBytesAmount,
+) -> Result<UnpaddedBytesAmount> {
+    ensure!(comm_d != [0; 32], Error::ZeroCommitment("comm_d"));
+
+    let comm_d =
+        as_safe_commitment::<<DefaultPieceHasher as Hasher>::Domain, _>(&comm_d, "comm_d")?;
+
+    let replica_id =
+        generate_replica_id::<DefaultTreeHasher, _>(&prover_id, sector_id.into(), &ticket, comm_d);
+
+    let f_in = File::open(&sealed_path).map_err(io_error(&sealed_path))?;
+    let mut data = Vec::new();
+    f_in.take(u64::from(PaddedBytesAmount::from(porep_config)))
+        .read_to_end(&mut data)
+        .map_err(io_error(&sealed_path))?;
+
+    let f_out = File::create(&output_path).map_err(io_error(&output_path))?;
+    let mut buf_writer = BufWriter::new(f_out);
+
+    // MT for original data is always named tree-d, and it will be
+    // referenced later in the process as such.
+    let config = StoreConfig::new(
+        cache_path,
+        CacheKey::CommDTree.to_string(),
+        DEFAULT_CACHED_ABOVE_BASE_LAYER,
+    );
+    let pp = public_params(
+        PaddedBytesAmount::from(porep_config),
+        usize::from(PoRepProofPartitions::from(porep_config)),
+        porep_config.params,
+    )?;
+
+    let offset_padded: PaddedBytesAmount = UnpaddedBytesAmount::from(offset).into();
+    let num_bytes_padded: PaddedBytesAmount = num_bytes.into();
+
+    let unsealed = StackedDrg::<DefaultTreeHasher, DefaultPieceHasher>::extract_range(
+        &pp,
+        &replica_id,
+        &data,
+        Some(config),
+        offset_padded.into(),
+        num_bytes_padded.into(),
+    )?;
+
+    // If the call to `extract_range` was successful, the `unsealed` vector must
+    // have a length which equals `num_bytes_padded`. The byte at its 0-index
+    // byte will be the the byte at index `offset_padded` in the sealed sector.
+    let written = write_unpadded(&unsealed, &mut buf_writer, 0, num_bytes.into())
+        .map_err(io_error(&output_path))?;
+
+    Ok(UnpaddedBytesAmount(written as u64))
+}
+
+/// Generates a piece commitment for the provided byte source. Returns an error
+/// if the byte source produced more than `piece_size` bytes.
+///
+/// # Arguments
+///
+/// * `source` - a readable source of unprocessed piece bytes. The piece's commitment will be
+/// generated for the bytes read from the source plus any added padding.
+/// * `piece_size` - the number of unpadded user-bytes which can be read from source before EOF.
+pub fn generate_piece_commitment<T: std::io::Read>(
+    source: T,
+    piece_size: UnpaddedBytesAmount,
+) -> Result<PieceInfo> {
+    ensure_piece_size(piece_size)?;
+
+    let mut temp_piece_file = tempfile()?;
+
+    // send the source through the preprocessor, writing output to temp file
+    let n = UnpaddedBytesAmount(
+        write_padded(source, &temp_piece_file).context("failed to write and preprocess bytes")?
+            as u64,
+    );
+
+    ensure!(
+        n == piece_size,
+        Error::PieceSizeMismatch {
+            expected: piece_size.into(),
+            actual: n.into(),
+        }
+    );
+
+    temp_piece_file
+        .seek(SeekFrom::Start(0))
+        .with_context(|| format!("could not seek in temp_piece_file={:?}", temp_piece_file))?;
+
+    let commitment = generate_piece_commitment_bytes_from_source::<DefaultPieceHasher>(
+        &mut temp_piece_file,
+        PaddedBytesAmount::from(n).into(),
+    )?;
+
+    PieceInfo::new(commitment, piece_size)
+}
+
+/// Computes a NUL-byte prefix and/or suffix for `source` using the provided
+/// `piece_lengths` and `piece_size` (such that the `source`, after
+/// preprocessing, will occupy a subtree of a merkle tree built using the bytes
+/// from `target`), runs the resultant byte stream through the preprocessor,
+/// and writes the result to `target`. Returns a tuple containing the number of
+/// bytes written to `target` (`source` plus alignment) and the commitment.
+///
+/// WARNING: Depending on the ordering and size of the pieces in
+/// `piece_lengths`, this function could write a prefix of NUL bytes which
+/// wastes ($SIZESECTORSIZE/2)-$MINIMUM_PIECE_SIZE space. This function will be
+/// deprecated in favor of `write_and_preprocess`, and miners will be prevented
+/// from sealing sectors containing more than $TOOMUCH alignment bytes.
+///
+/// # Arguments
+///
+/// * `source` - a readable source of unprocessed piece bytes.
+/// * `target` - a writer where we will write the processed piece bytes.
+/// * `piece_size` - the number of unpadded user-bytes which can be read from source before EOF.
+/// * `piece_lengths` - the number of bytes for each previous piece in the sector.
+pub fn add_piece<R, W>(
+    source: R,
+    target: W,
+    piece_size: UnpaddedBytesAmount,
+    piece_lengths: &[UnpaddedBytesAmount],
+) -> Result<(UnpaddedBytesAmount, Commitment)>
+where
+    R: Read,
+    W: Read + Write + Seek,
+{
+    ensure_piece_size(piece_size)?;
+
+    let (aligned_source_size, alignment, aligned_source) =
+        get_aligned_source(source, &piece_lengths, piece_size);
+
+    // allows us to tee the source byte stream
+    let (mut pipe_r, pipe_w) = os_pipe::pipe().context("failed to create pipe")?;
+
+    // all bytes read from the TeeReader are written to its writer, no bytes
+    // will be read from the TeeReader before they are written to its writer
+    let tee_r = tee::TeeReader::new(aligned_source, pipe_w);
+
+    // reads from tee_r block until the tee's source bytes can be written to its
+    // writer, so to prevent write_padded from blocking indefinitely, we need
+    // to spin up a separate thread (to read from the pipe which receives writes
+    // from the TeeReader)
+    let t_handle = std::thread::spawn(move || {
+        // discard n left-alignment bytes
+        let n = alignment.left_bytes.into();
+        io::copy(&mut pipe_r.by_ref().take(n), &mut io::sink())
+            .context("failed to skip alignment bytes")?;
+
+        // generate commitment for piece bytes
+        let result =
+            generate_piece_commitment(&mut pipe_r.by_ref().take(piece_size.into()), piece_size);
+
+        // drain the remaining bytes (all alignment) from the reader
+        std::io::copy(&mut pipe_r.by_ref(), &mut io::sink())
+            .context("failed to drain reader")
+            .and_then(|_| result)
+    });
+
+    // send the source through the preprocessor, writing output to target
+    let write_rslt = write_padded(tee_r, target).context("failed to write and preprocess bytes");
+
+    // block until piece commitment-generating thread returns
+    let join_rslt = t_handle
+        .join()
+        .map_err(|err| anyhow!("join piece commitment-generating thread failed: {:?}", err));
+
+    match (write_rslt, join_rslt) {
+        (Ok(n), Ok(Ok(r))) => {
+            let n = UnpaddedBytesAmount(n as u64);
+
+            ensure!(
+                aligned_source_size == n,
+                Error::PieceSizeMismatch {
+                    expected: aligned_source_size.into(),
+                    actual: n.into(),
+                }
+            );
+
+            Ok((n, r.commitment))
+        }
+        (Ok(n), Ok(Err(err))) => Err(err.context(format!(
+            "wrote {:?} to target but then failed to generate piece commitment",
+            n
+        ))),
+        (Ok(n), Err(err)) => {
+            let e = anyhow!(
+                "wrote {:?} to target but then failed to generate piece commitment: {:?}",
+                n,
+                err
+            );
+            Err(e)
+        }
+        (Err(err), _) => {
+            let e = anyhow!("failed to write and preprocess: {:?}", err);
+            Err(e)
+        }
+    }
+}
+
+fn ensure_piece_size(piece_size: UnpaddedBytesAmount) -> Result<()> {
+    ensure!(
+        piece_size >= UnpaddedBytesAmount(MINIMUM_PIECE_SIZE),
+        "Piece must be at least {} bytes",
+        MINIMUM_PIECE_SIZE
+    );
+
+    let padded_piece_size: PaddedBytesAmount = piece_size.into();
+    ensure!(
+        u64::from(padded_piece_size).is_power_of_two(),
+        "Bit-padded piece size must be a power of 2 ({:?})",
+        padded_piece_size,
+    );
+
+    Ok(())
+}
+
+/// Writes bytes from `source` to `target`, adding bit-padding ("preprocessing")
+/// as needed. Returns a tuple containing the number of bytes written to
+/// `target` and the commitment.
+///
+/// WARNING: This function neither prepends nor appends alignment bytes to the
+/// `target`; it is the caller's responsibility to ensure properly sized
+/// and ordered writes to `target` such that `source`-bytes occupy whole
+/// subtrees of the final merkle tree built over `target`.
+///
+/// # Arguments
+///
+/// * `source` - a readable source of unprocessed piece bytes.
+/// * `target` - a writer where we will write the processed piece bytes.
+/// * `piece_size` - the number of unpadded user-bytes which can be read from source before EOF.
+pub fn write_and_preprocess<R, W>(
+    source: R,
+    target: W,
+    piece_size: UnpaddedBytesAmount,
+) -> Result<(UnpaddedBytesAmount, Commitment)>
+where
+    R: Read,
+    W: Read + Write + Seek,
+{
+    add_piece(source, target, piece_size, Default::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+    use std::io::Write;
+    use std::sync::Once;
+
+    use ff::Field;
+    use paired::bls12_381::{Bls12, Fr};
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+    use storage_proofs::cancellation::{is_cancelled, CancellationToken};
+    use storage_proofs::election_post::Candidate;
+    use storage_proofs::fr32::bytes_into_fr;
+    use storage_proofs::progress::{Progress, RunOptions};
+    use storage_proofs::sector::OrderedSectorSet;
+    use storage_proofs::util::NODE_SIZE;
+    use tempfile::NamedTempFile;
+
+    use crate::api::{
+        cc_piece_infos, compute_comm_d, verify_post, verify_seal, verify_window_post,
+        PublicReplicaInfo,
+    };
+    use crate::constants::{
+        DEFAULT_POREP_PROOF_PARTITIONS, SECTOR_SIZE_ONE_KIB, SINGLE_PARTITION_PROOF_LEN,
+    };
+    use crate::types::{
+        PoStConfig, ProofParameters, SealPreCommitSector, SectorSize, WindowPoStConfig,
+    };
+
+    static INIT_LOGGER: Once = Once::new();
+    fn init_logger() {
+        INIT_LOGGER.call_once(|| {
+            fil_logger::init();
+        });
+    }
+
+    /// Writes `piece_bytes` as the only piece of a new staged sector file.
+    fn stage_piece(piece_bytes: &[u8]) -> Result<(NamedTempFile, Vec<PieceInfo>)> {
+        let piece_size = UnpaddedBytesAmount(piece_bytes.len() as u64);
+        let mut staged_sector_file = NamedTempFile::new()?;
+        let (_, comm_p) = add_piece(
+            &mut &piece_bytes[..],
+            &mut staged_sector_file,
+            piece_size,
+            &[],
+        )?;
+
+        Ok((
+            staged_sector_file,
+            vec![PieceInfo::new(comm_p, piece_size)?],
+        ))
+    }
+
+    /// Like `stage_piece`, with a piece of `piece_size` bytes drawn from `rng`.
+    fn stage_random_piece<R: Rng>(
+        rng: &mut R,
+        piece_size: UnpaddedBytesAmount,
+    ) -> Result<(NamedTempFile, Vec<PieceInfo>)> {
+        let piece_bytes: Vec<u8> = (0..piece_size.0).map(|_| rng.gen()).collect();
+        stage_piece(&piece_bytes)
+    }
+
+    #[test]
+    fn test_verify_seal_fr32_validation() {
+        let convertible_to_fr_bytes = [0; 32];
+        let out = bytes_into_fr::<Bls12>(&convertible_to_fr_bytes);
+        assert!(out.is_ok(), "tripwire");
+
+        let not_convertible_to_fr_bytes = [255; 32];
+        let out = bytes_into_fr::<Bls12>(&not_convertible_to_fr_bytes);
+        assert!(out.is_err(), "tripwire");
+
+        {
+            let result = verify_seal(
+                PoRepConfig {
+                    sector_size: SectorSize(SECTOR_SIZE_ONE_KIB),
+                    partitions: PoRepProofPartitions(DEFAULT_POREP_PROOF_PARTITIONS),
+                    params: ProofParameters::default(),
+                },
+                not_convertible_to_fr_bytes,
+                convertible_to_fr_bytes,
+                [0; 32],
+                SectorId::from(0),
+                [0; 32],
+                [0; 32],
+                &[],
+            );
+
+            if let Err(err) = result {
+                let needle = "invalid all zero commitment";
+                let haystack = format!("{}", err);
+
+                assert!(
+                    haystack.contains(needle),
+                    format!("\"{}\" did not contain \"{}\"", haystack, needle)
+                );
+            } else {
+                panic!("should have failed comm_r to Fr32 conversion");
+            }
+        }
+
+        {
+            let result = verify_seal(
+                PoRepConfig {
+                    sector_size: SectorSize(SECTOR_SIZE_ONE_KIB),
+                    partitions: PoRepProofPartitions(DEFAULT_POREP_PROOF_PARTITIONS),
+                    params: ProofParameters::default(),
+                },
+                convertible_to_fr_bytes,
+                not_convertible_to_fr_bytes,
+                [0; 32],
+                SectorId::from(0),
+                [0; 32],
+                [0; 32],
+                &[],
+            );
+
+            if let Err(err) = result {
+                let needle = "invalid all zero commitment";
+                let haystack = format!("{}", err);
+
+                assert!(
+                    haystack.contains(needle),
+                    format!("\"{}\" did not contain \"{}\"", haystack, needle)
+                );
+            } else {
+                panic!("should have failed comm_d to Fr32 conversion");
+            }
+        }
+    }
+
+    #[test]
+    fn test_typed_errors() {
+        let err = PieceInfo::new([0; 32], UnpaddedBytesAmount(127)).unwrap_err();
+        match err.downcast_ref::<Error>() {
+            Some(Error::ZeroCommitment("comm_p")) => {}
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let err = compute_comm_d(
+            PoRepConfig {
+                sector_size: SectorSize(SECTOR_SIZE_ONE_KIB),
+                partitions: PoRepProofPartitions(DEFAULT_POREP_PROOF_PARTITIONS),
+                params: ProofParameters::default(),
+            },
+            &[],
+        )
+        .unwrap_err();
+        match err.downcast_ref::<Error>() {
+            Some(Error::NoPieces) => {}
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        let err =
+            PrivateReplicaInfo::new("replica".into(), [1; 32], cache_dir.path().to_path_buf())
+                .unwrap_err();
+        match err.downcast_ref::<Error>() {
+            Some(Error::MissingCacheArtifact(path)) => {
+                assert_eq!(path, &cache_dir.path().join(CacheKey::PAux.to_string()))
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // the source ends before the piece does
+        let piece_size = UnpaddedBytesAmount(127);
+        for source in &[&[][..], &[1u8; 100][..]] {
+            let err = generate_piece_commitment(*source, piece_size).unwrap_err();
+            match err.downcast_ref::<Error>() {
+                Some(Error::PieceSizeMismatch { expected, actual }) => {
+                    assert_eq!(*expected, 127);
+                    assert_eq!(*actual, source.len() as u64);
+                }
+                other => panic!("unexpected error: {:?}", other),
+            }
+
+            let mut target = std::io::Cursor::new(Vec::new());
+            let err = add_piece(*source, &mut target, piece_size, &[]).unwrap_err();
+            assert!(
+                err.chain()
+                    .any(|cause| match cause.downcast_ref::<Error>() {
+                        Some(Error::PieceSizeMismatch { .. }) => true,
+                        _ => false,
+                    }),
+                "unexpected error: {:?}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_seal_pre_commit_in_place() -> Result<()> {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        // the piece fills half the sector, the staged file is zero padded when sealing
+        let (staged_sector_file, piece_infos) = stage_random_piece(
+            rng,
+            UnpaddedBytesAmount::from(PaddedBytesAmount(SECTOR_SIZE_ONE_KIB / 2)),
+        )?;
+
+        let config = PoRepConfig::new(SectorSize(SECTOR_SIZE_ONE_KIB))?;
+        let prover_id = rng.gen();
+        let ticket = rng.gen();
+        let sector_id = SectorId::from(12);
+
+        let sealed_sector_file = NamedTempFile::new()?;
+        let cache_dir = tempfile::tempdir()?;
+        let output = seal_pre_commit(
+            config,
+            cache_dir.path(),
+            staged_sector_file.path(),
+            sealed_sector_file.path(),
+            prover_id,
+            sector_id,
+            ticket,
+            &piece_infos,
+        )?;
+
+        // the staged data is left untouched
+        assert_eq!(
+            std::fs::metadata(staged_sector_file.path())?.len(),
+            SECTOR_SIZE_ONE_KIB / 2
+        );
+
+        let cache_dir = tempfile::tempdir()?;
+        assert!(seal_pre_commit(
+            config,
+            cache_dir.path(),
+            staged_sector_file.path(),
+            staged_sector_file.path(),
+            prover_id,
+            sector_id,
+            ticket,
+            &piece_infos,
+        )
+        .is_err());
+
+        let output_in_place = seal_pre_commit_in_place(
+            config,
+            cache_dir.path(),
+            staged_sector_file.path(),
+            prover_id,
+            sector_id,
+            ticket,
+            &piece_infos,
+        )?;
+
+        assert_eq!(output.comm_d, output_in_place.comm_d);
+        assert_eq!(output.comm_r, output_in_place.comm_r);
+        assert_eq!(
+            std::fs::read(sealed_sector_file.path())?,
+            std::fs::read(staged_sector_file.path())?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancelled_seal_pre_commit_cleans_up() -> Result<()> {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        let (staged_sector_file, piece_infos) = stage_random_piece(
+            rng,
+            UnpaddedBytesAmount::from(PaddedBytesAmount(SECTOR_SIZE_ONE_KIB)),
+        )?;
+
+        // Cancel as soon as the first layer of a window has been labeled.
+        let cancel = CancellationToken::new();
+        let observer = |progress: Progress| {
+            if let Progress::WindowLayerLabeled { .. } = progress {
+                cancel.cancel();
+            }
+        };
+
+        let sealed_sector_file = NamedTempFile::new()?;
+        let cache_dir = tempfile::tempdir()?;
+        let err = seal_pre_commit_with_options(
+            PoRepConfig::new(SectorSize(SECTOR_SIZE_ONE_KIB))?,
+            cache_dir.path(),
+            staged_sector_file.path(),
+            sealed_sector_file.path(),
+            rng.gen(),
+            SectorId::from(12),
+            rng.gen(),
+            &piece_infos,
+            RunOptions::default()
+                .with_progress(&observer)
+                .with_cancel(&cancel),
+        )
+        .err()
+        .expect("sealing was not cancelled");
+
+        assert!(is_cancelled(&err), "{:?}", err);
+        assert!(cancel.is_cancelled());
+        assert_eq!(std::fs::metadata(sealed_sector_file.path())?.len(), 0);
+        assert_eq!(std::fs::read_dir(cache_dir.path())?.count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_seal_pre_commit_cc() -> Result<()> {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        let config = PoRepConfig::new(SectorSize(SECTOR_SIZE_ONE_KIB))?;
+        let prover_id = rng.gen();
+        let ticket = rng.gen();
+        let sector_id = SectorId::from(12);
+
+        // leftovers in out_path must not end up in the replica
+        let mut cc_sector_file = NamedTempFile::new()?;
+        cc_sector_file.write_all(&[0xff; 100])?;
+
+        let cc_cache_dir = tempfile::tempdir()?;
+        let output = seal_pre_commit_cc(
+            config,
+            cc_cache_dir.path(),
+            cc_sector_file.path(),
+            prover_id,
+            sector_id,
+            ticket,
+        )?;
+
+        // the same sector sealed from an empty staged file
+        let staged_sector_file = NamedTempFile::new()?;
+        let sealed_sector_file = NamedTempFile::new()?;
+        let cache_dir = tempfile::tempdir()?;
+        let expected = seal_pre_commit(
+            config,
+            cache_dir.path(),
+            staged_sector_file.path(),
+            sealed_sector_file.path(),
+            prover_id,
+            sector_id,
+            ticket,
+            &cc_piece_infos(config.sector_size)?,
+        )?;
+
+        assert_eq!(output.comm_d, expected.comm_d);
+        assert_eq!(output.comm_r, expected.comm_r);
+        assert_eq!(
+            std::fs::read(cc_sector_file.path())?,
+            std::fs::read(sealed_sector_file.path())?
+        );
+
+        // only the root of tree_d is written, seal_commit writes the nodes it opens
+        let tree_d_path = |dir: &Path| {
+            StoreConfig::data_path(&dir.to_path_buf(), &CacheKey::CommDTree.to_string())
+        };
+        let cc_tree_d = std::fs::read(tree_d_path(cc_cache_dir.path()))?;
+        let tree_d = std::fs::read(tree_d_path(cache_dir.path()))?;
+        assert_eq!(cc_tree_d.len(), tree_d.len());
+        let root = cc_tree_d.len() - NODE_SIZE;
+        assert_eq!(cc_tree_d[root..], tree_d[root..]);
+        assert!(cc_tree_d[..root].iter().all(|&b| b == 0));
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore]
+    fn test_seal_lifecycle_cc() -> Result<()> {
+        init_logger();
+
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        let config = PoRepConfig::new(SectorSize(SECTOR_SIZE_ONE_KIB))?;
+        let prover_id = rng.gen();
+        let ticket = rng.gen();
+        let seed = rng.gen();
+        let sector_id = SectorId::from(12);
+
+        let cc_sector_file = NamedTempFile::new()?;
+        let cache_dir = tempfile::tempdir()?;
+        let pre_commit_output = seal_pre_commit_cc(
+            config,
+            cache_dir.path(),
+            cc_sector_file.path(),
+            prover_id,
+            sector_id,
+            ticket,
+        )?;
+
+        let comm_d = pre_commit_output.comm_d;
+        let comm_r = pre_commit_output.comm_r;
+        let piece_infos = cc_piece_infos(config.sector_size)?;
+        assert_eq!(comm_d, compute_comm_d(config, &piece_infos)?);
+
+        let commit_output = seal_commit(
+            config,
+            cache_dir.path(),
+            prover_id,
+            sector_id,
+            ticket,
+            seed,
+            pre_commit_output,
+            &piece_infos,
+        )?;
+
+        let verified = verify_seal(
+            config,
+            comm_r,
+            comm_d,
+            prover_id,
+            sector_id,
+            ticket,
+            seed,
+            &commit_output.proof,
+        )?;
+        assert!(verified, "failed to verify valid cc seal");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_seal_pre_commit_many() -> Result<()> {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        let config = PoRepConfig::new(SectorSize(SECTOR_SIZE_ONE_KIB))?;
+        let prover_id = rng.gen();
+
+        // a sector with data and a committed capacity sector
+        let (staged_sector_file, piece_infos) = stage_random_piece(
+            rng,
+            UnpaddedBytesAmount::from(PaddedBytesAmount(SECTOR_SIZE_ONE_KIB)),
+        )?;
+        let empty_sector_file = NamedTempFile::new()?;
+
+        let inputs = vec![
+            (staged_sector_file.path(), piece_infos),
+            (
+                empty_sector_file.path(),
+                cc_piece_infos(config.sector_size)?,
+            ),
+        ];
+
+        let mut dirs = Vec::new();
+        let mut sectors = Vec::new();
+        for (i, (in_path, piece_infos)) in inputs.iter().enumerate() {
+            let cache_dir = tempfile::tempdir()?;
+            let sealed_sector_file = NamedTempFile::new()?;
+            sectors.push(SealPreCommitSector {
+                cache_path: cache_dir.path().to_path_buf(),
+                in_path: in_path.to_path_buf(),
+                out_path: sealed_sector_file.path().to_path_buf(),
+                sector_id: SectorId::from(i as u64),
+                ticket: rng.gen(),
+                piece_infos: piece_infos.clone(),
+            });
+            dirs.push((cache_dir, sealed_sector_file));
+        }
+
+        let outputs = seal_pre_commit_many(config, prover_id, &sectors)?;
+
+        for (sector, output) in sectors.iter().zip(outputs) {
+            let cache_dir = tempfile::tempdir()?;
+            let sealed_sector_file = NamedTempFile::new()?;
+            let expected = seal_pre_commit(
+                config,
+                cache_dir.path(),
+                &sector.in_path,
+                sealed_sector_file.path(),
+                prover_id,
+                sector.sector_id,
+                sector.ticket,
+                &sector.piece_infos,
+            )?;
+
+            assert_eq!(output.comm_d, expected.comm_d);
+            assert_eq!(output.comm_r, expected.comm_r);
+            assert_eq!(
+                std::fs::read(&sector.out_path)?,
+                std::fs::read(sealed_sector_file.path())?
+            );
+            assert!(sector.cache_path.join(CacheKey::TAux.to_string()).exists());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_seal_pre_commit_many_checks_paths_first() -> Result<()> {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        let config = PoRepConfig::new(SectorSize(SECTOR_SIZE_ONE_KIB))?;
+        let dir = tempfile::tempdir()?;
+        let staged_bytes: Vec<u8> = (0..100).map(|_| rng.gen()).collect();
+        std::fs::write(dir.path().join("staged-0"), &staged_bytes)?;
+        std::fs::write(dir.path().join("staged-1"), &staged_bytes)?;
+
+        // the second sector would overwrite the staged data of the first one
+        let sectors: Vec<_> = [("staged-0", "sealed-0"), ("staged-1", "staged-0")]
+            .iter()
+            .enumerate()
+            .map(|(i, (in_path, out_path))| -> Result<_> {
+                let cache_path = dir.path().join(format!("cache-{}", i));
+                std::fs::create_dir(&cache_path)?;
+                Ok(SealPreCommitSector {
+                    cache_path,
+                    in_path: dir.path().join(in_path),
+                    out_path: dir.path().join(out_path),
+                    sector_id: SectorId::from(i as u64),
+                    ticket: rng.gen(),
+                    piece_infos: cc_piece_infos(config.sector_size)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let err = seal_pre_commit_many(config, rng.gen(), &sectors)
+            .expect_err("sealed over the staged data of another sector");
+        assert!(err.to_string().contains("is the in_path of sector"));
+
+        // nothing was opened
+        assert_eq!(std::fs::read(dir.path().join("staged-0"))?, staged_bytes);
+        assert!(!dir.path().join("sealed-0").exists());
+
+        // the staged data of the second sector is larger than a sector
+        let oversized: Vec<u8> = (0..SECTOR_SIZE_ONE_KIB + 1).map(|_| rng.gen()).collect();
+        std::fs::write(dir.path().join("staged-1"), &oversized)?;
+        let mut sectors = sectors;
+        sectors[1].out_path = dir.path().join("sealed-1");
+
+        let err = seal_pre_commit_many(config, rng.gen(), &sectors)
+            .expect_err("sealed an oversized sector");
+        assert!(err.to_string().contains("more than the sector size"));
+        assert!(!dir.path().join("sealed-0").exists());
+        assert!(!dir.path().join("sealed-1").exists());
+
+        // the pieces of the first sector do not match its data, which is only found after its
+        // replica was created and its tree_d written
+        std::fs::write(dir.path().join("staged-1"), &staged_bytes)?;
+        std::fs::write(dir.path().join("sealed-1"), b"existing")?;
+
+        assert!(seal_pre_commit_many(config, rng.gen(), &sectors).is_err());
+        assert!(!dir.path().join("sealed-0").exists());
+        assert_eq!(std::fs::read(dir.path().join("sealed-1"))?, b"existing");
+        let tree_d =
+            StoreConfig::data_path(&sectors[0].cache_path, &CacheKey::CommDTree.to_string());
+        assert!(!tree_d.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore]
+    fn test_verify_post_fr32_validation() {
+        init_logger();
+
+        let not_convertible_to_fr_bytes = [255; 32];
+        let out = bytes_into_fr::<Bls12>(&not_convertible_to_fr_bytes);
+        assert!(out.is_err(), "tripwire");
+        let mut replicas = BTreeMap::new();
+        replicas.insert(
+            1.into(),
+            PublicReplicaInfo::new(not_convertible_to_fr_bytes).unwrap(),
+        );
+        let winner = Candidate {
+            sector_id: 1.into(),
+            partial_ticket: Fr::zero(),
+            ticket: [0; 32],
+            sector_challenge_index: 0,
+        };
+
+        let result = verify_post(
+            PoStConfig {
+                sector_size: SectorSize(SECTOR_SIZE_ONE_KIB),
+                challenge_count: crate::constants::POST_CHALLENGE_COUNT,
+                challenged_nodes: crate::constants::POST_CHALLENGED_NODES,
+            },
+            &[0; 32],
+            1,
+            &[vec![0u8; SINGLE_PARTITION_PROOF_LEN]][..],
+            &replicas,
+            &[winner][..],
+            [0; 32],
+        );
+
+        if let Err(err) = result {
+            let needle = "invalid commitment (comm_r)";
+            let haystack = format!("{}", err);
+
+            assert!(
+                haystack.contains(needle),
+                format!("\"{}\" did not contain \"{}\"", haystack, needle)
+            );
+        } else {
+            panic!("should have failed comm_r to Fr32 conversion");
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn test_window_post_with_faulty_sector() -> Result<()> {
+        init_logger();
+
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        let porep_config = PoRepConfig::new(SectorSize(SECTOR_SIZE_ONE_KIB))?;
+        let window_post_config = WindowPoStConfig {
+            sector_size: SectorSize(SECTOR_SIZE_ONE_KIB),
+            challenge_count: 2,
+            sector_count: 2,
+        };
+        let prover_id = rng.gen();
+
+        let mut dirs = Vec::new();
+        let mut private_replicas = BTreeMap::new();
+        let mut public_replicas = BTreeMap::new();
+        for i in 0..3u64 {
+            let sector_id = SectorId::from(i);
+            let cache_dir = tempfile::tempdir()?;
+            let sealed_sector_file = NamedTempFile::new()?;
+            let output = seal_pre_commit_cc(
+                porep_config,
+                cache_dir.path(),
+                sealed_sector_file.path(),
+                prover_id,
+                sector_id,
+                rng.gen(),
+            )?;
+
+            private_replicas.insert(
+                sector_id,
+                PrivateReplicaInfo::new(
+                    sealed_sector_file.path().to_string_lossy().into_owned(),
+                    output.comm_r,
+                    cache_dir.path().to_path_buf(),
+                )?,
+            );
+            public_replicas.insert(sector_id, PublicReplicaInfo::new(output.comm_r)?);
+            dirs.push((cache_dir, sealed_sector_file));
+        }
+
+        // the trees of the faulty sector are lost, proving must not need them
+        let faults: OrderedSectorSet = vec![SectorId::from(1)].into_iter().collect();
+        for entry in std::fs::read_dir(dirs[1].0.path())? {
+            std::fs::remove_file(entry?.path())?;
+        }
+
+        let randomness: [u8; 32] = rng.gen();
+        let proof =
+            generate_window_post(window_post_config, &randomness, &private_replicas, &faults)?;
+        // the two proven sectors fit in a single partition
+        assert_eq!(proof.len(), SINGLE_PARTITION_PROOF_LEN);
+
+        assert!(verify_window_post(
+            window_post_config,
+            &randomness,
+            &proof,
+            &public_replicas,
+            &faults,
+        )?);
+
+        // the proof does not hold for other faults
+        let other_faults: OrderedSectorSet = vec![SectorId::from(2)].into_iter().collect();
+        assert!(!verify_window_post(
+            window_post_config,
+            &randomness,
+            &proof,
+            &public_replicas,
+            &other_faults,
+        )?);
+
+        // without the fault, three sectors are proven in two partitions
+        assert!(verify_window_post(
+            window_post_config,
+            &randomness,
+            &proof,
+            &public_replicas,
+            &OrderedSectorSet::new(),
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore]
+    fn test_seal_lifecycle() -> Result<()> {
+        init_logger();
+
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        let sector_size = SECTOR_SIZE_ONE_KIB;
+
+        let number_of_bytes_in_piece =
+            UnpaddedBytesAmount::from(PaddedBytesAmount(sector_size.clone()));
+
+        let piece_bytes: Vec<u8> = (0..number_of_bytes_in_piece.0).map(|_| rng.gen()).collect();
+        let (staged_sector_file, piece_infos) = stage_piece(&piece_bytes)?;
+
+        let sealed_sector_file = NamedTempFile::new()?;
+        let mut unseal_file = NamedTempFile::new()?;
+        let config = PoRepConfig {
+            sector_size: SectorSize(sector_size.clone()),
+            partitions: PoRepProofPartitions(DEFAULT_POREP_PROOF_PARTITIONS),
+            params: ProofParameters::default(),
+        };
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        let prover_id = rng.gen();
+        let ticket = rng.gen();
+        let seed = rng.gen();
+        let sector_id = SectorId::from(12);
+
+        let pre_commit_output = seal_pre_commit(
+            config,
+            cache_dir.path(),
+            &staged_sector_file.path(),
+            &sealed_sector_file.path(),
+            prover_id,
+            sector_id,
+            ticket,
+            &piece_infos,
+        )?;
+
+        let comm_d = pre_commit_output.comm_d.clone();
+        let comm_r = pre_commit_output.comm_r.clone();
+
+        let commit_output = seal_commit(
+            config,
+            cache_dir.path(),
+            prover_id,
+            sector_id,
+            ticket,
+            seed,
+            pre_commit_output,
+            &piece_infos,
+        )?;
+
+        let _ = get_unsealed_range(
+            config,
+            cache_dir.path(),
+            &sealed_sector_file.path(),
+            &unseal_file.path(),
+            prover_id,
+            sector_id,
+            comm_d,
+            ticket,
+            UnpaddedByteIndex(508),
+            UnpaddedBytesAmount(508),
+        )?;
+
+        let mut contents = vec![];
+        assert!(
+            unseal_file.read_to_end(&mut contents).is_ok(),
+            "failed to populate buffer with unsealed bytes"
+        );
+        assert_eq!(contents.len(), 508);
+        assert_eq!(&piece_bytes[508..], &contents[..]);
+
+        let computed_comm_d = compute_comm_d(config, &piece_infos)?;
+
+        assert_eq!(
+            comm_d, computed_comm_d,
+            "Computed and expected comm_d don't match."
+        );
+
+        let verified = verify_seal(
+            config,
+            comm_r,
+            comm_d,
+            prover_id,
+            sector_id,
+            ticket,
+            seed,
+            &commit_output.proof,
+        )?;
+        assert!(verified, "failed to verify valid seal");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Result};
+use bincode::deserialize;
+use log::info;
+use merkletree::merkle::{get_merkle_tree_leafs, MerkleTree};
+use merkletree::store::{DiskStore, Store, StoreConfig, DEFAULT_CACHED_ABOVE_BASE_LAYER};
+use rayon::prelude::*;
+use storage_proofs::circuit::election_post::ElectionPoStCompound;
+use storage_proofs::compound_proof::{self, CompoundProof};
+use storage_proofs::drgraph::DefaultTreeHasher;
+use storage_proofs::election_post::{self, Candidate};
+use storage_proofs::hasher::Hasher;
+use storage_proofs::progress::{report_stage, Progress, RunOptions, Stage};
+use storage_proofs::sector::*;
+use storage_proofs::stacked::CacheKey;
+use storage_proofs::thread_pools::{self, Pool};
+
+use crate::api::post::SnarkProof;
+use crate::api::util::as_safe_commitment;
+use crate::caches::get_post_params;
+use crate::error::{cache_artifact_error, io_error, Error};
+use crate::parameters::post_setup_params;
+use crate::types::{
+    ChallengeSeed, Commitment, PersistentAux, PoStConfig, ProverId, SectorSize, Tree,
+};
+
+/// The minimal information required about a replica, in order to be able to generate
+/// a PoSt over it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PrivateReplicaInfo {
+    /// Path to the replica.
+    access: String,
+    /// The replica commitment.
+    comm_r: Commitment,
+    /// Persistent Aux.
+    aux: PersistentAux,
+    /// Contains sector-specific (e.g. merkle trees) assets
+    cache_dir: PathBuf,
+}
+
+impl std::cmp::Ord for PrivateReplicaInfo {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.comm_r.as_ref().cmp(other.comm_r.as_ref())
+    }
+}
+
+impl std::cmp::PartialOrd for PrivateReplicaInfo {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PrivateReplicaInfo {
+    pub fn new(access: String, comm_r: Commitment, cache_dir: PathBuf) -> Result<Self> {
+        ensure!(comm_r != [0; 32], Error::ZeroCommitment("comm_r"));
+
+        let aux = {
+            let mut aux_bytes = vec![];
+            let f_aux_path = cache_dir.join(CacheKey::PAux.to_string());
+            let mut f_aux = File::open(&f_aux_path).map_err(cache_artifact_error(&f_aux_path))?;
+            f_aux
+                .read_to_end(&mut aux_bytes)
+                .map_err(io_error(&f_aux_path))?;
+
+            deserialize(&aux_bytes)
+        }?;
+
+        Ok(PrivateReplicaInfo {
+            access,
+            comm_r,
+            aux,
+            cache_dir,
+        })
+    }
+
+    pub fn cache_dir_path(&self) -> &Path {
+        self.cache_dir.as_path()
+    }
+
+    pub fn safe_comm_r(&self) -> Result<<DefaultTreeHasher as Hasher>::Domain> {
+        as_safe_commitment(&self.comm_r, "comm_r")
+    }
+
+    pub fn safe_comm_c(&self) -> Result<<DefaultTreeHasher as Hasher>::Domain> {
+        Ok(self.aux.comm_c)
+    }
+
+    pub fn safe_comm_q(&self) -> Result<<DefaultTreeHasher as Hasher>::Domain> {
+        Ok(self.aux.comm_q)
+    }
+
+    pub fn safe_comm_r_last(&self) -> Result<<DefaultTreeHasher as Hasher>::Domain> {
+        Ok(self.aux.comm_r_last)
+    }
+
+    /// Generate the merkle tree of this particular replica.
+    pub fn merkle_tree(&self, tree_size: usize, tree_leafs: usize) -> Result<Tree> {
+        let mut config = StoreConfig::new(
+            &self.cache_dir,
+            CacheKey::CommRLastTree.to_string(),
+            DEFAULT_CACHED_ABOVE_BASE_LAYER,
+        );
+        config.size = Some(tree_size);
+        let tree_r_last_store: DiskStore<<DefaultTreeHasher as Hasher>::Domain> =
+            DiskStore::new_from_disk(tree_size, &config)?;
+        let tree_r_last: Tree = MerkleTree::from_data_store(tree_r_last_store, tree_leafs)?;
+
+        Ok(tree_r_last)
+    }
+}
+
+pub(crate) fn get_tree_size(sector_size: SectorSize) -> usize {
+    let sector_size = u64::from(sector_size);
+    let elems = sector_size as usize / std::mem::size_of::<<DefaultTreeHasher as Hasher>::Domain>();
+
+    2 * elems - 1
+}
+
+/// Generates proof-of-spacetime candidates for ElectionPoSt.
+///
+/// # Arguments
+///
+/// * `post_config` - post config that contains the sector size of each sector that we are
+/// generating this post for.
+/// * `randomness` - randomness used to generate sector challenges.
+/// * `challenge_count` - the number sector challenges in this post.
+/// * `replicas` - each sector's sector-id and associated replica info.
+/// * `prover_id` - the prover-id that is generating this post.
+pub fn generate_candidates(
+    post_config: PoStConfig,
+    randomness: &ChallengeSeed,
+    challenge_count: u64,
+    replicas: &BTreeMap<SectorId, PrivateReplicaInfo>,
+    prover_id: ProverId,
+) -> Result<Vec<Candidate>> {
+    generate_candidates_with_options(
+        post_config,
+        randomness,
+        challenge_count,
+        replicas,
+        prover_id,
+        RunOptions::default(),
+    )
+}
+
+/// Same as `generate_candidates`, reporting each loaded replica tree and the candidate generation
+/// as set in `options`. Fails with a cancellation error (see `is_cancelled`) once cancelled
+/// through `options`.
+pub fn generate_candidates_with_options(
+    post_config: PoStConfig,
+    randomness: &ChallengeSeed,
+    challenge_count: u64,
+    replicas: &BTreeMap<SectorId, PrivateReplicaInfo>,
+    prover_id: ProverId,
+    options: RunOptions,
+) -> Result<Vec<Candidate>> {
+    info!("generate_candidates:start");
+    let progress = options.progress();
+
+    let vanilla_params = post_setup_params(post_config);
+    let setup_params = compound_proof::SetupParams {
+        vanilla_params,
+        partitions: None,
+    };
+    let public_params: compound_proof::PublicParams<
+        election_post::ElectionPoSt<DefaultTreeHasher>,
+    > = ElectionPoStCompound::setup(&setup_params)?;
+
+    let sector_count = replicas.len() as u64;
+    ensure!(sector_count > 0, Error::NoReplicas);
+
+    let sectors = replicas.keys().copied().collect();
+
+    let challenged_sectors =
+        election_post::generate_sector_challenges(randomness, challenge_count, &sectors)?;
+
+    // Match the replicas to the challenges, as these are the only ones required.
+    let challenged_replicas: Vec<_> = challenged_sectors
+        .iter()
+        .map(|c| {
+            replicas
+                .get(c)
+                .map(|replica| (c, replica))
+                .ok_or_else(|| Error::MissingReplica(*c))
+        })
+        .collect::<Result<_, _>>()?;
+
+    // Generate merkle trees for the challenged replicas.
+    // Merkle trees should be generated only once, not multiple times if the same sector is challenged
+    // multiple times, so we build a HashMap of trees.
+
+    let mut unique_challenged_replicas = challenged_replicas.clone();
+    unique_challenged_replicas.sort_unstable(); // dedup requires a sorted list
+    unique_challenged_replicas.dedup();
+
+    let tree_size = get_tree_size(post_config.sector_size);
+    let tree_leafs = get_merkle_tree_leafs(tree_size);
+
+    progress.on_progress(Progress::StageStarted {
+        stage: Stage::PostTrees,
+        steps: unique_challenged_replicas.len(),
+    });
+    let trees: BTreeMap<SectorId, Tree> = thread_pools::install(Pool::PostReads, || {
+        unique_challenged_replicas
+            .into_par_iter()
+            .map(|(id, replica)| -> Result<_> {
+                options.check_cancelled()?;
+                let tree = replica.merkle_tree(tree_size, tree_leafs)?;
+                progress.on_progress(Progress::SectorTreeLoaded {
+                    sector_id: *id,
+                    leaves: tree_leafs,
+                });
+
+                Ok((*id, tree))
+            })
+            .collect()
+    })?;
+    progress.on_progress(Progress::StageFinished {
+        stage: Stage::PostTrees,
+    });
+
+    let candidates = report_stage(progress, Stage::PostCandidates, 0, || {
+        thread_pools::install(Pool::PostReads, || {
+            election_post::generate_candidates::<DefaultTreeHasher>(
+                &public_params.vanilla_params,
+                &challenged_sectors,
+                &trees,
+                &prover_id,
+                randomness,
+            )
+        })
+    })?;
+
+    info!("generate_candidates:finish");
+
+    Ok(candidates)
+}
+
+/// Generates a proof-of-spacetime.
+///
+/// # Arguments
+///
+/// * `post_config` - post config that contains the sector size of each sector that we are
+/// generating this post for.
+/// * `randomness` - randomness used to generate sector challenges.
+/// * `replicas` - each sector's sector-id and associated replica info.
+/// * `winners` - a vector containing each winning ticket.
+/// * `prover_id` - the prover-id that is generating this post.
+pub fn generate_post(
+    post_config: PoStConfig,
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PrivateReplicaInfo>,
+    winners: Vec<Candidate>,
+    prover_id: ProverId,
+) -> Result<Vec<SnarkProof>> {
+    generate_post_with_options(
+        post_config,
+        randomness,
+        replicas,
+        winners,
+        prover_id,
+        RunOptions::default(),
+    )
+}
+
+/// Same as `generate_post`, reporting each proven winner as set in `options`. Fails with a
+/// cancellation error (see `is_cancelled`) once cancelled through `options`.
+pub fn generate_post_with_options(
+    post_config: PoStConfig,
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PrivateReplicaInfo>,
+    winners: Vec<Candidate>,
+    prover_id: ProverId,
+    options: RunOptions,
+) -> Result<Vec<SnarkProof>> {
+    info!("generate_post:start");
+    let progress = options.progress();
+
+    let sector_count = replicas.len() as u64;
+    ensure!(sector_count > 0, Error::NoReplicas);
+
+    let vanilla_params = post_setup_params(post_config);
+    let setup_params = compound_proof::SetupParams {
+        vanilla_params,
+        partitions: None,
+    };
+    let pub_params: compound_proof::PublicParams<election_post::ElectionPoSt<DefaultTreeHasher>> =
+        ElectionPoStCompound::setup(&setup_params)?;
+    let groth_params = get_post_params(post_config)?;
+
+    let tree_size = get_tree_size(post_config.sector_size);
+    let tree_leafs = get_merkle_tree_leafs(tree_size);
+
+    let mut proofs = Vec::with_capacity(winners.len());
+
+    let inputs: Vec<_> = winners
+        .par_iter()
+        .map(|winner| {
+            let replica = replicas
+                .get(&winner.sector_id)
+                .ok_or_else(|| Error::MissingReplica(winner.sector_id))?;
+            let tree = replica.merkle_tree(tree_size, tree_leafs)?;
+
+            let comm_r = replica.safe_comm_r()?;
+            let pub_inputs = election_post::PublicInputs {
+                randomness: *randomness,
+                comm_r,
+                sector_id: winner.sector_id,
+                partial_ticket: winner.partial_ticket,
+                sector_challenge_index: winner.sector_challenge_index,
+                prover_id,
+            };
+
+            let comm_c = replica.safe_comm_c()?;
+            let comm_q = replica.safe_comm_q()?;
+            let comm_r_last = replica.safe_comm_r_last()?;
+            let priv_inputs = election_post::PrivateInputs::<DefaultTreeHasher> {
+                tree,
+                comm_c,
+                comm_q,
+                comm_r_last,
+            };
+
+            Ok((pub_inputs, priv_inputs))
+        })
+        .collect::<Result<_>>()?;
+
+    progress.on_progress(Progress::StageStarted {
+        stage: Stage::PostProofs,
+        steps: inputs.len(),
+    });
+    for (pub_inputs, priv_inputs) in &inputs {
+        let proof = ElectionPoStCompound::prove_with_options(
+            &pub_params,
+            &pub_inputs,
+            &priv_inputs,
+            &*groth_params,
+            options.without_progress(),
+        )?;
+        proofs.push(proof.to_vec()?);
+        progress.on_progress(Progress::SectorProven {
+            sector_id: pub_inputs.sector_id,
+        });
+    }
+    progress.on_progress(Progress::StageFinished {
+        stage: Stage::PostProofs,
+    });
+
+    info!("generate_post:finish");
+
+    Ok(proofs)
+}
@@ -0,0 +1,1035 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context, Result};
+use bincode::{deserialize, serialize};
+use log::{info, warn};
+use memmap::{Mmap, MmapMut, MmapOptions};
+use merkletree::store::{DiskStore, Store, StoreConfig, DEFAULT_CACHED_ABOVE_BASE_LAYER};
+use paired::bls12_381::{Bls12, Fr};
+use storage_proofs::cancellation::is_cancelled;
+use storage_proofs::circuit::stacked::StackedCompound;
+use storage_proofs::compound_proof::{self, CompoundProof};
+use storage_proofs::drgraph::{DefaultTreeHasher, Graph};
+use storage_proofs::hasher::{Domain, Hasher};
+use storage_proofs::merkle::{create_merkle_tree, create_merkle_tree_zero_padded, MerkleTree};
+use storage_proofs::progress::{report_stage, Progress, ProgressObserver, RunOptions, Stage};
+use storage_proofs::sector::SectorId;
+use storage_proofs::stacked::{
+    self, generate_replica_id, BatchReplica, CacheKey, StackedDrg, TemporaryAux, TemporaryAuxCache,
+};
+use storage_proofs::util::NODE_SIZE;
+
+use crate::api::util::{as_safe_commitment, commitment_from_fr};
+use crate::api::verify_seal;
+use crate::caches::get_stacked_params;
+use crate::constants::{DefaultPieceHasher, SINGLE_PARTITION_PROOF_LEN};
+use crate::error::{cache_artifact_error, io_error, is_not_found, Error};
+use crate::parameters::{public_params, setup_params};
+use crate::pieces::{cc_piece_infos, ensure_pieces_match, piece_hash};
+use crate::types::{
+    Commitment, PaddedBytesAmount, PieceInfo, PoRepConfig, PoRepProofPartitions, ProverId,
+    SealCommitOutput, SealPreCommitOutput, SealPreCommitSector, Ticket,
+};
+
+type DataTree =
+    MerkleTree<<DefaultPieceHasher as Hasher>::Domain, <DefaultPieceHasher as Hasher>::Function>;
+
+/// Seals the staged sector at `in_path`, writing the resulting replica to `out_path`.
+///
+/// The staged data is read from `in_path` window by window and only the replica is written to
+/// `out_path`, which is created if it does not exist. `in_path` may be shorter than the sector,
+/// in which case it is treated as zero padded. `in_path` and `out_path` must be different files,
+/// see `seal_pre_commit_in_place` for sealing the staged file itself.
+///
+/// # Arguments
+///
+/// * `porep_config` - porep configuration containing the number of bytes in this sector.
+/// * `cache_path` - path to a directory in which the sector data's Merkle Tree can be written.
+/// * `in_path` - the path where the unsealed sector data is read.
+/// * `out_path` - the path where the sealed sector data will be written.
+/// * `prover_id` - the prover-id that is sealing this sector.
+/// * `sector_id` - the sector-id of this sector.
+/// * `ticket` - the ticket that will be used to generate this sector's replica-id.
+/// * `piece_infos` - each piece's info (number of bytes and commitment) in this sector.
+#[allow(clippy::too_many_arguments)]
+pub fn seal_pre_commit<R: AsRef<Path>, T: AsRef<Path>, S: AsRef<Path>>(
+    porep_config: PoRepConfig,
+    cache_path: R,
+    in_path: T,
+    out_path: S,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    piece_infos: &[PieceInfo],
+) -> Result<SealPreCommitOutput> {
+    seal_pre_commit_with_options(
+        porep_config,
+        cache_path,
+        in_path,
+        out_path,
+        prover_id,
+        sector_id,
+        ticket,
+        piece_infos,
+        RunOptions::default(),
+    )
+}
+
+/// Same as `seal_pre_commit`, reporting the progress of each stage and stopping once cancelled as
+/// set in `options`.
+///
+/// A cancelled seal stops at the next window layer or tree and fails with a cancellation error
+/// (see `is_cancelled`). The labels and trees it has written to `cache_path` are removed and
+/// `out_path` is truncated, so no partial replica is left behind.
+#[allow(clippy::too_many_arguments)]
+pub fn seal_pre_commit_with_options<R: AsRef<Path>, T: AsRef<Path>, S: AsRef<Path>>(
+    porep_config: PoRepConfig,
+    cache_path: R,
+    in_path: T,
+    out_path: S,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    piece_infos: &[PieceInfo],
+    options: RunOptions,
+) -> Result<SealPreCommitOutput> {
+    info!("seal_pre_commit: start");
+    options.check_cancelled()?;
+
+    let output =
+        map_sector_files(porep_config, &in_path, &out_path).and_then(|(in_map, mut data)| {
+            let source: &[u8] = match in_map {
+                Some(ref in_map) => in_map,
+                None => &[],
+            };
+
+            seal_pre_commit_data(
+                porep_config,
+                cache_path,
+                Some(source),
+                &mut data,
+                prover_id,
+                sector_id,
+                ticket,
+                Some(piece_infos),
+                options,
+            )
+        });
+    let output = discard_cancelled_replicas(output, &[&out_path])?;
+
+    info!("seal_pre_commit: end");
+
+    Ok(output)
+}
+
+/// Seals the staged sector at `sector_path` in place, replacing the staged data with the replica.
+///
+/// The arguments are the same as for `seal_pre_commit`, `sector_path` taking the place of both
+/// `in_path` and `out_path`.
+pub fn seal_pre_commit_in_place<R: AsRef<Path>, T: AsRef<Path>>(
+    porep_config: PoRepConfig,
+    cache_path: R,
+    sector_path: T,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    piece_infos: &[PieceInfo],
+) -> Result<SealPreCommitOutput> {
+    seal_pre_commit_in_place_with_options(
+        porep_config,
+        cache_path,
+        sector_path,
+        prover_id,
+        sector_id,
+        ticket,
+        piece_infos,
+        RunOptions::default(),
+    )
+}
+
+/// Same as `seal_pre_commit_in_place`, reporting the progress of each stage and stopping once
+/// cancelled as set in `options` (see `seal_pre_commit_with_options`).
+///
+/// The staged data is lost if sealing is cancelled or fails part way through. A cancelled seal
+/// truncates `sector_path`.
+#[allow(clippy::too_many_arguments)]
+pub fn seal_pre_commit_in_place_with_options<R: AsRef<Path>, T: AsRef<Path>>(
+    porep_config: PoRepConfig,
+    cache_path: R,
+    sector_path: T,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    piece_infos: &[PieceInfo],
+    options: RunOptions,
+) -> Result<SealPreCommitOutput> {
+    info!("seal_pre_commit_in_place: start");
+    options.check_cancelled()?;
+
+    let sector_bytes = usize::from(PaddedBytesAmount::from(porep_config));
+
+    let f_data = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&sector_path)
+        .map_err(io_error(&sector_path))?;
+
+    // Zero-pad the data to the requested size by extending the underlying file if needed.
+    f_data
+        .set_len(sector_bytes as u64)
+        .map_err(io_error(&sector_path))?;
+
+    let output = {
+        let mut data = unsafe {
+            MmapOptions::new()
+                .map_mut(&f_data)
+                .map_err(io_error(&sector_path))?
+        };
+
+        seal_pre_commit_data(
+            porep_config,
+            cache_path,
+            None,
+            &mut data,
+            prover_id,
+            sector_id,
+            ticket,
+            Some(piece_infos),
+            options,
+        )
+    };
+    let output = discard_cancelled_replicas(output, &[&sector_path])?;
+
+    info!("seal_pre_commit_in_place: end");
+
+    Ok(output)
+}
+
+/// Seals a committed capacity sector, one holding no data, writing the resulting replica to
+/// `out_path`.
+///
+/// No staged file is needed: the sector is known to be all zeros, so its comm_d is the
+/// precomputed commitment of a zero sector and only the root of its tree_d is written, without
+/// hashing the sector. The nodes of tree_d which the proofs open are written by `seal_commit`,
+/// once the challenges are known. The sector is committed like any other, passing
+/// `cc_piece_infos(porep_config.sector_size)` as its pieces.
+///
+/// # Arguments
+///
+/// * `porep_config` - porep configuration containing the number of bytes in this sector.
+/// * `cache_path` - path to a directory in which the sector data's Merkle Tree can be written.
+/// * `out_path` - the path where the sealed sector data will be written.
+/// * `prover_id` - the prover-id that is sealing this sector.
+/// * `sector_id` - the sector-id of this sector.
+/// * `ticket` - the ticket that will be used to generate this sector's replica-id.
+pub fn seal_pre_commit_cc<R: AsRef<Path>, S: AsRef<Path>>(
+    porep_config: PoRepConfig,
+    cache_path: R,
+    out_path: S,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+) -> Result<SealPreCommitOutput> {
+    seal_pre_commit_cc_with_options(
+        porep_config,
+        cache_path,
+        out_path,
+        prover_id,
+        sector_id,
+        ticket,
+        RunOptions::default(),
+    )
+}
+
+/// Same as `seal_pre_commit_cc`, reporting the progress of each stage and stopping once cancelled
+/// as set in `options` (see `seal_pre_commit_with_options`).
+#[allow(clippy::too_many_arguments)]
+pub fn seal_pre_commit_cc_with_options<R: AsRef<Path>, S: AsRef<Path>>(
+    porep_config: PoRepConfig,
+    cache_path: R,
+    out_path: S,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    options: RunOptions,
+) -> Result<SealPreCommitOutput> {
+    info!("seal_pre_commit_cc: start");
+    options.check_cancelled()?;
+
+    let sector_bytes = usize::from(PaddedBytesAmount::from(porep_config));
+
+    let f_data = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(&out_path)
+        .map_err(io_error(&out_path))?;
+    f_data
+        .set_len(sector_bytes as u64)
+        .map_err(io_error(&out_path))?;
+
+    let output = {
+        let mut data = unsafe {
+            MmapOptions::new()
+                .map_mut(&f_data)
+                .map_err(io_error(&out_path))?
+        };
+
+        // An empty source is all padding, so nothing is read and any previous contents of
+        // `out_path` are ignored.
+        seal_pre_commit_data(
+            porep_config,
+            cache_path,
+            Some(&[]),
+            &mut data,
+            prover_id,
+            sector_id,
+            ticket,
+            None,
+            options,
+        )
+    };
+    let output = discard_cancelled_replicas(output, &[&out_path])?;
+
+    info!("seal_pre_commit_cc: end");
+
+    Ok(output)
+}
+
+/// Seals several sectors at once, writing the replica of each to its `out_path`.
+///
+/// The sectors are labeled together: the windows of all sectors are labeled in lock-step, so the
+/// parents of each node are computed once rather than once per sector, and the labels of all
+/// sectors are written with one pass over the graph. Each sector is otherwise sealed as by
+/// `seal_pre_commit`, see there for the meaning of the fields of `SealPreCommitSector`, and the
+/// outputs are returned in the order of `sectors`.
+///
+/// All sectors must be of the size of `porep_config` and have their own cache directory.
+pub fn seal_pre_commit_many(
+    porep_config: PoRepConfig,
+    prover_id: ProverId,
+    sectors: &[SealPreCommitSector],
+) -> Result<Vec<SealPreCommitOutput>> {
+    seal_pre_commit_many_with_options(porep_config, prover_id, sectors, RunOptions::default())
+}
+
+/// Same as `seal_pre_commit_many`, reporting the progress of each stage and stopping once
+/// cancelled as set in `options` (see `seal_pre_commit_with_options`). A cancelled seal truncates
+/// the `out_path` of every sector, a batch failing before replication leaves them as they were.
+///
+/// Labeling is reported once for all sectors, the trees of each sector are reported in turn.
+pub fn seal_pre_commit_many_with_options(
+    porep_config: PoRepConfig,
+    prover_id: ProverId,
+    sectors: &[SealPreCommitSector],
+    options: RunOptions,
+) -> Result<Vec<SealPreCommitOutput>> {
+    info!("seal_pre_commit_many: start ({} sectors)", sectors.len());
+    options.check_cancelled()?;
+    ensure!(!sectors.is_empty(), "no sectors to seal");
+
+    let sector_bytes = u64::from(PaddedBytesAmount::from(porep_config));
+
+    // Check all the paths before any out_path is created or truncated, so that a rejected batch
+    // leaves the files as they were. A batch that fails later, but before replication, puts the
+    // files back with `discard_unreplicated`.
+    let mut cache_paths = Vec::with_capacity(sectors.len());
+    let mut in_paths = Vec::with_capacity(sectors.len());
+    let mut out_paths = Vec::with_capacity(sectors.len());
+    for sector in sectors {
+        let cache_path =
+            fs::canonicalize(&sector.cache_path).map_err(io_error(&sector.cache_path))?;
+        ensure!(
+            !cache_paths.contains(&cache_path),
+            "sectors must not share the cache directory {:?}",
+            cache_path
+        );
+        cache_paths.push(cache_path);
+
+        let in_len = fs::metadata(&sector.in_path)
+            .map_err(io_error(&sector.in_path))?
+            .len();
+        ensure!(
+            in_len <= sector_bytes,
+            "in_path={:?} holds {} bytes, more than the sector size {}",
+            sector.in_path,
+            in_len,
+            sector_bytes
+        );
+        in_paths.push(fs::canonicalize(&sector.in_path).map_err(io_error(&sector.in_path))?);
+
+        let out_path = canonical_out_path(&sector.out_path)?;
+        ensure!(
+            !out_paths.contains(&out_path),
+            "sectors must not share the out_path {:?}",
+            out_path
+        );
+        out_paths.push(out_path);
+    }
+    for (index, (sector, out_path)) in sectors.iter().zip(&out_paths).enumerate() {
+        if let Some(other) = in_paths.iter().position(|in_path| in_path == out_path) {
+            ensure!(
+                other != index,
+                "in_path and out_path are both {:?}, use seal_pre_commit_in_place to seal in place",
+                out_path
+            );
+            bail!(
+                "the out_path of sector {:?} is the in_path of sector {:?}: {:?}",
+                sector.sector_id,
+                sectors[other].sector_id,
+                out_path
+            );
+        }
+    }
+
+    let public_params = public_params(
+        PaddedBytesAmount::from(porep_config),
+        usize::from(PoRepProofPartitions::from(porep_config)),
+        porep_config.params,
+    )?;
+
+    let out_lens: Vec<_> = out_paths
+        .iter()
+        .map(|out_path| fs::metadata(out_path).ok().map(|m| m.len()))
+        .collect();
+    let mut tree_d_paths = Vec::with_capacity(sectors.len());
+
+    let (in_maps, mut out_maps): (Vec<_>, Vec<_>) = sectors
+        .iter()
+        .map(|sector| map_sector_files(porep_config, &sector.in_path, &sector.out_path))
+        .collect::<Result<Vec<_>>>()
+        .map_err(|err| discard_unreplicated(err, &out_paths, &out_lens, &tree_d_paths))?
+        .into_iter()
+        .unzip();
+
+    let mut replicas = Vec::with_capacity(sectors.len());
+    let mut comms_d = Vec::with_capacity(sectors.len());
+    for ((sector, in_map), data) in sectors.iter().zip(&in_maps).zip(out_maps.iter_mut()) {
+        let source: &[u8] = match in_map {
+            Some(in_map) => in_map,
+            None => &[],
+        };
+
+        // MT for original data is always named tree-d, and it will be
+        // referenced later in the process as such.
+        let config = StoreConfig::new(
+            &sector.cache_path,
+            CacheKey::CommDTree.to_string(),
+            DEFAULT_CACHED_ABOVE_BASE_LAYER,
+        );
+        tree_d_paths.push(StoreConfig::data_path(&config.path, &config.id));
+
+        let (data_tree, comm_d) = build_data_tree(
+            porep_config,
+            &public_params,
+            &config,
+            Some(source),
+            data,
+            &sector.piece_infos,
+            options.progress(),
+        )
+        .map_err(|err| discard_unreplicated(err, &out_paths, &out_lens, &tree_d_paths))?;
+
+        let replica_id = generate_replica_id::<DefaultTreeHasher, _>(
+            &prover_id,
+            sector.sector_id.into(),
+            &sector.ticket,
+            data_tree.root(),
+        );
+
+        replicas.push(BatchReplica {
+            replica_id,
+            source: Some(source),
+            data,
+            data_tree: Some(data_tree),
+            config,
+        });
+        comms_d.push(comm_d);
+    }
+
+    let replicated = StackedDrg::<DefaultTreeHasher, DefaultPieceHasher>::replicate_many(
+        &public_params,
+        replicas,
+        options,
+    );
+    drop(in_maps);
+    drop(out_maps);
+    let replicated = discard_cancelled_replicas(replicated, &out_paths)?;
+
+    let mut outputs = Vec::with_capacity(sectors.len());
+    for ((sector, comm_d), (tau, (p_aux, t_aux))) in sectors.iter().zip(comms_d).zip(replicated) {
+        persist_aux(&sector.cache_path, &p_aux, &t_aux)?;
+
+        outputs.push(SealPreCommitOutput {
+            comm_r: commitment_from_fr::<Bls12>(tau.comm_r.into()),
+            comm_d,
+        });
+    }
+
+    info!("seal_pre_commit_many: end");
+
+    Ok(outputs)
+}
+
+/// Truncates the replicas at `out_paths` if `res` failed because sealing was cancelled, so that a
+/// partly written replica is not mistaken for a sealed one. The replicas must not be mapped any
+/// more. The labels and trees in the cache directories are removed while replicating.
+fn discard_cancelled_replicas<T, P: AsRef<Path>>(res: Result<T>, out_paths: &[P]) -> Result<T> {
+    if let Err(err) = &res {
+        if is_cancelled(err) {
+            for out_path in out_paths {
+                info!("sealing cancelled, truncating {:?}", out_path.as_ref());
+                OpenOptions::new()
+                    .write(true)
+                    .open(out_path)
+                    .and_then(|f| f.set_len(0))
+                    .map_err(io_error(out_path))?;
+            }
+        }
+    }
+
+    res
+}
+
+/// Puts the replicas at `out_paths` back as they were before a batch that failed with `err`
+/// ahead of replication: a replica the batch created is removed, the others are set back to their
+/// length in `out_lens`. Their contents were not written yet. The trees at `tree_d_paths`, which
+/// the batch wrote, are removed as well. Failing to restore a file is logged, `err` is returned.
+fn discard_unreplicated(
+    err: anyhow::Error,
+    out_paths: &[PathBuf],
+    out_lens: &[Option<u64>],
+    tree_d_paths: &[PathBuf],
+) -> anyhow::Error {
+    for (out_path, out_len) in out_paths.iter().zip(out_lens) {
+        let res = match out_len {
+            Some(len) => OpenOptions::new()
+                .write(true)
+                .open(out_path)
+                .and_then(|f| f.set_len(*len)),
+            None => fs::remove_file(out_path),
+        };
+        if let Err(e) = res {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("could not restore {:?}: {}", out_path, e);
+            }
+        }
+    }
+
+    for tree_d_path in tree_d_paths {
+        if let Err(e) = fs::remove_file(tree_d_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("could not remove {:?}: {}", tree_d_path, e);
+            }
+        }
+    }
+
+    err
+}
+
+/// The canonical form of `out_path`, which need not exist yet: the canonical parent directory
+/// joined with the file name.
+fn canonical_out_path<P: AsRef<Path>>(out_path: P) -> Result<PathBuf> {
+    let out_path = out_path.as_ref();
+    if out_path.exists() {
+        return Ok(fs::canonicalize(out_path).map_err(io_error(out_path))?);
+    }
+
+    let file_name = out_path
+        .file_name()
+        .with_context(|| format!("out_path {:?} has no file name", out_path))?;
+    let parent = match out_path.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    };
+    let parent = fs::canonicalize(parent).map_err(io_error(parent))?;
+
+    Ok(parent.join(file_name))
+}
+
+/// Maps the staged sector at `in_path` for reading, `None` if it is empty, and the replica at
+/// `out_path` for writing, creating it if needed.
+fn map_sector_files<T: AsRef<Path>, S: AsRef<Path>>(
+    porep_config: PoRepConfig,
+    in_path: T,
+    out_path: S,
+) -> Result<(Option<Mmap>, MmapMut)> {
+    let sector_bytes = usize::from(PaddedBytesAmount::from(porep_config));
+
+    let f_in = File::open(&in_path).map_err(io_error(&in_path))?;
+    let in_len = f_in.metadata().map_err(io_error(&in_path))?.len();
+    ensure!(
+        in_len <= sector_bytes as u64,
+        "in_path={:?} holds {} bytes, more than the sector size {}",
+        in_path.as_ref(),
+        in_len,
+        sector_bytes
+    );
+
+    let in_canonical = fs::canonicalize(&in_path).map_err(io_error(&in_path))?;
+    ensure!(
+        in_canonical != canonical_out_path(&out_path)?,
+        "in_path and out_path are both {:?}, use seal_pre_commit_in_place to seal in place",
+        in_canonical
+    );
+
+    // An empty file can not be mapped, it is all padding.
+    let in_map = if in_len == 0 {
+        None
+    } else {
+        Some(unsafe { MmapOptions::new().map(&f_in).map_err(io_error(&in_path))? })
+    };
+
+    let f_data = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(&out_path)
+        .map_err(io_error(&out_path))?;
+    f_data
+        .set_len(sector_bytes as u64)
+        .map_err(io_error(&out_path))?;
+
+    let data = unsafe {
+        MmapOptions::new()
+            .map_mut(&f_data)
+            .map_err(io_error(&out_path))?
+    };
+
+    Ok((in_map, data))
+}
+
+/// Seals a sector into `data`, reading the original data from `source` if given and from `data`
+/// otherwise.
+#[allow(clippy::too_many_arguments)]
+fn seal_pre_commit_data<R: AsRef<Path>>(
+    porep_config: PoRepConfig,
+    cache_path: R,
+    source: Option<&[u8]>,
+    data: &mut [u8],
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    piece_infos: Option<&[PieceInfo]>,
+    options: RunOptions,
+) -> Result<SealPreCommitOutput> {
+    let compound_setup_params = compound_proof::SetupParams {
+        vanilla_params: setup_params(
+            PaddedBytesAmount::from(porep_config),
+            usize::from(PoRepProofPartitions::from(porep_config)),
+            porep_config.params,
+        )?,
+        partitions: Some(usize::from(PoRepProofPartitions::from(porep_config))),
+    };
+
+    let compound_public_params = <StackedCompound as CompoundProof<
+        _,
+        StackedDrg<DefaultTreeHasher, DefaultPieceHasher>,
+        _,
+    >>::setup(&compound_setup_params)?;
+
+    // MT for original data is always named tree-d, and it will be
+    // referenced later in the process as such.
+    let config = StoreConfig::new(
+        cache_path.as_ref(),
+        CacheKey::CommDTree.to_string(),
+        DEFAULT_CACHED_ABOVE_BASE_LAYER,
+    );
+
+    let (data_tree, comm_d) = match piece_infos {
+        Some(piece_infos) => build_data_tree(
+            porep_config,
+            &compound_public_params.vanilla_params,
+            &config,
+            source,
+            data,
+            piece_infos,
+            options.progress(),
+        )?,
+        None => build_zero_data_tree(
+            porep_config,
+            &compound_public_params.vanilla_params,
+            &config,
+            options.progress(),
+        )?,
+    };
+
+    let replica_id = generate_replica_id::<DefaultTreeHasher, _>(
+        &prover_id,
+        sector_id.into(),
+        &ticket,
+        data_tree.root(),
+    );
+
+    let (tau, (p_aux, t_aux)) =
+        StackedDrg::<DefaultTreeHasher, DefaultPieceHasher>::replicate_with_options(
+            &compound_public_params.vanilla_params,
+            &replica_id,
+            source,
+            data,
+            Some(data_tree),
+            Some(config),
+            options,
+        )?;
+
+    let comm_r = commitment_from_fr::<Bls12>(tau.comm_r.into());
+
+    persist_aux(cache_path, &p_aux, &t_aux)?;
+
+    Ok(SealPreCommitOutput { comm_r, comm_d })
+}
+
+/// Builds the tree over the original data of a sector, read from `source` if given and from
+/// `data` otherwise, and checks that its root matches `piece_infos`. Returns the tree and comm_d.
+fn build_data_tree(
+    porep_config: PoRepConfig,
+    public_params: &stacked::PublicParams<DefaultTreeHasher>,
+    config: &StoreConfig,
+    source: Option<&[u8]>,
+    data: &[u8],
+    piece_infos: &[PieceInfo],
+    progress: &dyn ProgressObserver,
+) -> Result<(DataTree, Commitment)> {
+    info!("building merkle tree for the original data");
+    let data_tree = report_stage(progress, Stage::TreeD, 0, || {
+        let size = public_params.wrapper_graph.size();
+        match source {
+            Some(source) => create_merkle_tree_zero_padded::<DefaultPieceHasher>(
+                Some(config.clone()),
+                size,
+                source,
+            ),
+            None => create_merkle_tree::<DefaultPieceHasher>(Some(config.clone()), size, data),
+        }
+    })?;
+    progress.on_progress(Progress::TreeCompleted {
+        stage: Stage::TreeD,
+        leaves: data_tree.leafs(),
+        height: data_tree.height(),
+    });
+
+    let comm_d_root: Fr = data_tree.root().into();
+    let comm_d = commitment_from_fr::<Bls12>(comm_d_root);
+
+    ensure_pieces_match(&comm_d, piece_infos, porep_config.into())?;
+
+    Ok((data_tree, comm_d))
+}
+
+/// Writes the tree over the original data of a committed capacity sector, which is all zeros.
+/// Returns the tree and comm_d.
+///
+/// Every level of such a tree repeats a single node, so no node needs to be hashed from the
+/// data. The file is given the length of the whole tree, which replication checks, but only its
+/// root is written: the rest is left as a hole, which takes no space on file systems with sparse
+/// files. `seal_commit` writes the nodes its inclusion proofs read (see
+/// `write_zero_data_tree_openings`), so pre-committing writes a single node instead of a
+/// sector's worth of interior nodes.
+fn build_zero_data_tree(
+    porep_config: PoRepConfig,
+    public_params: &stacked::PublicParams<DefaultTreeHasher>,
+    config: &StoreConfig,
+    progress: &dyn ProgressObserver,
+) -> Result<(DataTree, Commitment)> {
+    info!("writing merkle tree for a committed capacity sector");
+    let leaves = public_params.wrapper_graph.size();
+    let data_tree = report_stage(progress, Stage::TreeD, 0, || -> Result<DataTree> {
+        let path = StoreConfig::data_path(&config.path, &config.id);
+        let mut file = File::create(&path).map_err(io_error(&path))?;
+        file.set_len((2 * leaves - 1) as u64 * NODE_SIZE as u64)
+            .map_err(io_error(&path))?;
+
+        let root = zero_tree_levels(leaves).pop().expect("a tree has a root");
+        file.seek(SeekFrom::Start((2 * leaves - 2) as u64 * NODE_SIZE as u64))
+            .map_err(io_error(&path))?;
+        file.write_all(&root).map_err(io_error(&path))?;
+
+        let store = DiskStore::new_from_disk(2 * leaves - 1, config)?;
+        MerkleTree::from_data_store(store, leaves)
+    })?;
+    progress.on_progress(Progress::TreeCompleted {
+        stage: Stage::TreeD,
+        leaves: data_tree.leafs(),
+        height: data_tree.height(),
+    });
+
+    let comm_d = cc_piece_infos(porep_config.sector_size)?[0].commitment;
+    let comm_d_root: Fr = data_tree.root().into();
+    ensure!(
+        commitment_from_fr::<Bls12>(comm_d_root) == comm_d,
+        "tree_d of a committed capacity sector does not match its comm_d"
+    );
+
+    Ok((data_tree, comm_d))
+}
+
+/// Writes the nodes of the tree_d described by `config`, the tree over an all zero sector, which
+/// the inclusion proofs of the leaves in `openings` read: each node on their paths and its
+/// sibling, below the root. The leaves are zeros, which the hole left by `build_zero_data_tree`
+/// reads as already.
+///
+/// A tree_d hashed from the data of a sector which happens to be all zeros holds the same nodes,
+/// so this leaves it unchanged.
+fn write_zero_data_tree_openings(config: &StoreConfig, openings: &[usize]) -> Result<()> {
+    let path = StoreConfig::data_path(&config.path, &config.id);
+    let size = config.size.context("tree_d config has no size")?;
+    let leaves = (size + 1) / 2;
+    let levels = zero_tree_levels(leaves);
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .map_err(cache_artifact_error(&path))?;
+    for &leaf in openings {
+        ensure!(leaf < leaves, "tree_d opening {} out of range", leaf);
+
+        let mut base = leaves;
+        let mut width = leaves / 2;
+        let mut index = leaf / 2;
+        for node in &levels[1..levels.len() - 1] {
+            let pair = base + (index & !1);
+            file.seek(SeekFrom::Start((pair * NODE_SIZE) as u64))
+                .map_err(io_error(&path))?;
+            file.write_all(node).map_err(io_error(&path))?;
+            file.write_all(node).map_err(io_error(&path))?;
+
+            base += width;
+            width /= 2;
+            index /= 2;
+        }
+    }
+
+    Ok(())
+}
+
+/// The node repeated on each level of the tree over an all zero sector of `leaves` leaves, from
+/// the leaves up to the root.
+fn zero_tree_levels(leaves: usize) -> Vec<[u8; NODE_SIZE]> {
+    let mut levels = vec![[0u8; NODE_SIZE]];
+    let mut width = leaves;
+    while width > 1 {
+        let below = levels[levels.len() - 1];
+        let mut node = [0u8; NODE_SIZE];
+        node.copy_from_slice(piece_hash(&below, &below).as_ref());
+        levels.push(node);
+        width /= 2;
+    }
+
+    levels
+}
+
+/// Writes the auxiliary data of a replica, needed by `seal_commit`, to `cache_path`.
+fn persist_aux<R: AsRef<Path>>(
+    cache_path: R,
+    p_aux: &stacked::PersistentAux<<DefaultTreeHasher as Hasher>::Domain>,
+    t_aux: &TemporaryAux<DefaultTreeHasher, DefaultPieceHasher>,
+) -> Result<()> {
+    // Persist p_aux and t_aux here
+    let p_aux_path = cache_path.as_ref().join(CacheKey::PAux.to_string());
+    let mut f_p_aux = File::create(&p_aux_path).map_err(io_error(&p_aux_path))?;
+    let p_aux_bytes = serialize(p_aux)?;
+    f_p_aux
+        .write_all(&p_aux_bytes)
+        .map_err(io_error(&p_aux_path))?;
+
+    let t_aux_path = cache_path.as_ref().join(CacheKey::TAux.to_string());
+    let mut f_t_aux = File::create(&t_aux_path).map_err(io_error(&t_aux_path))?;
+    let t_aux_bytes = serialize(t_aux)?;
+    f_t_aux
+        .write_all(&t_aux_bytes)
+        .map_err(io_error(&t_aux_path))?;
+
+    Ok(())
+}
+
+/// Generates a proof for the pre committed sector.
+///
+/// # Arguments
+///
+/// * `porep_config` - porep configuration containing the number of bytes in this sector.
+/// * `cache_path` - path to a directory in which the sector data's Merkle Tree can be written.
+/// * `prover_id` - the prover-id that is sealing the sector.
+/// * `sector_id` - the sector-id of this sector.
+/// * `ticket` - the ticket that will be used to generate this sector's replica-id.
+/// * `seed` - the seed used to derive the porep challenges.
+/// * `pre_commit` - commitments to the sector data and its replica.
+/// * `piece_infos` - each piece's info (number of bytes and commitment) in this sector.
+#[allow(clippy::too_many_arguments)]
+pub fn seal_commit<T: AsRef<Path>>(
+    porep_config: PoRepConfig,
+    cache_path: T,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+    pre_commit: SealPreCommitOutput,
+    piece_infos: &[PieceInfo],
+) -> Result<SealCommitOutput> {
+    seal_commit_with_options(
+        porep_config,
+        cache_path,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        pre_commit,
+        piece_infos,
+        RunOptions::default(),
+    )
+}
+
+/// Same as `seal_commit`, reporting the proving stages and each proven partition as set in
+/// `options`.
+///
+/// Once cancelled through `options`, proving stops before the next partition and fails with a
+/// cancellation error (see `is_cancelled`). The contents of `cache_path` are kept, so the commit
+/// can be retried.
+#[allow(clippy::too_many_arguments)]
+pub fn seal_commit_with_options<T: AsRef<Path>>(
+    porep_config: PoRepConfig,
+    cache_path: T,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+    pre_commit: SealPreCommitOutput,
+    piece_infos: &[PieceInfo],
+    options: RunOptions,
+) -> Result<SealCommitOutput> {
+    info!("seal_commit:start");
+
+    let SealPreCommitOutput { comm_d, comm_r } = pre_commit;
+
+    ensure!(comm_d != [0; 32], Error::ZeroCommitment("comm_d"));
+    ensure!(comm_r != [0; 32], Error::ZeroCommitment("comm_r"));
+    ensure_pieces_match(&comm_d, piece_infos, porep_config.into())?;
+
+    let p_aux = {
+        let mut p_aux_bytes = vec![];
+        let p_aux_path = cache_path.as_ref().join(CacheKey::PAux.to_string());
+        let mut f_p_aux = File::open(&p_aux_path).map_err(cache_artifact_error(&p_aux_path))?;
+        f_p_aux
+            .read_to_end(&mut p_aux_bytes)
+            .map_err(io_error(&p_aux_path))?;
+
+        deserialize(&p_aux_bytes)
+    }?;
+
+    let cache_dir = cache_path.as_ref().to_path_buf();
+    let t_aux = {
+        let mut t_aux_bytes = vec![];
+        let t_aux_path = cache_path.as_ref().join(CacheKey::TAux.to_string());
+        let mut f_t_aux = File::open(&t_aux_path).map_err(cache_artifact_error(&t_aux_path))?;
+        f_t_aux
+            .read_to_end(&mut t_aux_bytes)
+            .map_err(io_error(&t_aux_path))?;
+
+        let mut res: TemporaryAux<_, _> = deserialize(&t_aux_bytes)?;
+
+        // Switch t_aux to the passed in cache_path
+        res.set_cache_path(cache_path);
+        res
+    };
+
+    let comm_r_safe = as_safe_commitment(&comm_r, "comm_r")?;
+    let comm_d_safe = <DefaultPieceHasher as Hasher>::Domain::try_from_bytes(&comm_d)?;
+
+    let replica_id = generate_replica_id::<DefaultTreeHasher, _>(
+        &prover_id,
+        sector_id.into(),
+        &ticket,
+        comm_d_safe,
+    );
+
+    let public_inputs = stacked::PublicInputs {
+        replica_id,
+        tau: Some(stacked::Tau {
+            comm_d: comm_d_safe,
+            comm_r: comm_r_safe,
+        }),
+        k: None,
+        seed,
+    };
+
+    let compound_setup_params = compound_proof::SetupParams {
+        vanilla_params: setup_params(
+            PaddedBytesAmount::from(porep_config),
+            usize::from(PoRepProofPartitions::from(porep_config)),
+            porep_config.params,
+        )?,
+        partitions: Some(usize::from(PoRepProofPartitions::from(porep_config))),
+    };
+
+    let compound_public_params = StackedCompound::setup(&compound_setup_params)?;
+
+    // tree_d of a committed capacity sector only holds the nodes of these proofs
+    if comm_d == cc_piece_infos(porep_config.sector_size)?[0].commitment {
+        let openings = StackedDrg::<DefaultTreeHasher, DefaultPieceHasher>::tree_d_openings(
+            &compound_public_params.vanilla_params,
+            &public_inputs,
+            usize::from(PoRepProofPartitions::from(porep_config)),
+        )?;
+        write_zero_data_tree_openings(&t_aux.tree_d_config, &openings)?;
+    }
+
+    // Convert TemporaryAux to TemporaryAuxCache, which instantiates all
+    // elements based on the configs stored in TemporaryAux.
+    let t_aux_cache: TemporaryAuxCache<DefaultTreeHasher, DefaultPieceHasher> =
+        TemporaryAuxCache::new(&t_aux).map_err(|err| {
+            if is_not_found(&err) {
+                err.context(Error::MissingCacheArtifact(cache_dir))
+            } else {
+                err.context(Error::CorruptCacheArtifact(cache_dir))
+            }
+        })?;
+
+    let private_inputs = stacked::PrivateInputs::<DefaultTreeHasher, DefaultPieceHasher> {
+        p_aux,
+        t_aux: t_aux_cache,
+    };
+
+    let groth_params = get_stacked_params(porep_config)?;
+
+    info!(
+        "got groth params ({}) while sealing",
+        u64::from(PaddedBytesAmount::from(porep_config))
+    );
+
+    let proof = StackedCompound::prove_with_options(
+        &compound_public_params,
+        &public_inputs,
+        &private_inputs,
+        &*groth_params,
+        options,
+    )?;
+
+    // Delete cached MTs that are no longer needed.
+    TemporaryAux::<DefaultTreeHasher, DefaultPieceHasher>::delete(t_aux)?;
+
+    let mut buf = Vec::with_capacity(
+        SINGLE_PARTITION_PROOF_LEN * usize::from(PoRepProofPartitions::from(porep_config)),
+    );
+
+    proof.write(&mut buf)?;
+
+    // Verification is cheap when parameters are cached,
+    // and it is never correct to return a proof which does not verify.
+    verify_seal(
+        porep_config,
+        comm_r,
+        comm_d,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        &buf,
+    )
+    .context("post-seal verification sanity check failed")?;
+
+    info!("seal_commit:end");
+
+    Ok(SealCommitOutput { proof: buf })
+}
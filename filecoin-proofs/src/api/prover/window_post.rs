@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+
+use anyhow::{ensure, Result};
+use log::info;
+use merkletree::merkle::get_merkle_tree_leafs;
+use rayon::prelude::*;
+use storage_proofs::circuit::window_post::WindowPoStCompound;
+use storage_proofs::compound_proof::{self, CompoundProof};
+use storage_proofs::drgraph::DefaultTreeHasher;
+use storage_proofs::progress::{Progress, RunOptions, Stage};
+use storage_proofs::sector::*;
+use storage_proofs::thread_pools::{self, Pool};
+use storage_proofs::window_post;
+
+use crate::api::post::SnarkProof;
+use crate::api::prover::post::{get_tree_size, PrivateReplicaInfo};
+use crate::api::window_post::{proven_sectors, setup_params};
+use crate::caches::get_window_post_params;
+use crate::error::Error;
+use crate::types::{ChallengeSeed, Tree, WindowPoStConfig};
+
+/// Generates a window proof-of-spacetime, proving every sector in `replicas` which is not listed
+/// in `faults`. The proof consists of one circuit proof per `WindowPoStConfig::sector_count`
+/// proven sectors.
+///
+/// # Arguments
+///
+/// * `window_post_config` - window post config that contains the sector size of each sector that
+/// we are generating this post for.
+/// * `randomness` - randomness used to generate the leaf challenges.
+/// * `replicas` - each sector's sector-id and associated replica info.
+/// * `faults` - the sectors declared as faulty, which are not proven.
+pub fn generate_window_post(
+    window_post_config: WindowPoStConfig,
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PrivateReplicaInfo>,
+    faults: &OrderedSectorSet,
+) -> Result<SnarkProof> {
+    generate_window_post_with_options(
+        window_post_config,
+        randomness,
+        replicas,
+        faults,
+        RunOptions::default(),
+    )
+}
+
+/// Same as `generate_window_post`, reporting each loaded replica tree and each proven partition
+/// as set in `options`. Fails with a cancellation error (see `is_cancelled`) once cancelled
+/// through `options`.
+pub fn generate_window_post_with_options(
+    window_post_config: WindowPoStConfig,
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PrivateReplicaInfo>,
+    faults: &OrderedSectorSet,
+    options: RunOptions,
+) -> Result<SnarkProof> {
+    info!("generate_window_post:start");
+    let progress = options.progress();
+
+    ensure!(!replicas.is_empty(), Error::NoReplicas);
+    let sectors = proven_sectors(replicas, faults);
+    ensure!(!sectors.is_empty(), "all sectors are faulty");
+
+    let pub_params: compound_proof::PublicParams<window_post::WindowPoSt<DefaultTreeHasher>> =
+        WindowPoStCompound::setup(&setup_params(window_post_config, sectors.len()))?;
+    let groth_params = get_window_post_params(window_post_config)?;
+
+    let tree_size = get_tree_size(window_post_config.sector_size);
+    let tree_leafs = get_merkle_tree_leafs(tree_size);
+
+    progress.on_progress(Progress::StageStarted {
+        stage: Stage::PostTrees,
+        steps: sectors.len(),
+    });
+    let trees: Vec<Tree> = thread_pools::install(Pool::PostReads, || {
+        sectors
+            .par_iter()
+            .map(|id| -> Result<_> {
+                options.check_cancelled()?;
+                let tree = replicas[id].merkle_tree(tree_size, tree_leafs)?;
+                progress.on_progress(Progress::SectorTreeLoaded {
+                    sector_id: *id,
+                    leaves: tree_leafs,
+                });
+
+                Ok(tree)
+            })
+            .collect::<Result<_>>()
+    })?;
+    progress.on_progress(Progress::StageFinished {
+        stage: Stage::PostTrees,
+    });
+
+    let mut pub_sectors = Vec::with_capacity(sectors.len());
+    let mut priv_sectors = Vec::with_capacity(sectors.len());
+    for (id, tree) in sectors.iter().zip(trees.iter()) {
+        let replica = &replicas[id];
+
+        pub_sectors.push(window_post::PublicSector {
+            id: *id,
+            comm_r: replica.safe_comm_r()?,
+        });
+        priv_sectors.push(window_post::PrivateSector {
+            tree,
+            comm_c: replica.safe_comm_c()?,
+            comm_q: replica.safe_comm_q()?,
+        });
+    }
+
+    let pub_inputs = window_post::PublicInputs {
+        randomness: *randomness,
+        sectors: pub_sectors,
+        k: None,
+    };
+    let priv_inputs = window_post::PrivateInputs::<DefaultTreeHasher> {
+        sectors: &priv_sectors,
+    };
+
+    let proof = WindowPoStCompound::prove_with_options(
+        &pub_params,
+        &pub_inputs,
+        &priv_inputs,
+        &*groth_params,
+        options,
+    )?;
+
+    info!("generate_window_post:finish");
+
+    proof.to_vec()
+}
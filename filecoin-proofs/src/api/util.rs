@@ -4,6 +4,7 @@ use paired::Engine;
 use storage_proofs::fr32::{bytes_into_fr, fr_into_bytes};
 use storage_proofs::hasher::Domain;
 
+use crate::error::Error;
 use crate::types::Commitment;
 
 pub(crate) fn as_safe_commitment<H: Domain, T: AsRef<str>>(
@@ -12,9 +13,11 @@ pub(crate) fn as_safe_commitment<H: Domain, T: AsRef<str>>(
 ) -> Result<H> {
     bytes_into_fr::<Bls12>(comm)
         .map(Into::into)
-        .with_context(|| format!("Invalid commitment ({})", commitment_name.as_ref(),))
+        .with_context(|| Error::InvalidCommitment(commitment_name.as_ref().to_string()))
 }
 
+// only used by tests without the `prover` feature
+#[cfg_attr(not(feature = "prover"), allow(dead_code))]
 pub(crate) fn commitment_from_fr<E: Engine>(fr: E::Fr) -> Commitment {
     let mut commitment = [0; 32];
     for (i, b) in fr_into_bytes::<E>(&fr).iter().enumerate() {
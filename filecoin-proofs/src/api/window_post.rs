@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+
+use anyhow::{ensure, Result};
+use log::info;
+use storage_proofs::circuit::multi_proof::MultiProof;
+use storage_proofs::circuit::window_post::WindowPoStCompound;
+use storage_proofs::compound_proof::{self, CompoundProof};
+use storage_proofs::drgraph::DefaultTreeHasher;
+use storage_proofs::proof::NoRequirements;
+use storage_proofs::sector::*;
+use storage_proofs::window_post;
+
+use crate::api::post::PublicReplicaInfo;
+use crate::caches::{get_window_post_verifying_key, Bls12VerifyingKey};
+use crate::constants::SINGLE_PARTITION_PROOF_LEN;
+use crate::error::Error;
+use crate::parameters::window_post_setup_params;
+use crate::types::{ChallengeSeed, WindowPoStConfig};
+
+/// The sectors proven by a window post: all `sectors` except the `faults`.
+pub(crate) fn proven_sectors<T>(
+    sectors: &BTreeMap<SectorId, T>,
+    faults: &OrderedSectorSet,
+) -> Vec<SectorId> {
+    sectors
+        .keys()
+        .filter(|id| !faults.contains(id))
+        .copied()
+        .collect()
+}
+
+pub(crate) fn setup_params(
+    window_post_config: WindowPoStConfig,
+    sector_total: usize,
+) -> compound_proof::SetupParams {
+    compound_proof::SetupParams {
+        vanilla_params: window_post_setup_params(window_post_config),
+        partitions: Some(window_post_config.partitions(sector_total)),
+    }
+}
+
+/// Verifies a window proof-of-spacetime.
+///
+/// # Arguments
+///
+/// * `window_post_config` - window post config that contains the sector size of each sector that
+/// this post was generated for.
+/// * `randomness` - the randomness used to generate the leaf challenges.
+/// * `proof` - the serialized circuit proofs of all partitions.
+/// * `replicas` - each sector's sector-id and associated replica info.
+/// * `faults` - the sectors declared as faulty, which were not proven.
+pub fn verify_window_post(
+    window_post_config: WindowPoStConfig,
+    randomness: &ChallengeSeed,
+    proof: &[u8],
+    replicas: &BTreeMap<SectorId, PublicReplicaInfo>,
+    faults: &OrderedSectorSet,
+) -> Result<bool> {
+    let verifying_key = get_window_post_verifying_key(window_post_config)?;
+
+    verify_window_post_with_verifying_key(
+        window_post_config,
+        &verifying_key,
+        randomness,
+        proof,
+        replicas,
+        faults,
+    )
+}
+
+/// Verifies a window proof-of-spacetime against the given verifying key, rather than the one
+/// found in the parameter cache.
+pub fn verify_window_post_with_verifying_key(
+    window_post_config: WindowPoStConfig,
+    verifying_key: &Bls12VerifyingKey,
+    randomness: &ChallengeSeed,
+    proof: &[u8],
+    replicas: &BTreeMap<SectorId, PublicReplicaInfo>,
+    faults: &OrderedSectorSet,
+) -> Result<bool> {
+    info!("verify_window_post:start");
+
+    ensure!(!replicas.is_empty(), Error::NoReplicas);
+    let sectors = proven_sectors(replicas, faults);
+    ensure!(!sectors.is_empty(), "all sectors are faulty");
+
+    let partitions = window_post_config.partitions(sectors.len());
+    ensure!(
+        proof.len() == partitions * SINGLE_PARTITION_PROOF_LEN,
+        Error::InvalidProofLength {
+            expected: partitions * SINGLE_PARTITION_PROOF_LEN,
+            actual: proof.len(),
+        }
+    );
+
+    let pub_params: compound_proof::PublicParams<window_post::WindowPoSt<DefaultTreeHasher>> =
+        WindowPoStCompound::setup(&setup_params(window_post_config, sectors.len()))?;
+
+    let pub_sectors = sectors
+        .iter()
+        .map(|id| {
+            Ok(window_post::PublicSector {
+                id: *id,
+                comm_r: replicas[id].safe_comm_r()?,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    let pub_inputs = window_post::PublicInputs {
+        randomness: *randomness,
+        sectors: pub_sectors,
+        k: None,
+    };
+
+    let proof = MultiProof::new_from_reader(Some(partitions), proof, verifying_key)?;
+    let is_valid = WindowPoStCompound::verify(&pub_params, &pub_inputs, &proof, &NoRequirements)?;
+
+    info!("verify_window_post:finish");
+
+    Ok(is_valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proven_sectors_skip_faults() {
+        let sectors: BTreeMap<SectorId, ()> = (1..=5).map(|i| (SectorId::from(i), ())).collect();
+        let faults: OrderedSectorSet = vec![SectorId::from(2), SectorId::from(4), 9.into()]
+            .into_iter()
+            .collect();
+
+        assert_eq!(
+            proven_sectors(&sectors, &faults),
+            vec![SectorId::from(1), 3.into(), 5.into()]
+        );
+        assert!(proven_sectors(&sectors, &sectors.keys().copied().collect()).is_empty());
+    }
+}
@@ -0,0 +1,506 @@
+use std::path::Path;
+use std::process::exit;
+
+use anyhow::{anyhow, ensure, Context, Result};
+use bellperson::Circuit;
+use clap::{value_t, values_t, App, Arg, ArgMatches, SubCommand};
+use log::info;
+use paired::bls12_381::Bls12;
+use rand::{rngs::OsRng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+use filecoin_proofs::constants::*;
+use filecoin_proofs::param::TRUSTED_KEYS_ENV_VAR;
+#[cfg(feature = "manifest-signing")]
+use filecoin_proofs::param::{check_manifest_signature, parse_trusted_keys};
+use filecoin_proofs::parameters::{post_public_params, public_params, window_post_public_params};
+use filecoin_proofs::sector_sizes::{published_sector_sizes, register_sector_size, SectorSizeInfo};
+use filecoin_proofs::types::*;
+use std::collections::BTreeSet;
+use storage_proofs::circuit::election_post::{ElectionPoStCircuit, ElectionPoStCompound};
+use storage_proofs::circuit::rational_post::{RationalPoStCircuit, RationalPoStCompound};
+use storage_proofs::circuit::stacked::StackedCompound;
+use storage_proofs::circuit::window_post::{WindowPoStCircuit, WindowPoStCompound};
+use storage_proofs::compound_proof::{CircuitComponent, CompoundProof};
+use storage_proofs::election_post::ElectionPoSt;
+use storage_proofs::hasher::pedersen::PedersenHasher;
+use storage_proofs::parameter_cache::{
+    parameter_cache_metadata_path, parameter_cache_params_path, parameter_cache_verifying_key_path,
+    CacheableParameters, ParameterSetMetadata,
+};
+use storage_proofs::proof::ProofScheme;
+use storage_proofs::rational_post::{self, RationalPoSt};
+use storage_proofs::stacked::StackedDrg;
+use storage_proofs::window_post::WindowPoSt;
+
+mod status;
+
+use status::{collect_garbage, load_manifest, print_status, read_cache_entries};
+
+const SEED: [u8; 16] = [
+    0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc, 0xe5,
+];
+
+/// Checks the cached metadata, Groth parameters and verifying key of the circuit proven by `C`,
+/// generating the missing ones. With `is_predictable` the parameters are generated from `SEED`.
+fn cache_params<'a, C, S, Circ>(is_predictable: bool, public_params: &S::PublicParams) -> Result<()>
+where
+    C: CompoundProof<'a, Bls12, S, Circ>,
+    S: ProofScheme<'a>,
+    S::Proof: Sync + Send,
+    S::PublicParams: ParameterSetMetadata + Sync + Send,
+    S::PublicInputs: Clone + Sync,
+    Circ: Circuit<Bls12> + CircuitComponent,
+{
+    C::get_param_metadata(C::blank_circuit(public_params), public_params)
+        .context("failed to get metadata")?;
+
+    let circuit = C::blank_circuit(public_params);
+    if is_predictable {
+        C::get_groth_params(
+            Some(&mut XorShiftRng::from_seed(SEED)),
+            circuit,
+            public_params,
+        )
+    } else {
+        C::get_groth_params(Some(&mut OsRng), circuit, public_params)
+    }
+    .context("failed to get groth params")?;
+
+    let circuit = C::blank_circuit(public_params);
+    if is_predictable {
+        C::get_verifying_key::<OsRng>(None, circuit, public_params)
+    } else {
+        C::get_verifying_key(Some(&mut OsRng), circuit, public_params)
+    }
+    .context("failed to get verifying key")?;
+
+    Ok(())
+}
+
+fn cache_porep_params(is_predictable: bool, porep_config: PoRepConfig) -> Result<()> {
+    let n = u64::from(PaddedBytesAmount::from(porep_config));
+    info!(
+        "begin PoRep parameter-cache check/populate routine for {}-byte sectors",
+        n
+    );
+
+    let public_params = public_params(
+        PaddedBytesAmount::from(porep_config),
+        usize::from(PoRepProofPartitions::from(porep_config)),
+        porep_config.params,
+    )?;
+
+    cache_params::<StackedCompound, StackedDrg<DefaultTreeHasher, DefaultPieceHasher>, _>(
+        is_predictable,
+        &public_params,
+    )
+}
+
+fn cache_post_params(is_predictable: bool, post_config: PoStConfig) -> Result<()> {
+    let n = u64::from(PaddedBytesAmount::from(post_config));
+    info!(
+        "begin PoSt parameter-cache check/populate routine for {}-byte sectors",
+        n
+    );
+
+    let post_public_params = post_public_params(post_config)?;
+
+    cache_params::<
+        ElectionPoStCompound<PedersenHasher>,
+        ElectionPoSt<PedersenHasher>,
+        ElectionPoStCircuit<Bls12, PedersenHasher>,
+    >(is_predictable, &post_public_params)
+}
+
+fn rational_post_public_params(post_config: PoStConfig) -> Result<rational_post::PublicParams> {
+    RationalPoSt::<PedersenHasher>::setup(&rational_post::SetupParams {
+        sector_size: u64::from(PaddedBytesAmount::from(post_config)),
+        challenges_count: post_config.challenge_count,
+    })
+}
+
+fn cache_rational_post_params(is_predictable: bool, post_config: PoStConfig) -> Result<()> {
+    let n = u64::from(PaddedBytesAmount::from(post_config));
+    info!(
+        "begin rational PoSt parameter-cache check/populate routine for {}-byte sectors",
+        n
+    );
+
+    let post_public_params = rational_post_public_params(post_config)?;
+
+    cache_params::<
+        RationalPoStCompound<PedersenHasher>,
+        RationalPoSt<PedersenHasher>,
+        RationalPoStCircuit<Bls12, PedersenHasher>,
+    >(is_predictable, &post_public_params)
+}
+
+fn cache_window_post_params(
+    is_predictable: bool,
+    window_post_config: WindowPoStConfig,
+) -> Result<()> {
+    let n = u64::from(PaddedBytesAmount::from(window_post_config));
+    info!(
+        "begin window PoSt parameter-cache check/populate routine for {}-byte sectors",
+        n
+    );
+
+    let post_public_params = window_post_public_params(window_post_config)?;
+
+    cache_params::<
+        WindowPoStCompound<PedersenHasher>,
+        WindowPoSt<PedersenHasher>,
+        WindowPoStCircuit<Bls12, PedersenHasher>,
+    >(is_predictable, &post_public_params)
+}
+
+// Run this from the command-line to pre-generate the groth parameters used by the API.
+pub fn main() {
+    fil_logger::init();
+
+    let matches = App::new("paramcache")
+        .version("0.1")
+        .about("Generate and persist Groth parameters and verifying keys")
+        .arg(
+            Arg::with_name("params-for-sector-sizes")
+                .short("z")
+                .long("params-for-sector-sizes")
+                .conflicts_with("all")
+                .require_delimiter(true)
+                .value_delimiter(",")
+                .multiple(true)
+                .help("A comma-separated list of sector sizes, in bytes, for which Groth parameters will be generated")
+        )
+        .arg(
+            Arg::with_name("register-sector-size")
+                .long("register-sector-size")
+                .require_delimiter(true)
+                .value_delimiter(",")
+                .multiple(true)
+                .help("A comma-separated list of unpublished sector sizes to register before generating, each as SECTOR_SIZE:WINDOW_SIZE in bytes, so that they can be passed to --params-for-sector-sizes")
+        )
+        .arg(
+            Arg::with_name("predictable")
+                .long("predictable")
+                .help("When set the paramters will be seeded with a fixed value.")
+        )
+        .arg(
+            Arg::with_name("only-election-post")
+                .long("only-election-post")
+                .conflicts_with("circuits")
+                .help("Only generate parameters for election-post, the same as --circuits=election-post")
+        )
+        .arg(
+            Arg::with_name("circuits")
+                .long("circuits")
+                .require_delimiter(true)
+                .value_delimiter(",")
+                .multiple(true)
+                .possible_values(&["stacked-porep", "election-post", "rational-post", "window-post"])
+                .help("A comma-separated list of the circuits for which Groth parameters will be generated, stacked-porep and election-post by default")
+        )
+        .arg(
+            Arg::with_name("partitions")
+                .long("partitions")
+                .takes_value(true)
+                .help("Generate stacked-porep parameters for this number of partitions instead of the registered one")
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .help("Print the cache identifier and files of each selected circuit, and whether they exist, without generating anything")
+        )
+        .subcommand(
+            SubCommand::with_name("status")
+                .about("List the files in the parameter cache: current, stale (another circuit version), unknown or corrupt")
+                .arg(json_arg())
+        )
+        .subcommand(
+            SubCommand::with_name("gc")
+                .about("Remove the stale and corrupt files from the parameter cache, skipping files in use")
+                .arg(json_arg().help("Use specific json file to classify the cache entries, which must be signed by a trusted key (see --trusted-key), as the files it calls corrupt are removed (defaults to the built-in manifest)"))
+                .arg(
+                    Arg::with_name("trusted-key")
+                        .takes_value(true)
+                        .long("trusted-key")
+                        .env(TRUSTED_KEYS_ENV_VAR)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_delimiter(",")
+                        .help("Hex encoded ed25519 public key trusted to sign the JSON file given with --json, its signature being read from <JSON>.sig"),
+                )
+                .arg(
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .help("List the files which would be removed, without removing them")
+                )
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        ("status", Some(m)) => exit_on_error(status(m, false)),
+        ("gc", Some(m)) => exit_on_error(status(m, true)),
+        _ => {}
+    }
+
+    exit_on_error(generate(&matches));
+}
+
+/// Parses a `SECTOR_SIZE:WINDOW_SIZE` pair, in bytes, into the production parameters for that
+/// sector size.
+fn parse_sector_size_info(value: &str) -> Result<SectorSizeInfo> {
+    let mut sizes = value.splitn(2, ':');
+    match (sizes.next(), sizes.next()) {
+        (Some(sector_size), Some(window_size)) => Ok(SectorSizeInfo::new(
+            sector_size.parse()?,
+            window_size.parse()?,
+        )),
+        _ => Err(anyhow!(
+            "invalid sector size {:?}, expected SECTOR_SIZE:WINDOW_SIZE",
+            value
+        )),
+    }
+}
+
+/// The circuits whose parameters can be generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CircuitKind {
+    StackedPoRep,
+    ElectionPoSt,
+    RationalPoSt,
+    WindowPoSt,
+}
+
+impl CircuitKind {
+    const ALL: [CircuitKind; 4] = [
+        CircuitKind::ElectionPoSt,
+        CircuitKind::RationalPoSt,
+        CircuitKind::WindowPoSt,
+        CircuitKind::StackedPoRep,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            CircuitKind::StackedPoRep => "stacked-porep",
+            CircuitKind::ElectionPoSt => "election-post",
+            CircuitKind::RationalPoSt => "rational-post",
+            CircuitKind::WindowPoSt => "window-post",
+        }
+    }
+}
+
+fn generate(matches: &ArgMatches) -> Result<()> {
+    if matches.is_present("register-sector-size") {
+        for value in values_t!(matches.values_of("register-sector-size"), String)? {
+            register_sector_size(parse_sector_size_info(&value)?)?;
+        }
+    }
+
+    let sizes: BTreeSet<u64> = if matches.is_present("params-for-sector-sizes") {
+        values_t!(matches.values_of("params-for-sector-sizes"), u64)?
+            .into_iter()
+            .collect()
+    } else {
+        published_sector_sizes().into_iter().collect()
+    };
+
+    let selected: Vec<String> = if matches.is_present("only-election-post") {
+        vec![CircuitKind::ElectionPoSt.name().to_string()]
+    } else if matches.is_present("circuits") {
+        values_t!(matches.values_of("circuits"), String)?
+    } else {
+        vec![
+            CircuitKind::StackedPoRep.name().to_string(),
+            CircuitKind::ElectionPoSt.name().to_string(),
+        ]
+    };
+    let circuits: Vec<CircuitKind> = CircuitKind::ALL
+        .iter()
+        .cloned()
+        .filter(|circuit| selected.iter().any(|name| name == circuit.name()))
+        .collect();
+
+    let partitions = if matches.is_present("partitions") {
+        Some(value_t!(matches.value_of("partitions"), u8)?)
+    } else {
+        None
+    };
+
+    let is_predictable = matches.is_present("predictable");
+    let dry_run = matches.is_present("dry-run");
+
+    let mut failed = 0;
+    for sector_size in sizes {
+        let sector_size = SectorSize(sector_size);
+
+        // a failing circuit does not keep the others from being generated
+        for circuit in &circuits {
+            if let Err(err) =
+                cache_circuit(*circuit, sector_size, partitions, is_predictable, dry_run)
+            {
+                println!(
+                    "{} ({} bytes): error: {:#}",
+                    circuit.name(),
+                    u64::from(sector_size),
+                    err
+                );
+                failed += 1;
+            }
+        }
+    }
+
+    ensure!(failed == 0, "{} circuits failed", failed);
+
+    Ok(())
+}
+
+/// Generates the parameters of `circuit` for `sector_size`, or with `dry_run` prints where they
+/// are cached.
+fn cache_circuit(
+    circuit: CircuitKind,
+    sector_size: SectorSize,
+    partitions: Option<u8>,
+    is_predictable: bool,
+    dry_run: bool,
+) -> Result<()> {
+    match circuit {
+        CircuitKind::StackedPoRep => {
+            let mut porep_config = PoRepConfig::new(sector_size)?;
+            if let Some(partitions) = partitions {
+                porep_config.partitions = PoRepProofPartitions(partitions);
+            }
+
+            if dry_run {
+                print_cache_entry(circuit, sector_size, &porep_config.get_cache_identifier()?);
+            } else {
+                cache_porep_params(is_predictable, porep_config)?;
+            }
+        }
+        CircuitKind::ElectionPoSt => {
+            let post_config = PoStConfig::new(sector_size)?;
+
+            if dry_run {
+                print_cache_entry(circuit, sector_size, &post_config.get_cache_identifier()?);
+            } else {
+                cache_post_params(is_predictable, post_config)?;
+            }
+        }
+        CircuitKind::RationalPoSt => {
+            let post_config = PoStConfig::new(sector_size)?;
+
+            if dry_run {
+                let public_params = rational_post_public_params(post_config)?;
+                let id = <RationalPoStCompound<PedersenHasher> as CacheableParameters<
+                    Bls12,
+                    RationalPoStCircuit<Bls12, PedersenHasher>,
+                    _,
+                >>::cache_identifier(&public_params);
+                print_cache_entry(circuit, sector_size, &id);
+            } else {
+                cache_rational_post_params(is_predictable, post_config)?;
+            }
+        }
+        CircuitKind::WindowPoSt => {
+            let window_post_config = WindowPoStConfig::new(sector_size)?;
+
+            if dry_run {
+                print_cache_entry(
+                    circuit,
+                    sector_size,
+                    &window_post_config.get_cache_identifier()?,
+                );
+            } else {
+                cache_window_post_params(is_predictable, window_post_config)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the cache identifier of a circuit and the files it is cached in, with whether they
+/// exist.
+fn print_cache_entry(circuit: CircuitKind, sector_size: SectorSize, id: &str) {
+    println!(
+        "{} ({} bytes): {}",
+        circuit.name(),
+        u64::from(sector_size),
+        id
+    );
+
+    for path in &[
+        parameter_cache_params_path(id),
+        parameter_cache_verifying_key_path(id),
+        parameter_cache_metadata_path(id),
+    ] {
+        let exists = if path.exists() { "exists" } else { "missing" };
+        println!("  {:<8} {}", exists, path.display());
+    }
+}
+
+fn json_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("json")
+        .value_name("JSON")
+        .takes_value(true)
+        .short("j")
+        .long("json")
+        .help("Use specific json file to classify the cache entries (defaults to the built-in manifest)")
+}
+
+fn exit_on_error(res: Result<()>) {
+    match res {
+        Ok(()) => exit(0),
+        Err(err) => {
+            println!("fatal error: {}", err);
+            exit(1);
+        }
+    }
+}
+
+fn status(matches: &ArgMatches, gc: bool) -> Result<()> {
+    let manifest = load_manifest(matches.value_of("json"), |json_path, manifest| {
+        if gc {
+            verify_gc_manifest(matches, json_path, manifest)
+        } else {
+            Ok(())
+        }
+    })?;
+    let entries = read_cache_entries(&manifest)?;
+
+    print_status(&entries);
+
+    if gc {
+        println!();
+        let failed = collect_garbage(&entries, matches.is_present("dry-run"));
+        ensure!(failed == 0, "{} files could not be removed", failed);
+    }
+
+    Ok(())
+}
+
+/// As `gc` removes the files a manifest calls corrupt, a manifest given with `--json` has to be
+/// signed by a trusted key, checked as by `paramfetch`.
+#[cfg(feature = "manifest-signing")]
+fn verify_gc_manifest(matches: &ArgMatches, json_path: &Path, manifest: &[u8]) -> Result<()> {
+    let trusted_keys = parse_trusted_keys(matches.values_of("trusted-key"))?;
+
+    match check_manifest_signature(json_path, manifest, &trusted_keys)? {
+        Some(public_key) => {
+            println!("manifest signed by trusted key {}", public_key);
+            Ok(())
+        }
+        None => Err(anyhow!(
+            "refusing to collect garbage: JSON file {:?} is not signed",
+            json_path
+        )),
+    }
+}
+
+#[cfg(not(feature = "manifest-signing"))]
+fn verify_gc_manifest(_matches: &ArgMatches, json_path: &Path, _manifest: &[u8]) -> Result<()> {
+    Err(anyhow!(
+        "refusing to collect garbage: the signature of JSON file {:?} cannot be checked without the manifest-signing feature",
+        json_path
+    ))
+}
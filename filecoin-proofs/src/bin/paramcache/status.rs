@@ -0,0 +1,271 @@
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use fs2::FileExt;
+
+use filecoin_proofs::param::{
+    get_digest_for_file_within_cache, has_extension, ParameterMap, INVALID_DIGEST_SUFFIX,
+    IPGET_DIR_INFIX, PARTIAL_EXT,
+};
+use storage_proofs::parameter_cache::{
+    parameter_cache_dir, parameter_cache_entry_version, remove_cache_entry, VERSION,
+};
+
+const DEFAULT_PARAMETERS: &str = include_str!("../../../parameters.json");
+
+/// How a file in the parameter cache relates to this version of the circuits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryStatus {
+    /// Listed in the manifest, with a matching digest.
+    Current,
+    /// Generated for another version of the circuits, see `parameter_cache::VERSION`, or left
+    /// behind by an interrupted download of `paramfetch`.
+    Stale,
+    /// Not listed in the manifest, e.g. generated locally for an unpublished sector size.
+    Unknown,
+    /// Listed in the manifest, but with another digest or unreadable, or set aside by `paramfetch`
+    /// for not matching the manifest.
+    Corrupt,
+}
+
+impl EntryStatus {
+    fn name(self) -> &'static str {
+        match self {
+            EntryStatus::Current => "current",
+            EntryStatus::Stale => "stale",
+            EntryStatus::Unknown => "unknown",
+            EntryStatus::Corrupt => "corrupt",
+        }
+    }
+
+    /// Whether `gc` removes entries of this status.
+    fn is_garbage(self) -> bool {
+        self == EntryStatus::Stale || self == EntryStatus::Corrupt
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub filename: String,
+    pub size: u64,
+    pub status: EntryStatus,
+    /// Whether the entry is a directory ipget downloaded to, see `IPGET_DIR_INFIX`.
+    pub is_ipget_dir: bool,
+}
+
+/// Reads the manifest at `json_path`, after checking its contents with `verify`, or the built-in
+/// one.
+pub fn load_manifest<F>(json_path: Option<&str>, verify: F) -> Result<ParameterMap>
+where
+    F: FnOnce(&Path, &[u8]) -> Result<()>,
+{
+    let manifest = match json_path {
+        Some(json_path) => {
+            println!("using JSON file: {:?}", json_path);
+
+            let bytes = fs::read(json_path)
+                .with_context(|| format!("could not read JSON file {:?}", json_path))?;
+            verify(Path::new(json_path), &bytes)?;
+
+            serde_json::from_slice(&bytes)
+                .with_context(|| format!("JSON file {:?} did not parse correctly", json_path))?
+        }
+        None => {
+            println!("using built-in manifest");
+            serde_json::from_str(DEFAULT_PARAMETERS)?
+        }
+    };
+
+    Ok(manifest)
+}
+
+/// Lists the files in the parameter cache, checking the digest of those in the manifest.
+pub fn read_cache_entries(manifest: &ParameterMap) -> Result<Vec<CacheEntry>> {
+    let cache_dir = parameter_cache_dir();
+    let mut entries = Vec::new();
+
+    for dir_entry in fs::read_dir(&cache_dir)
+        .with_context(|| format!("could not read parameter cache {:?}", cache_dir))?
+    {
+        let dir_entry = dir_entry?;
+        let metadata = dir_entry.metadata()?;
+        let filename = match dir_entry.file_name().into_string() {
+            Ok(filename) => filename,
+            Err(_) => continue,
+        };
+
+        // the directory of an interrupted ipget download is as stale as its partial file
+        if metadata.is_dir() && filename.contains(IPGET_DIR_INFIX) {
+            entries.push(CacheEntry {
+                size: dir_size(&dir_entry.path())?,
+                filename,
+                status: EntryStatus::Stale,
+                is_ipget_dir: true,
+            });
+            continue;
+        }
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let status = entry_status(&filename, manifest);
+        entries.push(CacheEntry {
+            filename,
+            size: metadata.len(),
+            status,
+            is_ipget_dir: false,
+        });
+    }
+
+    entries.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+    Ok(entries)
+}
+
+/// The size of the files in the directory at `path` and below.
+fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for dir_entry in fs::read_dir(path)? {
+        let dir_entry = dir_entry?;
+        let metadata = dir_entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&dir_entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
+
+fn entry_status(filename: &str, manifest: &ParameterMap) -> EntryStatus {
+    match parameter_cache_entry_version(filename) {
+        Some(version) if version != VERSION => return EntryStatus::Stale,
+        _ => {}
+    }
+
+    // the leftovers of paramfetch carry the current version, but are never in the manifest
+    if has_extension(filename, PARTIAL_EXT) {
+        return EntryStatus::Stale;
+    }
+    if filename.ends_with(INVALID_DIGEST_SUFFIX) {
+        return EntryStatus::Corrupt;
+    }
+
+    match manifest.get(filename) {
+        Some(data) => match get_digest_for_file_within_cache(filename) {
+            Ok(digest) if digest == data.digest => EntryStatus::Current,
+            _ => EntryStatus::Corrupt,
+        },
+        None => EntryStatus::Unknown,
+    }
+}
+
+/// Prints every entry followed by the count and size of the entries of each status.
+pub fn print_status(entries: &[CacheEntry]) {
+    println!("parameter cache: {:?}", parameter_cache_dir());
+    println!();
+
+    for entry in entries {
+        println!(
+            "{:<8} {:>14} {}",
+            entry.status.name(),
+            entry.size,
+            entry.filename
+        );
+    }
+    println!();
+
+    for status in &[
+        EntryStatus::Current,
+        EntryStatus::Stale,
+        EntryStatus::Unknown,
+        EntryStatus::Corrupt,
+    ] {
+        let (count, size) = entries
+            .iter()
+            .filter(|entry| entry.status == *status)
+            .fold((0, 0), |(count, size), entry| {
+                (count + 1, size + entry.size)
+            });
+
+        println!("{}: {} files, {} bytes", status.name(), count, size);
+    }
+}
+
+/// Removes the stale and corrupt entries, skipping those locked by a process using them. Returns
+/// the number of entries which could not be removed.
+pub fn collect_garbage(entries: &[CacheEntry], dry_run: bool) -> usize {
+    let mut removed = 0;
+    let mut freed = 0;
+    let mut failed = 0;
+
+    for entry in entries.iter().filter(|entry| entry.status.is_garbage()) {
+        if dry_run {
+            println!("would remove: {} ({})", entry.filename, entry.status.name());
+            removed += 1;
+            freed += entry.size;
+            continue;
+        }
+
+        let path = parameter_cache_dir().join(&entry.filename);
+        let result = if entry.is_ipget_dir {
+            remove_ipget_dir(&path, &entry.filename)
+        } else {
+            remove_cache_entry(&path)
+        };
+        match result {
+            Ok(true) => {
+                println!("removed: {} ({})", entry.filename, entry.status.name());
+                removed += 1;
+                freed += entry.size;
+            }
+            Ok(false) => {
+                println!("{}: in use, skipped", entry.filename);
+                failed += 1;
+            }
+            Err(err) => {
+                println!("{}: error: {}", entry.filename, err);
+                failed += 1;
+            }
+        }
+    }
+
+    println!();
+    if dry_run {
+        println!("would remove {} files, freeing {} bytes", removed, freed);
+    } else {
+        println!("removed {} files, freed {} bytes", removed, freed);
+    }
+
+    failed
+}
+
+/// Removes a directory ipget downloaded to, unless the fetch it belongs to is still running: that
+/// fetch holds the lock on the partial file of the parameter, which is taken here as well, like
+/// `remove_cache_entry` does for the partial file itself. Returns `false` if the lock is held.
+fn remove_ipget_dir(path: &Path, dirname: &str) -> Result<bool> {
+    let filename = dirname.splitn(2, IPGET_DIR_INFIX).next().unwrap_or(dirname);
+    let partial_path = path.with_file_name(format!("{}.{}", filename, PARTIAL_EXT));
+
+    // without a partial file, no fetch is writing to it
+    let _locked = match OpenOptions::new().read(true).open(&partial_path) {
+        Ok(file) => {
+            if let Err(err) = file.try_lock_exclusive() {
+                if err.kind() == fs2::lock_contended_error().kind() {
+                    return Ok(false);
+                }
+                return Err(err.into());
+            }
+            Some(file)
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err.into()),
+    };
+
+    fs::remove_dir_all(path)?;
+
+    Ok(true)
+}
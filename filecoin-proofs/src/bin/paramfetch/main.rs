@@ -0,0 +1,564 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{create_dir_all, remove_file, rename, OpenOptions};
+use std::io;
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::{bail, ensure, Context, Result};
+use blake2b_simd::State as Blake2b;
+use clap::{value_t, values_t, App, Arg, ArgMatches};
+use fs2::FileExt;
+use itertools::Itertools;
+
+use filecoin_proofs::param::*;
+use filecoin_proofs::sector_sizes::sector_size_info;
+use filecoin_proofs::{say, say_inline};
+use storage_proofs::parameter_cache::{
+    parameter_cache_dir, GROTH_PARAMETER_EXT, PARAMETER_CACHE_DIR, PARAMETER_CACHE_ENV_VAR,
+};
+
+use crate::sources::{parse_source, FetchSource, IpgetOptions, IPGET_VERSION};
+
+mod sources;
+
+const ERROR_PARAMETER_FILE: &str = "failed to find file in cache";
+const ERROR_PARAMETER_ID: &str = "failed to find key in manifest";
+
+const DEFAULT_PARAMETERS: &str = include_str!("../../../parameters.json");
+const SOURCE_ENV_VAR: &str = "FIL_PROOFS_PARAMETER_SOURCE";
+
+pub fn main() {
+    fil_logger::init();
+
+    let matches = App::new("paramfetch")
+        .version("1.1")
+        .about(
+            &format!(
+                "
+Set {} to specify Groth parameter and verifying key-cache directory.
+Defaults to '{}'
+",
+                PARAMETER_CACHE_ENV_VAR,
+                PARAMETER_CACHE_DIR
+            )[..],
+        )
+        .after_help(EXIT_CODES_HELP)
+        .arg(
+            Arg::with_name("json")
+                .value_name("JSON")
+                .takes_value(true)
+                .short("j")
+                .long("json")
+                .help("Use specific JSON file, which must be signed by a trusted key (see --trusted-key)"),
+        )
+        .arg(
+            Arg::with_name("trusted-key")
+                .takes_value(true)
+                .long("trusted-key")
+                .env(TRUSTED_KEYS_ENV_VAR)
+                .multiple(true)
+                .number_of_values(1)
+                .value_delimiter(",")
+                .help("Hex encoded ed25519 public key trusted to sign the JSON file given with --json, its signature being read from <JSON>.sig"),
+        )
+        .arg(
+            Arg::with_name("allow-unsigned")
+                .long("allow-unsigned")
+                .help("Accept a JSON file without a signature, e.g. a development manifest"),
+        )
+        .arg(
+            Arg::with_name("retry")
+                .short("r")
+                .long("retry")
+                .conflicts_with("non-interactive")
+                .help("Prompt to retry on failure"),
+        )
+        .arg(
+            Arg::with_name("non-interactive")
+                .long("non-interactive")
+                .help("Never prompt, fetching every file unless -z is given"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .takes_value(true)
+                .long("output")
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .help("Print a JSON report of each file to stdout, the messages going to stderr"),
+        )
+        .arg(
+            Arg::with_name("all")
+                .short("a")
+                .long("all")
+                .conflicts_with("params-for-sector-sizes")
+                .help("Download all available parameters and verifying keys"),
+        )
+        .arg(
+            Arg::with_name("params-for-sector-sizes")
+                .short("z")
+                .long("params-for-sector-sizes")
+                .conflicts_with("all")
+                .require_delimiter(true)
+                .value_delimiter(",")
+                .multiple(true)
+                .help("A comma-separated list of sector sizes, in bytes, for which Groth parameters will be downloaded"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Print diagnostic information to stdout"),
+        )
+        .arg(
+            Arg::with_name("source")
+                .takes_value(true)
+                .short("s")
+                .long("source")
+                .env(SOURCE_ENV_VAR)
+                .help("Where to fetch from: ipget (default), gateway[:<url>] for an IPFS HTTP gateway (https://ipfs.io by default), dir:<path> or file://<path> for a local directory or mirror"),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .takes_value(true)
+                .long("jobs")
+                .default_value("4")
+                .help("Number of files to download at once"),
+        )
+        .arg(
+            Arg::with_name("ipget-bin")
+                .conflicts_with("ipget-version")
+                .takes_value(true)
+                .short("i")
+                .long("ipget-bin")
+                .help("Use specific ipget binary instead of looking for (or installing) one in /var/tmp/ipget/ipget"),
+        )
+        .arg(
+            Arg::with_name("ipget-args")
+                .takes_value(true)
+                .long("ipget-args")
+                .help("Specify additional arguments for ipget")
+        )
+        .arg(
+            Arg::with_name("ipget-version")
+                .conflicts_with("ipget-bin")
+                .long("ipget-version")
+                .takes_value(true)
+                .help("Set the version of ipget to use")
+        )
+        .get_matches();
+
+    let json_output = matches.value_of("output") == Some("json");
+    set_json_output(json_output);
+
+    match fetch(&matches) {
+        Ok(reports) => {
+            if json_output {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&reports).expect("failed to serialize report")
+                );
+            }
+
+            let detailed = json_output || matches.is_present("non-interactive");
+            let code = exit_code(&reports, detailed);
+            if code == EXIT_FAILED {
+                say!("fatal error: some files failed to be fetched. try again, or run paramcache to generate locally");
+                exit(EXIT_FAILED);
+            }
+
+            say!("done");
+            exit(code);
+        }
+        Err(err) => {
+            say_fatal_error(&err);
+            exit(EXIT_FAILED);
+        }
+    }
+}
+
+fn fetch(matches: &ArgMatches) -> Result<Vec<ParameterReport>> {
+    let manifest = if matches.is_present("json") {
+        let json_path = PathBuf::from(matches.value_of("json").unwrap());
+        say!("using JSON file: {:?}", json_path);
+
+        if !json_path.exists() {
+            bail!(
+                "JSON file '{}' does not exist",
+                &json_path.to_str().unwrap_or("")
+            );
+        }
+
+        let bytes = std::fs::read(&json_path)?;
+        verify_manifest_file(matches, &json_path, &bytes)?;
+
+        serde_json::from_slice(&bytes).with_context(|| {
+            format!(
+                "JSON file '{}' did not parse correctly",
+                &json_path.to_str().unwrap_or(""),
+            )
+        })?
+    } else {
+        say!("using built-in manifest");
+        serde_json::from_str(&DEFAULT_PARAMETERS)?
+    };
+
+    let retry = matches.is_present("retry");
+    let non_interactive = matches.is_present("non-interactive");
+
+    let mut filenames = get_filenames_from_parameter_map(&manifest)?;
+    let mut reports: BTreeMap<String, ParameterReport> = filenames
+        .iter()
+        .map(|filename| {
+            let mut report = ParameterReport::new(filename, ParameterAction::Skipped);
+            if let Some(data) = manifest.get(filename) {
+                report.sector_size = Some(data.sector_size);
+                report.expected_digest = Some(data.digest.clone());
+                report.cid = Some(data.cid.clone());
+            }
+            (filename.clone(), report)
+        })
+        .collect();
+
+    say!("{} files in manifest...", filenames.len());
+    say!();
+
+    // if user has specified sector sizes for which they wish to download Groth
+    // parameters, trim non-matching Groth parameter filenames from the list
+    if matches.is_present("params-for-sector-sizes") {
+        let whitelisted_sector_sizes: HashSet<u64> =
+            values_t!(matches.values_of("params-for-sector-sizes"), u64)?
+                .into_iter()
+                .collect();
+
+        for sector_size in &whitelisted_sector_sizes {
+            ensure!(
+                sector_size_info(*sector_size).is_ok()
+                    || manifest.values().any(|p| p.sector_size == *sector_size),
+                "sector size {} is neither registered nor in the manifest",
+                sector_size
+            );
+        }
+
+        // always download all verifying keys - but conditionally skip Groth
+        // parameters for sector sizes the user doesn't care about
+        filenames = filenames
+            .into_iter()
+            .filter(|id| {
+                !has_extension(id, GROTH_PARAMETER_EXT) || {
+                    manifest
+                        .get(id)
+                        .map(|p| p.sector_size)
+                        .map(|n| whitelisted_sector_sizes.contains(&n))
+                        .unwrap_or(false)
+                }
+            })
+            .collect_vec();
+    }
+
+    say!("{} files to check for (re)download...", filenames.len());
+    say!();
+
+    // ensure filename corresponds to asset on disk and that its checksum
+    // matches that which is specified in the manifest
+    filenames = get_filenames_requiring_download(&manifest, filenames, &mut reports)?;
+
+    // don't prompt the user to download files if they've used certain flags
+    if !matches.is_present("params-for-sector-sizes")
+        && !matches.is_present("all")
+        && !non_interactive
+        && !filenames.is_empty()
+    {
+        filenames = choose_from(&filenames, |filename| {
+            manifest.get(filename).map(|x| x.sector_size)
+        })?;
+        say!();
+    }
+
+    let is_verbose = matches.is_present("verbose");
+    let source = parse_source(
+        matches.value_of("source").unwrap_or("ipget"),
+        IpgetOptions {
+            bin_path: matches.value_of("ipget-bin"),
+            version: matches.value_of("ipget-version").unwrap_or(IPGET_VERSION),
+            args: matches.value_of("ipget-args"),
+        },
+        // progress bars and ipget output would end up in the report
+        is_verbose && !json_output(),
+    )?;
+    say!("fetching from {}", source.describe());
+    say!();
+
+    let source: Arc<dyn FetchSource> = Arc::from(source);
+    let manifest = Arc::new(manifest);
+    let jobs = value_t!(matches, "jobs", usize)?;
+    ensure!(jobs > 0, "--jobs must be at least 1");
+
+    loop {
+        say!("{} files to fetch...", filenames.len());
+        say!();
+
+        for (filename, res) in fetch_parameter_files(&source, &manifest, &filenames, jobs) {
+            let report = reports.get_mut(&filename).expect("file not in manifest");
+            match res {
+                Ok(_) => {
+                    report.action = ParameterAction::Fetched;
+                    report.error = None;
+                }
+                Err(err) => {
+                    report.action = ParameterAction::Failed;
+                    report.error = Some(err.to_string());
+                }
+            }
+        }
+
+        // if we haven't downloaded a valid copy of each asset specified in the
+        // manifest, ask the user if they wish to try again
+        filenames = get_filenames_requiring_download(&manifest, filenames, &mut reports)?;
+
+        if filenames.is_empty() {
+            break;
+        } else {
+            say!("{} files failed to be fetched:", filenames.len());
+
+            for parameter_id in &filenames {
+                say!("{}", parameter_id);
+            }
+
+            say!();
+
+            if !retry || !choose("try again?") {
+                break;
+            }
+        }
+    }
+
+    Ok(reports.into_iter().map(|(_, report)| report).collect())
+}
+
+/// Fetches `filenames` from `source`, `jobs` files at a time, returning the outcome for each.
+fn fetch_parameter_files(
+    source: &Arc<dyn FetchSource>,
+    parameter_map: &Arc<ParameterMap>,
+    filenames: &[String],
+    jobs: usize,
+) -> Vec<(String, Result<()>)> {
+    let queue = Arc::new(Mutex::new(filenames.to_vec().into_iter()));
+
+    let workers: Vec<_> = (0..jobs.min(filenames.len()))
+        .map(|_| {
+            let queue = queue.clone();
+            let source = source.clone();
+            let parameter_map = parameter_map.clone();
+
+            thread::spawn(move || {
+                let mut results = Vec::new();
+                loop {
+                    let filename = match queue.lock().unwrap().next() {
+                        Some(filename) => filename,
+                        None => break,
+                    };
+
+                    say!("fetching: {}", filename);
+                    let res = fetch_parameter_file(source.as_ref(), &parameter_map, &filename);
+                    match &res {
+                        Ok(_) => say!("{}: ok\n", filename),
+                        Err(err) => say!("{}: error: {}\n", filename, err),
+                    }
+                    results.push((filename, res));
+                }
+                results
+            })
+        })
+        .collect();
+
+    workers
+        .into_iter()
+        .flat_map(|worker| worker.join().expect("fetch worker panicked"))
+        .collect()
+}
+
+/// Fetches a file from `source` into the cache, checking its digest against the manifest
+/// whichever the source.
+///
+/// The file is downloaded to `<file>.partial`, hashing it along the way, and only renamed into
+/// place once its digest matches. If the source supports it, an existing partial file is resumed
+/// rather than downloaded again. A partial file whose digest does not match is removed. The
+/// partial file is locked while it is written, failing the fetch if another process holds it.
+fn fetch_parameter_file(
+    source: &dyn FetchSource,
+    parameter_map: &ParameterMap,
+    filename: &str,
+) -> Result<()> {
+    let parameter_data = parameter_map_lookup(parameter_map, filename)?;
+    let path = get_full_path_for_file_within_cache(filename);
+    let partial_path = path.with_file_name(format!("{}.{}", filename, PARTIAL_EXT));
+
+    create_dir_all(parameter_cache_dir())?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .open(&partial_path)
+        .with_context(|| format!("could not open path={:?}", partial_path))?;
+    // another paramfetch writing to the same partial file would corrupt it
+    if let Err(err) = file.try_lock_exclusive() {
+        if err.kind() == fs2::lock_contended_error().kind() {
+            bail!("{} is being fetched by another process", filename);
+        }
+        return Err(err).with_context(|| format!("could not lock path={:?}", partial_path));
+    }
+
+    let mut hasher = Blake2b::new();
+    let offset = if source.supports_resume() {
+        io::copy(&mut file, &mut hasher)?
+    } else {
+        file.set_len(0)?;
+        0
+    };
+    if offset > 0 {
+        say!("{}: resuming from byte {}", filename, offset);
+    }
+
+    let mut out = DigestWriter {
+        inner: BufWriter::new(file),
+        hasher,
+    };
+
+    source.fetch(filename, parameter_data, offset, &mut out)?;
+    out.inner.flush()?;
+    out.inner
+        .get_ref()
+        .sync_all()
+        .with_context(|| format!("could not sync path={:?}", partial_path))?;
+
+    if digest_from_state(&out.hasher) != parameter_data.digest {
+        remove_file(&partial_path)?;
+        bail!("digest of {} does not match the manifest", filename);
+    }
+
+    rename(&partial_path, &path)?;
+
+    Ok(())
+}
+
+/// Hashes everything written through it.
+struct DigestWriter<W> {
+    inner: W,
+    hasher: Blake2b,
+}
+
+impl<W: Write> Write for DigestWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns the files of `parameter_ids` which are missing from the cache or invalid, recording
+/// the valid ones as present in `reports`.
+fn get_filenames_requiring_download(
+    parameter_map: &ParameterMap,
+    parameter_ids: Vec<String>,
+    reports: &mut BTreeMap<String, ParameterReport>,
+) -> Result<Vec<String>> {
+    Ok(parameter_ids
+        .into_iter()
+        .filter(|parameter_id| {
+            say!("checking: {}", parameter_id);
+            say_inline!("does file exist... ");
+
+            let report = reports.get_mut(parameter_id).expect("file not in manifest");
+            if get_full_path_for_file_within_cache(parameter_id).exists() {
+                say!("yes");
+                say_inline!("is file valid... ");
+
+                match validate_parameter_file(&parameter_map, &parameter_id) {
+                    Ok((true, digest)) => {
+                        say!("yes\n");
+                        report.actual_digest = Some(digest);
+                        if report.action != ParameterAction::Fetched {
+                            report.action = ParameterAction::Present;
+                        }
+                        false
+                    }
+                    Ok((false, digest)) => {
+                        say!("no\n");
+                        report.actual_digest = Some(digest);
+                        invalidate_parameter_file(&parameter_id).unwrap();
+                        true
+                    }
+                    Err(err) => {
+                        say!("error: {}\n", err);
+                        report.error = Some(err.to_string());
+                        true
+                    }
+                }
+            } else {
+                say!("no\n");
+                true
+            }
+        })
+        .collect())
+}
+
+/// Checks the signature of the manifest at `json_path`, with contents `manifest`, against the
+/// trusted keys. A manifest without a signature is only accepted with `--allow-unsigned`.
+fn verify_manifest_file(matches: &ArgMatches, json_path: &Path, manifest: &[u8]) -> Result<()> {
+    let trusted_keys = parse_trusted_keys(matches.values_of("trusted-key"))?;
+
+    match check_manifest_signature(json_path, manifest, &trusted_keys)? {
+        Some(public_key) => say!("manifest signed by trusted key {}", public_key),
+        None => {
+            ensure!(
+                matches.is_present("allow-unsigned"),
+                "JSON file '{}' is not signed, pass --allow-unsigned to use it anyway",
+                json_path.display()
+            );
+            say!("warning: using unsigned JSON file");
+        }
+    }
+
+    Ok(())
+}
+
+fn get_filenames_from_parameter_map(parameter_map: &ParameterMap) -> Result<Vec<String>> {
+    Ok(parameter_map.iter().map(|(k, _)| k.clone()).collect())
+}
+
+/// Returns whether the digest of the cached file matches the manifest, and the digest.
+fn validate_parameter_file(parameter_map: &ParameterMap, filename: &str) -> Result<(bool, String)> {
+    let parameter_data = parameter_map_lookup(parameter_map, filename)?;
+    let digest = get_digest_for_file_within_cache(filename)?;
+
+    Ok((parameter_data.digest == digest, digest))
+}
+
+fn invalidate_parameter_file(filename: &str) -> Result<()> {
+    let parameter_file_path = get_full_path_for_file_within_cache(filename);
+    let target_parameter_file_path =
+        parameter_file_path.with_file_name(format!("{}{}", filename, INVALID_DIGEST_SUFFIX));
+
+    ensure!(parameter_file_path.exists(), ERROR_PARAMETER_FILE);
+    rename(parameter_file_path, target_parameter_file_path)?;
+
+    Ok(())
+}
+
+fn parameter_map_lookup<'a>(
+    parameter_map: &'a ParameterMap,
+    filename: &str,
+) -> Result<&'a ParameterData> {
+    ensure!(parameter_map.contains_key(filename), ERROR_PARAMETER_ID);
+
+    Ok(parameter_map.get(filename).unwrap())
+}
@@ -0,0 +1,403 @@
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::{self, copy, SeekFrom, Stdout};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, ensure, Context, Result};
+use flate2::read::GzDecoder;
+use pbr::{ProgressBar, Units};
+use reqwest::{header, Client, Proxy, StatusCode, Url};
+use tar::Archive;
+
+use filecoin_proofs::param::{ParameterData, IPGET_DIR_INFIX};
+use storage_proofs::parameter_cache::parameter_cache_dir;
+
+const IPGET_PATH: &str = "/var/tmp/ipget";
+pub const IPGET_VERSION: &str = "v0.4.0";
+pub const DEFAULT_GATEWAY: &str = "https://ipfs.io";
+
+/// Where parameter files are fetched from.
+pub trait FetchSource: Send + Sync {
+    /// Describes the source in messages to the user.
+    fn describe(&self) -> String;
+
+    /// Whether `fetch` can start part way through a file, to resume an interrupted download.
+    fn supports_resume(&self) -> bool {
+        false
+    }
+
+    /// Writes the file `filename`, described by `parameter` in the manifest, to `out`, starting
+    /// at byte `offset`. `offset` is always zero unless the source supports resuming.
+    fn fetch(
+        &self,
+        filename: &str,
+        parameter: &ParameterData,
+        offset: u64,
+        out: &mut dyn Write,
+    ) -> Result<()>;
+}
+
+/// Fetches from IPFS with an `ipget` binary, by the `cid` of each file.
+pub struct IpgetSource {
+    bin_path: PathBuf,
+    args: Option<String>,
+    is_verbose: bool,
+}
+
+impl IpgetSource {
+    /// Uses the ipget binary at `bin_path`, or the given version of ipget, installed into
+    /// `/var/tmp` if it is not there yet.
+    pub fn new(
+        bin_path: Option<&str>,
+        version: &str,
+        args: Option<&str>,
+        is_verbose: bool,
+    ) -> Result<Self> {
+        let bin_path = match bin_path {
+            Some(bin_path) => PathBuf::from(bin_path),
+            None => {
+                ensure_ipget(is_verbose, version)?;
+                PathBuf::from(get_ipget_bin(version))
+            }
+        };
+
+        Ok(IpgetSource {
+            bin_path,
+            args: args.map(ToString::to_string),
+            is_verbose,
+        })
+    }
+}
+
+impl FetchSource for IpgetSource {
+    fn describe(&self) -> String {
+        format!("ipget ({})", self.bin_path.display())
+    }
+
+    fn fetch(
+        &self,
+        filename: &str,
+        parameter: &ParameterData,
+        _offset: u64,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        // ipget writes to a path of its own, which is then copied to `out`. It is in a directory
+        // of its own next to the cache, so that concurrent fetches of the same file do not share
+        // it, and removed with the directory.
+        let target_dir = tempfile::Builder::new()
+            .prefix(&format!("{}{}", filename, IPGET_DIR_INFIX))
+            .tempdir_in(parameter_cache_dir())
+            .context("could not create a directory for ipget")?;
+        let target = target_dir.path().join(&parameter.cid);
+
+        let mut cmd = Command::new(self.bin_path.as_os_str());
+        cmd.arg("-o")
+            .arg(target.to_str().context("invalid target path")?)
+            .arg(&parameter.cid);
+
+        if let Some(args) = &self.args {
+            cmd.args(args.split(' '));
+        }
+
+        let output = cmd.output()?;
+
+        if self.is_verbose {
+            io::stdout().write_all(&output.stdout)?;
+            io::stderr().write_all(&output.stderr)?;
+        }
+
+        ensure!(
+            output.status.success(),
+            "failed to download {}",
+            parameter.cid
+        );
+
+        let mut file =
+            File::open(&target).with_context(|| format!("could not open path={:?}", target))?;
+        copy(&mut file, out)?;
+
+        Ok(())
+    }
+}
+
+/// Fetches over HTTP(S) from an IPFS gateway, by the `cid` of each file.
+pub struct GatewaySource {
+    url: Url,
+    client: Client,
+    is_verbose: bool,
+}
+
+impl GatewaySource {
+    pub fn new(url: &str, is_verbose: bool) -> Result<Self> {
+        // `Url::join` replaces the last path segment unless the path ends with a slash.
+        let mut url = Url::parse(url).with_context(|| format!("invalid gateway url {}", url))?;
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+
+        Ok(GatewaySource {
+            url,
+            client: http_client()?,
+            is_verbose,
+        })
+    }
+}
+
+impl FetchSource for GatewaySource {
+    fn describe(&self) -> String {
+        format!("gateway {}", self.url)
+    }
+
+    fn supports_resume(&self) -> bool {
+        true
+    }
+
+    fn fetch(
+        &self,
+        _filename: &str,
+        parameter: &ParameterData,
+        offset: u64,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        let url = self.url.join(&format!("ipfs/{}", parameter.cid))?;
+
+        download(&self.client, url, offset, out, self.is_verbose)
+    }
+}
+
+/// Copies from a local directory, or a mirror mounted locally, holding the files by digest (as
+/// written by `parampublish --target dir:<path>`) or by name.
+pub struct DirSource {
+    path: PathBuf,
+}
+
+impl DirSource {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let path = path.into();
+        ensure!(path.is_dir(), "source directory {:?} does not exist", path);
+
+        Ok(DirSource { path })
+    }
+}
+
+impl FetchSource for DirSource {
+    fn describe(&self) -> String {
+        format!("directory {}", self.path.display())
+    }
+
+    fn supports_resume(&self) -> bool {
+        true
+    }
+
+    fn fetch(
+        &self,
+        filename: &str,
+        parameter: &ParameterData,
+        offset: u64,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        // a digest from the manifest is only used as a path if it cannot leave the directory
+        let digest = &parameter.digest;
+        let by_digest = self.path.join(digest);
+        let source = if !digest.is_empty()
+            && digest.chars().all(|c| c.is_ascii_hexdigit())
+            && by_digest.exists()
+        {
+            by_digest
+        } else {
+            self.path.join(filename)
+        };
+        ensure!(
+            source.exists(),
+            "{:?} not found in source directory",
+            filename
+        );
+
+        let mut file =
+            File::open(&source).with_context(|| format!("could not open path={:?}", source))?;
+        file.seek(SeekFrom::Start(offset))?;
+        copy(&mut file, out).with_context(|| format!("failed to copy {:?}", source))?;
+
+        Ok(())
+    }
+}
+
+/// Options for the ipget source, which is the only one with options of its own.
+pub struct IpgetOptions<'a> {
+    pub bin_path: Option<&'a str>,
+    pub version: &'a str,
+    pub args: Option<&'a str>,
+}
+
+/// Parses a source given on the command line:
+///
+/// * `ipget` - IPFS, through ipget.
+/// * `gateway` or `gateway:<url>` - an IPFS HTTP gateway, `https://ipfs.io` by default.
+/// * `dir:<path>` or `file://<path>` - a local directory or mirror.
+pub fn parse_source(
+    spec: &str,
+    ipget: IpgetOptions,
+    is_verbose: bool,
+) -> Result<Box<dyn FetchSource>> {
+    let source: Box<dyn FetchSource> = if spec == "ipget" {
+        Box::new(IpgetSource::new(
+            ipget.bin_path,
+            ipget.version,
+            ipget.args,
+            is_verbose,
+        )?)
+    } else if spec == "gateway" {
+        Box::new(GatewaySource::new(DEFAULT_GATEWAY, is_verbose)?)
+    } else if spec.starts_with("gateway:") {
+        Box::new(GatewaySource::new(
+            spec.trim_start_matches("gateway:"),
+            is_verbose,
+        )?)
+    } else if spec.starts_with("dir:") {
+        Box::new(DirSource::new(spec.trim_start_matches("dir:"))?)
+    } else if spec.starts_with("file://") {
+        Box::new(DirSource::new(spec.trim_start_matches("file://"))?)
+    } else {
+        bail!(
+            "unknown source {:?}, expected ipget, gateway[:<url>], dir:<path> or file://<path>",
+            spec
+        );
+    };
+
+    Ok(source)
+}
+
+struct FetchProgress<R> {
+    inner: R,
+    progress_bar: ProgressBar<Stdout>,
+}
+
+impl<R: Read> Read for FetchProgress<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf).map(|n| {
+            self.progress_bar.add(n as u64);
+            n
+        })
+    }
+}
+
+fn get_ipget_bin(version: &str) -> String {
+    format!("{}-{}/ipget/ipget", IPGET_PATH, version)
+}
+
+/// Check if ipget is available, dowwnload it otherwise.
+fn ensure_ipget(is_verbose: bool, version: &str) -> Result<()> {
+    let ipget_bin = get_ipget_bin(version);
+    if Path::new(&ipget_bin).exists() {
+        Ok(())
+    } else {
+        download_ipget(is_verbose, version)
+    }
+    .map(|_| {
+        if is_verbose {
+            println!("ipget installed: {}", ipget_bin);
+        }
+    })
+}
+
+/// Download a version of ipget.
+fn download_ipget(is_verbose: bool, version: &str) -> Result<()> {
+    let (os, extension) = if cfg!(target_os = "macos") {
+        ("darwin", "tar.gz")
+    } else if cfg!(target_os = "windows") {
+        ("windows", "zip")
+    } else {
+        ("linux", "tar.gz")
+    };
+
+    let url = Url::parse(&format!(
+        "https://dist.ipfs.io/ipget/{}/ipget_{}_{}-amd64.{}",
+        version, version, os, extension
+    ))?;
+
+    if is_verbose {
+        println!("downloading ipget@{}-{}...", version, os);
+    }
+
+    // download file
+    let p = format!("{}-{}.{}", IPGET_PATH, version, extension);
+    download_file(url, &p, is_verbose)?;
+
+    // extract file
+    if extension == "tar.gz" {
+        let tar_gz = fs::File::open(p)?;
+        let tar = GzDecoder::new(tar_gz);
+        let mut archive = Archive::new(tar);
+        archive.unpack(format!("/var/tmp/ipget-{}", version))?;
+    } else {
+        // TODO: handle zip archives on windows
+        unimplemented!("failed to install ipget: unzip is not yet supported");
+    }
+
+    Ok(())
+}
+
+fn http_client() -> Result<Client> {
+    let client = Client::builder()
+        .proxy(Proxy::custom(move |url| env_proxy::for_url(&url).to_url()))
+        .build()?;
+
+    Ok(client)
+}
+
+/// Download the given file.
+fn download_file(url: Url, target: impl AsRef<Path>, is_verbose: bool) -> Result<()> {
+    let mut file = File::create(target)?;
+
+    download(&http_client()?, url, 0, &mut file, is_verbose)
+}
+
+/// Downloads `url` from byte `offset` on, with a range request, into `out`.
+fn download(
+    client: &Client,
+    url: Url,
+    offset: u64,
+    out: &mut dyn Write,
+    is_verbose: bool,
+) -> Result<()> {
+    let mut req = client.get(url.as_str());
+    if offset > 0 {
+        req = req.header(header::RANGE, format!("bytes={}-", offset));
+    }
+
+    let res = req.send()?;
+    let skip = match res.status() {
+        StatusCode::PARTIAL_CONTENT => 0,
+        // the server ignored the range, the bytes we already have are skipped instead
+        status if status.is_success() => offset,
+        // everything up to `offset` is all there is
+        StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => return Ok(()),
+        status => bail!("failed to download file: {} ({})", url, status),
+    };
+
+    let remaining = res
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|ct_len| ct_len.to_str().ok())
+        .and_then(|ct_len| ct_len.parse::<u64>().ok())
+        .unwrap_or(0);
+
+    let mut source: Box<dyn Read> = if is_verbose {
+        let mut pb = ProgressBar::new(remaining);
+        pb.set_units(Units::Bytes);
+
+        Box::new(FetchProgress {
+            inner: res,
+            progress_bar: pb,
+        })
+    } else {
+        Box::new(res)
+    };
+
+    copy(&mut (&mut source).take(skip), &mut io::sink())?;
+    copy(&mut source, out)?;
+
+    Ok(())
+}
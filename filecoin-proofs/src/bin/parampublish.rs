@@ -1,16 +1,21 @@
 use std::collections::BTreeMap;
-use std::fs::{read_dir, File};
+use std::fs::{self, copy, create_dir_all, read_dir, remove_file, rename, File, OpenOptions};
 use std::io;
 use std::io::prelude::*;
 use std::io::BufWriter;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
 use std::path::{Path, PathBuf};
 use std::process::{exit, Command};
 
-use anyhow::{ensure, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use clap::{App, Arg, ArgMatches};
+use ed25519_dalek::Keypair;
 use itertools::Itertools;
+use rand::rngs::OsRng;
 
 use filecoin_proofs::param::*;
+use filecoin_proofs::{say, say_inline};
 use storage_proofs::parameter_cache::{
     parameter_cache_dir, CacheEntryMetadata, GROTH_PARAMETER_EXT, PARAMETER_CACHE_DIR,
     PARAMETER_METADATA_EXT, VERIFYING_KEY_EXT,
@@ -18,6 +23,7 @@ use storage_proofs::parameter_cache::{
 
 const ERROR_IPFS_COMMAND: &str = "failed to run ipfs";
 const ERROR_IPFS_PUBLISH: &str = "failed to publish via ipfs";
+const SIGNING_KEY_ENV_VAR: &str = "FIL_PROOFS_MANIFEST_SIGNING_KEY";
 
 pub fn main() {
     fil_logger::init();
@@ -33,6 +39,7 @@ Defaults to '{}'
                 PARAMETER_CACHE_DIR
             )[..],
         )
+        .after_help(EXIT_CODES_HELP)
         .arg(
             Arg::with_name("json")
                 .value_name("JSON")
@@ -41,12 +48,53 @@ Defaults to '{}'
                 .long("json")
                 .help("Use specific json file"),
         )
+        .arg(
+            Arg::with_name("signing-key")
+                .value_name("KEY_FILE")
+                .takes_value(true)
+                .long("signing-key")
+                .env(SIGNING_KEY_ENV_VAR)
+                .help("Sign the JSON file with the hex encoded ed25519 secret key in KEY_FILE, writing the signature to <JSON>.sig"),
+        )
+        .arg(
+            Arg::with_name("allow-unverified-mirror")
+                .long("allow-unverified-mirror")
+                .help("With --target dir: and --signing-key, merge into the mirror's JSON file even if it is unsigned or not signed by the signing key"),
+        )
+        .arg(
+            Arg::with_name("generate-signing-key")
+                .value_name("KEY_FILE")
+                .takes_value(true)
+                .long("generate-signing-key")
+                .help("Write a new ed25519 secret key to KEY_FILE, print its public key and exit"),
+        )
         .arg(
             Arg::with_name("all")
                 .short("a")
                 .long("all")
                 .help("Publish all local Groth parameters and verifying keys"),
         )
+        .arg(
+            Arg::with_name("non-interactive")
+                .long("non-interactive")
+                .help("Never prompt, publishing every file"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .takes_value(true)
+                .long("output")
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .help("Print a JSON report of each file to stdout, the messages going to stderr"),
+        )
+        .arg(
+            Arg::with_name("target")
+                .takes_value(true)
+                .short("t")
+                .long("target")
+                .default_value("ipfs")
+                .help("Where to publish to: ipfs (default), or dir:<path> for a mirror directory holding each file under its digest, with the JSON file (<path>/parameters.json unless --json is given), which paramfetch --source dir:<path> fetches from"),
+        )
         .arg(
             Arg::with_name("ipfs-bin")
                 .takes_value(true)
@@ -56,17 +104,47 @@ Defaults to '{}'
         )
         .get_matches();
 
+    if let Some(key_path) = matches.value_of("generate-signing-key") {
+        match generate_signing_key(key_path) {
+            Ok(public_key) => {
+                println!("public key: {}", public_key);
+                exit(0);
+            }
+            Err(err) => {
+                println!("fatal error: {}", err);
+                exit(EXIT_FAILED);
+            }
+        }
+    }
+
+    let json_output = matches.value_of("output") == Some("json");
+    set_json_output(json_output);
+
     match publish(&matches) {
-        Ok(_) => println!("done"),
+        Ok(reports) => {
+            if json_output {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&reports).expect("failed to serialize report")
+                );
+            }
+
+            say!("done");
+            let detailed = json_output || matches.is_present("non-interactive");
+            exit(exit_code(&reports, detailed));
+        }
         Err(err) => {
-            println!("fatal error: {}", err);
-            exit(1);
+            say_fatal_error(&err);
+            exit(EXIT_FAILED);
         }
     }
 }
 
-fn publish(matches: &ArgMatches) -> Result<()> {
-    let ipfs_bin_path = matches.value_of("ipfs-bin").unwrap_or("ipfs");
+fn publish(matches: &ArgMatches) -> Result<Vec<ParameterReport>> {
+    let target = parse_target(
+        matches.value_of("target").unwrap_or("ipfs"),
+        matches.value_of("ipfs-bin").unwrap_or("ipfs"),
+    )?;
 
     let mut filenames = get_filenames_in_cache_dir()?
         .into_iter()
@@ -86,21 +164,48 @@ fn publish(matches: &ArgMatches) -> Result<()> {
         .filter(|f| !has_extension(f, PARAMETER_METADATA_EXT))
         .collect_vec();
 
-    if !matches.is_present("all") {
+    let mut reports: BTreeMap<String, ParameterReport> = filenames
+        .iter()
+        .map(|filename| {
+            let mut report = ParameterReport::new(filename, ParameterAction::Skipped);
+            report.sector_size = filename_to_parameter_id(filename)
+                .and_then(|id| meta_map.get(&id))
+                .map(|meta| meta.sector_size);
+            (filename.clone(), report)
+        })
+        .collect();
+
+    if !matches.is_present("all") && !matches.is_present("non-interactive") {
         filenames = choose_from(&filenames, |filename| {
             filename_to_parameter_id(PathBuf::from(filename))
                 .as_ref()
                 .and_then(|p_id| meta_map.get(p_id).map(|x| x.sector_size))
         })?;
-        println!();
+        say!();
     };
 
-    let json = PathBuf::from(matches.value_of("json").unwrap_or("./parameters.json"));
-    let mut parameter_map: ParameterMap = BTreeMap::new();
+    let json = match (matches.value_of("json"), &target) {
+        (Some(json), _) => PathBuf::from(json),
+        (None, Target::Dir(path)) => path.join("parameters.json"),
+        (None, Target::Ipfs(_)) => PathBuf::from("./parameters.json"),
+    };
+    let signing_key = matches
+        .value_of("signing-key")
+        .map(read_signing_key)
+        .transpose()?;
+    // a mirror keeps the files of earlier runs, which its manifest has to keep listing
+    let mut parameter_map = match target {
+        Target::Dir(_) if json.exists() => read_mirror_manifest(
+            &json,
+            signing_key.as_ref(),
+            matches.is_present("allow-unverified-mirror"),
+        )?,
+        _ => ParameterMap::new(),
+    };
 
     if !filenames.is_empty() {
-        println!("publishing {} files...", filenames.len());
-        println!();
+        say!("publishing {} files...", filenames.len());
+        say!();
 
         for filename in filenames {
             let id = filename_to_parameter_id(&filename)
@@ -110,39 +215,46 @@ fn publish(matches: &ArgMatches) -> Result<()> {
                 .get(&id)
                 .with_context(|| format!("no metadata found for parameter id {}", id))?;
 
-            println!("publishing: {}", filename);
-            print!("publishing to ipfs... ");
-            io::stdout().flush().unwrap();
+            say!("publishing: {}", filename);
+            say_inline!("generating digest... ");
+
+            let digest = get_digest_for_file_within_cache(&filename)?;
+            say!("ok");
+            say_inline!("publishing to {}... ", target.describe());
 
-            match publish_parameter_file(&ipfs_bin_path, &filename) {
+            let report = reports.get_mut(&filename).expect("file not in cache");
+            report.actual_digest = Some(digest.clone());
+            match publish_parameter_file(&target, &filename, &digest) {
                 Ok(cid) => {
-                    println!("ok");
-                    print!("generating digest... ");
-                    io::stdout().flush().unwrap();
+                    report.action = ParameterAction::Published;
+                    report.cid = cid.clone();
 
-                    let digest = get_digest_for_file_within_cache(&filename)?;
                     let data = ParameterData {
-                        cid,
+                        cid: cid.unwrap_or_default(),
                         digest,
                         sector_size: meta.sector_size,
                     };
 
                     parameter_map.insert(filename, data);
 
-                    println!("ok");
+                    say!("ok");
+                }
+                Err(err) => {
+                    say!("error: {:#}", err);
+                    report.action = ParameterAction::Failed;
+                    report.error = Some(format!("{:#}", err));
                 }
-                Err(err) => println!("error: {}", err),
             }
 
-            println!();
+            say!();
         }
 
-        write_parameter_map_to_disk(&parameter_map, &json)?;
+        write_parameter_map_to_disk(&parameter_map, &json, signing_key.as_ref())?;
     } else {
-        println!("no files to publish");
+        say!("no files to publish");
     }
 
-    Ok(())
+    Ok(reports.into_iter().map(|(_, report)| report).collect())
 }
 
 fn get_filenames_in_cache_dir() -> Result<Vec<String>> {
@@ -162,7 +274,7 @@ fn get_filenames_in_cache_dir() -> Result<Vec<String>> {
             })
             .collect())
     } else {
-        println!(
+        say!(
             "parameter directory '{}' does not exist",
             path.as_path().to_str().unwrap()
         );
@@ -171,29 +283,157 @@ fn get_filenames_in_cache_dir() -> Result<Vec<String>> {
     }
 }
 
-fn publish_parameter_file(ipfs_bin_path: &str, filename: &str) -> Result<String> {
+/// Where parameter files are published to.
+enum Target {
+    /// IPFS, through the `ipfs` binary at the given path.
+    Ipfs(String),
+    /// A mirror directory holding each file under its digest.
+    Dir(PathBuf),
+}
+
+impl Target {
+    fn describe(&self) -> String {
+        match self {
+            Target::Ipfs(_) => "ipfs".to_string(),
+            Target::Dir(path) => format!("directory {}", path.display()),
+        }
+    }
+}
+
+/// Parses a target given on the command line, `ipfs` or `dir:<path>`, creating the directory.
+fn parse_target(spec: &str, ipfs_bin_path: &str) -> Result<Target> {
+    if spec == "ipfs" {
+        Ok(Target::Ipfs(ipfs_bin_path.to_string()))
+    } else if spec.starts_with("dir:") {
+        let path = PathBuf::from(spec.trim_start_matches("dir:"));
+        create_dir_all(&path)
+            .with_context(|| format!("could not create target directory {:?}", path))?;
+
+        Ok(Target::Dir(path))
+    } else {
+        bail!("unknown target {:?}, expected ipfs or dir:<path>", spec);
+    }
+}
+
+/// Publishes a file of the cache, returning its cid if published to IPFS.
+fn publish_parameter_file(target: &Target, filename: &str, digest: &str) -> Result<Option<String>> {
     let path = get_full_path_for_file_within_cache(filename);
 
-    let output = Command::new(ipfs_bin_path)
-        .arg("add")
-        .arg("-Q")
-        .arg(&path)
-        .output()
-        .expect(ERROR_IPFS_COMMAND);
+    match target {
+        Target::Ipfs(ipfs_bin_path) => {
+            let output = Command::new(ipfs_bin_path)
+                .arg("add")
+                .arg("-Q")
+                .arg(&path)
+                .output()
+                .with_context(|| format!("{} {:?}", ERROR_IPFS_COMMAND, ipfs_bin_path))?;
 
-    ensure!(output.status.success(), ERROR_IPFS_PUBLISH);
+            ensure!(output.status.success(), ERROR_IPFS_PUBLISH);
 
-    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+            Ok(Some(String::from_utf8(output.stdout)?.trim().to_string()))
+        }
+        Target::Dir(dir) => {
+            // files with the same contents are only stored once
+            let dest = dir.join(digest);
+            if !dest.exists() {
+                let partial = dir.join(format!("{}.partial", digest));
+                copy(&path, &partial)
+                    .with_context(|| format!("failed to copy {:?} to {:?}", path, partial))?;
+                rename(&partial, &dest)?;
+            }
+
+            Ok(None)
+        }
+    }
+}
+
+/// Reads the manifest of an earlier run at `path`, to add the files of this run to.
+///
+/// The merged manifest is signed with `signing_key`, so the entries of the earlier run have to be
+/// signed by that key already: an unsigned manifest, or one signed by another key, is refused
+/// unless `allow_unverified` is set.
+fn read_mirror_manifest(
+    path: &Path,
+    signing_key: Option<&Keypair>,
+    allow_unverified: bool,
+) -> Result<ParameterMap> {
+    let bytes = fs::read(path).with_context(|| format!("could not open {:?}", path))?;
+
+    if let Some(keypair) = signing_key {
+        let problem = match check_manifest_signature(path, &bytes, &[keypair.public]) {
+            Ok(Some(_)) => None,
+            Ok(None) => Some("is not signed".to_string()),
+            Err(err) => Some(format!("is not signed by the signing key: {:#}", err)),
+        };
+
+        if let Some(problem) = problem {
+            ensure!(
+                allow_unverified,
+                "the manifest {:?} {}, not merging it (see --allow-unverified-mirror)",
+                path,
+                problem
+            );
+            say!(
+                "warning: the manifest {:?} {}, merging it anyway",
+                path,
+                problem
+            );
+        }
+    }
+
+    serde_json::from_slice(&bytes)
+        .with_context(|| format!("could not read the manifest {:?}", path))
 }
 
+/// Writes the manifest to `dest_path` and, given a key, its signature next to it.
 fn write_parameter_map_to_disk<P: AsRef<Path>>(
     parameter_map: &ParameterMap,
     dest_path: P,
+    signing_key: Option<&Keypair>,
 ) -> Result<()> {
     let p: &Path = dest_path.as_ref();
-    let file = File::create(p)?;
-    let writer = BufWriter::new(file);
-    serde_json::to_writer_pretty(writer, &parameter_map)?;
+    let bytes = serde_json::to_vec_pretty(&parameter_map)?;
+    let mut writer = BufWriter::new(File::create(p)?);
+    writer.write_all(&bytes)?;
+    writer.flush()?;
+
+    let sig_path = signature_path(p);
+    match signing_key {
+        Some(keypair) => {
+            let signature = sign_manifest(&bytes, keypair);
+            let writer = BufWriter::new(File::create(&sig_path)?);
+            serde_json::to_writer_pretty(writer, &signature)?;
+            say!("signed manifest with key {}", signature.public_key);
+        }
+        None => {
+            // a signature of an earlier manifest would no longer match
+            if sig_path.exists() {
+                remove_file(&sig_path)?;
+            }
+            say!("manifest is not signed, paramfetch only accepts it with --allow-unsigned");
+        }
+    }
 
     Ok(())
 }
+
+/// Writes a new hex encoded ed25519 secret key to `key_path`, returning the public key.
+fn generate_signing_key(key_path: &str) -> Result<String> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    // only the owner may read the secret key
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(key_path).map_err(|err| {
+        if err.kind() == io::ErrorKind::AlreadyExists {
+            anyhow!("{} already exists, not overwriting it", key_path)
+        } else {
+            anyhow!(err).context(format!("could not create {}", key_path))
+        }
+    })?;
+
+    let keypair = Keypair::generate(&mut OsRng);
+    file.write_all(hex::encode(keypair.secret.as_bytes()).as_bytes())?;
+
+    Ok(hex::encode(keypair.public.as_bytes()))
+}
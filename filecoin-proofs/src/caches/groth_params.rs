@@ -0,0 +1,126 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use lazy_static::lazy_static;
+use paired::bls12_381::Bls12;
+use storage_proofs::circuit::election_post::{ElectionPoStCircuit, ElectionPoStCompound};
+use storage_proofs::circuit::stacked::StackedCompound;
+use storage_proofs::circuit::window_post::{WindowPoStCircuit, WindowPoStCompound};
+use storage_proofs::compound_proof::CompoundProof;
+use storage_proofs::drgraph::DefaultTreeHasher;
+use storage_proofs::election_post::ElectionPoSt;
+use storage_proofs::mapped_parameters::MappedParameters;
+use storage_proofs::settings;
+use storage_proofs::stacked::StackedDrg;
+use storage_proofs::window_post::WindowPoSt;
+
+use super::{cache_lookup, evict_least_recently_used, Cache, CacheEntrySize, CacheLimits};
+use crate::constants::DefaultPieceHasher;
+use crate::error::parameter_error;
+use crate::parameters::{post_public_params, public_params, window_post_public_params};
+use crate::types::*;
+
+pub type Bls12GrothParams = MappedParameters<Bls12>;
+
+type GrothMemCache = Cache<Bls12GrothParams>;
+
+lazy_static! {
+    pub(super) static ref GROTH_PARAM_MEMORY_CACHE: Mutex<GrothMemCache> = Default::default();
+}
+
+impl CacheEntrySize for Bls12GrothParams {
+    fn cache_entry_size(&self) -> u64 {
+        self.mapped_len() as u64
+    }
+}
+
+fn groth_params_limits() -> CacheLimits {
+    let settings = settings::SETTINGS.lock().unwrap();
+
+    CacheLimits {
+        max_entries: match settings.max_cached_groth_params {
+            0 => None,
+            n => Some(n),
+        },
+        max_bytes: match settings.max_cached_groth_params_bytes {
+            0 => None,
+            n => Some(n),
+        },
+    }
+}
+
+#[inline]
+pub fn lookup_groth_params<F>(identifier: String, generator: F) -> Result<Arc<Bls12GrothParams>>
+where
+    F: FnOnce() -> Result<Bls12GrothParams>,
+{
+    cache_lookup(
+        &*GROTH_PARAM_MEMORY_CACHE,
+        identifier,
+        groth_params_limits(),
+        generator,
+    )
+}
+
+/// Evicts the least recently used Groth parameters until the memory cache fits `limits`.
+pub fn shrink_cached_parameters(limits: CacheLimits) {
+    let cache = &mut GROTH_PARAM_MEMORY_CACHE.lock().unwrap();
+    evict_least_recently_used(cache, "", limits);
+}
+
+pub fn get_stacked_params(porep_config: PoRepConfig) -> Result<Arc<Bls12GrothParams>> {
+    let public_params = public_params(
+        PaddedBytesAmount::from(porep_config),
+        usize::from(PoRepProofPartitions::from(porep_config)),
+        porep_config.params,
+    )?;
+
+    let parameters_generator = || {
+        <StackedCompound as CompoundProof<
+            _,
+            StackedDrg<DefaultTreeHasher, DefaultPieceHasher>,
+            _,
+        >>::mapped_groth_params::<rand::rngs::OsRng>(None, &public_params)
+        .map_err(Into::into)
+    };
+
+    let identifier = porep_config.get_cache_identifier()?;
+    lookup_groth_params(identifier.clone(), parameters_generator)
+        .map_err(parameter_error(identifier))
+}
+
+pub fn get_post_params(post_config: PoStConfig) -> Result<Arc<Bls12GrothParams>> {
+    let post_public_params = post_public_params(post_config)?;
+
+    let parameters_generator = || {
+        <ElectionPoStCompound<DefaultTreeHasher> as CompoundProof<
+            Bls12,
+            ElectionPoSt<DefaultTreeHasher>,
+            ElectionPoStCircuit<Bls12, DefaultTreeHasher>,
+        >>::mapped_groth_params::<rand::rngs::OsRng>(None, &post_public_params)
+        .map_err(Into::into)
+    };
+
+    let identifier = post_config.get_cache_identifier()?;
+    lookup_groth_params(identifier.clone(), parameters_generator)
+        .map_err(parameter_error(identifier))
+}
+
+pub fn get_window_post_params(
+    window_post_config: WindowPoStConfig,
+) -> Result<Arc<Bls12GrothParams>> {
+    let window_post_public_params = window_post_public_params(window_post_config)?;
+
+    let parameters_generator = || {
+        <WindowPoStCompound<DefaultTreeHasher> as CompoundProof<
+            Bls12,
+            WindowPoSt<DefaultTreeHasher>,
+            WindowPoStCircuit<Bls12, DefaultTreeHasher>,
+        >>::mapped_groth_params::<rand::rngs::OsRng>(None, &window_post_public_params)
+        .map_err(Into::into)
+    };
+
+    let identifier = window_post_config.get_cache_identifier()?;
+    lookup_groth_params(identifier.clone(), parameters_generator)
+        .map_err(parameter_error(identifier))
+}
@@ -0,0 +1,590 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::sync::{Mutex, RwLock};
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use bellperson::groth16;
+use lazy_static::lazy_static;
+use log::info;
+use paired::bls12_381::Bls12;
+use storage_proofs::circuit::election_post::ElectionPoStCircuit;
+use storage_proofs::circuit::election_post::ElectionPoStCompound;
+use storage_proofs::circuit::stacked::StackedCompound;
+use storage_proofs::circuit::window_post::{WindowPoStCircuit, WindowPoStCompound};
+use storage_proofs::compound_proof::CompoundProof;
+use storage_proofs::drgraph::DefaultTreeHasher;
+use storage_proofs::election_post::ElectionPoSt;
+#[cfg(feature = "embedded-verifying-keys")]
+use storage_proofs::parameter_cache::parameter_cache_verifying_key_path;
+use storage_proofs::stacked::StackedDrg;
+use storage_proofs::window_post::WindowPoSt;
+
+use crate::constants::DefaultPieceHasher;
+use crate::error::parameter_error;
+use crate::parameters::{post_public_params, public_params, window_post_public_params};
+use crate::types::*;
+
+#[cfg(feature = "prover")]
+mod groth_params;
+
+#[cfg(feature = "prover")]
+use self::groth_params::GROTH_PARAM_MEMORY_CACHE;
+#[cfg(feature = "prover")]
+pub use self::groth_params::{
+    get_post_params, get_stacked_params, get_window_post_params, lookup_groth_params,
+    shrink_cached_parameters, Bls12GrothParams,
+};
+
+pub type Bls12VerifyingKey = groth16::VerifyingKey<Bls12>;
+
+struct CacheEntry<G> {
+    value: Arc<G>,
+    size: u64,
+    last_used: Instant,
+    /// Value of `ACCESS_COUNTER` at the last lookup. Orders entries for eviction, as two lookups
+    /// can read the same `Instant`.
+    last_access: u64,
+}
+
+static ACCESS_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn next_access() -> u64 {
+    ACCESS_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+type Cache<G> = HashMap<String, CacheEntry<G>>;
+type VerifyingKeyMemCache = Cache<Bls12VerifyingKey>;
+type VerifyingKeyRegistry = HashMap<String, Arc<Bls12VerifyingKey>>;
+type ConfigVerifyingKeyRegistry = HashMap<VerifyingKeyConfig, Arc<Bls12VerifyingKey>>;
+
+lazy_static! {
+    static ref VERIFYING_KEY_MEMORY_CACHE: Mutex<VerifyingKeyMemCache> = Default::default();
+    static ref REGISTERED_VERIFYING_KEYS: RwLock<VerifyingKeyRegistry> = Default::default();
+    static ref CONFIG_VERIFYING_KEYS: RwLock<ConfigVerifyingKeyRegistry> = Default::default();
+}
+
+/// The `.vk` files listed in `parameters.json`, as `(file name, contents)`.
+#[cfg(feature = "embedded-verifying-keys")]
+static EMBEDDED_VERIFYING_KEYS: &[(&str, &[u8])] =
+    include!(concat!(env!("OUT_DIR"), "/embedded_verifying_keys.rs"));
+
+/// Bounds applied to a memory cache after each insertion. `None` means unbounded.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheLimits {
+    pub max_entries: Option<usize>,
+    pub max_bytes: Option<u64>,
+}
+
+/// The kind of value held by an entry of the parameter memory caches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachedParameterKind {
+    GrothParams,
+    VerifyingKey,
+}
+
+/// Describes one entry of the parameter memory caches, as returned by `list_cached_parameters`.
+#[derive(Debug, Clone)]
+pub struct CachedParameterInfo {
+    pub identifier: String,
+    pub kind: CachedParameterKind,
+    /// Bytes backing the entry: the mapped file for Groth parameters, the serialized key for
+    /// verifying keys.
+    pub size: u64,
+    pub last_used: Instant,
+}
+
+pub trait CacheEntrySize {
+    fn cache_entry_size(&self) -> u64;
+}
+
+impl CacheEntrySize for Bls12VerifyingKey {
+    fn cache_entry_size(&self) -> u64 {
+        let mut buf = Vec::new();
+        self.write(&mut buf).map(|_| buf.len() as u64).unwrap_or(0)
+    }
+}
+
+/// Looks up `identifier` in the cache, calling `generator` to create the entry if it is missing.
+/// Afterwards the least recently used entries are evicted until the cache fits `limits`. Evicted
+/// entries stay valid for as long as callers hold on to them.
+pub fn cache_lookup<F, G>(
+    cache_ref: &Mutex<Cache<G>>,
+    identifier: String,
+    limits: CacheLimits,
+    generator: F,
+) -> Result<Arc<G>>
+where
+    F: FnOnce() -> Result<G>,
+    G: Send + Sync + CacheEntrySize,
+{
+    info!("trying parameters memory cache for: {}", &identifier);
+    {
+        let mut cache = (*cache_ref).lock().unwrap();
+
+        if let Some(entry) = cache.get_mut(&identifier) {
+            info!("found params in memory cache for {}", &identifier);
+            entry.last_used = Instant::now();
+            entry.last_access = next_access();
+            return Ok(entry.value.clone());
+        }
+    }
+
+    info!("no params in memory cache for {}", &identifier);
+
+    let new_entry = Arc::new(generator()?);
+    let res = new_entry.clone();
+    {
+        let cache = &mut (*cache_ref).lock().unwrap();
+        let size = new_entry.cache_entry_size();
+        cache.insert(
+            identifier.clone(),
+            CacheEntry {
+                value: new_entry,
+                size,
+                last_used: Instant::now(),
+                last_access: next_access(),
+            },
+        );
+
+        evict_least_recently_used(cache, &identifier, limits);
+    }
+
+    Ok(res)
+}
+
+/// Evicts entries, oldest first, until the cache fits `limits`. The entry named by `keep` (the
+/// one just inserted) is never evicted, even if it alone exceeds the limits.
+fn evict_least_recently_used<G>(cache: &mut Cache<G>, keep: &str, limits: CacheLimits) {
+    let over_limits = |cache: &Cache<G>| {
+        let too_many = limits.max_entries.map_or(false, |n| cache.len() > n);
+        let too_big = limits
+            .max_bytes
+            .map_or(false, |n| cache.values().map(|e| e.size).sum::<u64>() > n);
+
+        too_many || too_big
+    };
+
+    while over_limits(cache) {
+        let oldest = cache
+            .iter()
+            .filter(|(id, _)| id.as_str() != keep)
+            .min_by_key(|(_, entry)| entry.last_access)
+            .map(|(id, _)| id.clone());
+
+        match oldest {
+            Some(id) => {
+                info!("evicting params from memory cache: {}", &id);
+                cache.remove(&id);
+            }
+            None => break,
+        }
+    }
+}
+
+#[inline]
+pub fn lookup_verifying_key<F>(identifier: String, generator: F) -> Result<Arc<Bls12VerifyingKey>>
+where
+    F: FnOnce() -> Result<Bls12VerifyingKey>,
+{
+    let vk_identifier = format!("{}-verifying-key", &identifier);
+    cache_lookup(
+        &*VERIFYING_KEY_MEMORY_CACHE,
+        vk_identifier,
+        CacheLimits::default(),
+        generator,
+    )
+}
+
+/// Lists the entries of a cache together with their `last_access`.
+fn list_cache<G>(
+    cache_ref: &Mutex<Cache<G>>,
+    kind: CachedParameterKind,
+) -> Vec<(u64, CachedParameterInfo)> {
+    let cache = (*cache_ref).lock().unwrap();
+
+    cache
+        .iter()
+        .map(|(identifier, entry)| {
+            let info = CachedParameterInfo {
+                identifier: identifier.clone(),
+                kind,
+                size: entry.size,
+                last_used: entry.last_used,
+            };
+            (entry.last_access, info)
+        })
+        .collect()
+}
+
+/// Lists every Groth parameter set and verifying key currently held in memory, most recently
+/// used first.
+pub fn list_cached_parameters() -> Vec<CachedParameterInfo> {
+    let mut entries = list_cache(
+        &*VERIFYING_KEY_MEMORY_CACHE,
+        CachedParameterKind::VerifyingKey,
+    );
+    #[cfg(feature = "prover")]
+    entries.extend(list_cache(
+        &*GROTH_PARAM_MEMORY_CACHE,
+        CachedParameterKind::GrothParams,
+    ));
+    entries.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    entries.into_iter().map(|(_, info)| info).collect()
+}
+
+/// Drops the entry named `identifier` (as reported by `list_cached_parameters`) from the memory
+/// caches. Returns whether an entry was found.
+pub fn evict_cached_parameters(identifier: &str) -> bool {
+    let found = VERIFYING_KEY_MEMORY_CACHE
+        .lock()
+        .unwrap()
+        .remove(identifier)
+        .is_some();
+    #[cfg(feature = "prover")]
+    let found = GROTH_PARAM_MEMORY_CACHE
+        .lock()
+        .unwrap()
+        .remove(identifier)
+        .is_some()
+        || found;
+
+    found
+}
+
+/// Drops every entry from the memory caches.
+pub fn clear_cached_parameters() {
+    #[cfg(feature = "prover")]
+    GROTH_PARAM_MEMORY_CACHE.lock().unwrap().clear();
+    VERIFYING_KEY_MEMORY_CACHE.lock().unwrap().clear();
+}
+
+/// Loads the Groth parameters and verifying keys for the given configurations into the memory
+/// caches, so that the first seal or PoSt does not pay for it. Without the `prover` feature only
+/// the verifying keys are loaded.
+pub fn preload_parameters(
+    porep_configs: &[PoRepConfig],
+    post_configs: &[PoStConfig],
+) -> Result<()> {
+    for porep_config in porep_configs {
+        #[cfg(feature = "prover")]
+        get_stacked_params(*porep_config)?;
+        get_stacked_verifying_key(*porep_config)?;
+    }
+
+    for post_config in post_configs {
+        #[cfg(feature = "prover")]
+        get_post_params(*post_config)?;
+        get_post_verifying_key(*post_config)?;
+    }
+
+    Ok(())
+}
+
+/// Registers the serialized verifying key in `bytes` under a parameter cache identifier, as
+/// returned by `PoRepConfig::get_cache_identifier` or `PoStConfig::get_cache_identifier`.
+/// Registered keys are used in preference to the parameter cache.
+pub fn register_verifying_key(identifier: &str, bytes: &[u8]) -> Result<()> {
+    let vk = read_registered_verifying_key(identifier, bytes)?;
+
+    info!("registering verifying key for {}", identifier);
+    REGISTERED_VERIFYING_KEYS
+        .write()
+        .unwrap()
+        .insert(identifier.to_string(), Arc::new(vk));
+
+    Ok(())
+}
+
+/// Registers the verifying key of the porep circuit for `porep_config`.
+pub fn register_stacked_verifying_key(porep_config: PoRepConfig, bytes: &[u8]) -> Result<()> {
+    register_config_verifying_key(VerifyingKeyConfig::PoRep(porep_config), bytes)
+}
+
+/// Registers the verifying key of the election post circuit for `post_config`.
+pub fn register_post_verifying_key(post_config: PoStConfig, bytes: &[u8]) -> Result<()> {
+    register_config_verifying_key(VerifyingKeyConfig::PoSt(post_config), bytes)
+}
+
+/// Registers the verifying key of the window post circuit for `window_post_config`.
+pub fn register_window_post_verifying_key(
+    window_post_config: WindowPoStConfig,
+    bytes: &[u8],
+) -> Result<()> {
+    register_config_verifying_key(VerifyingKeyConfig::WindowPoSt(window_post_config), bytes)
+}
+
+/// Keys verifying keys registered for a config, so that they are found without deriving the
+/// config's cache identifier, which needs its public parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum VerifyingKeyConfig {
+    PoRep(PoRepConfig),
+    PoSt(PoStConfig),
+    WindowPoSt(WindowPoStConfig),
+}
+
+fn register_config_verifying_key(config: VerifyingKeyConfig, bytes: &[u8]) -> Result<()> {
+    let vk = read_registered_verifying_key(&format!("{:?}", config), bytes)?;
+
+    info!("registering verifying key for {:?}", config);
+    CONFIG_VERIFYING_KEYS
+        .write()
+        .unwrap()
+        .insert(config, Arc::new(vk));
+
+    Ok(())
+}
+
+fn read_registered_verifying_key(name: &str, bytes: &[u8]) -> Result<Bls12VerifyingKey> {
+    Bls12VerifyingKey::read(bytes).with_context(|| format!("invalid verifying key for {}", name))
+}
+
+/// Looks up a registered verifying key, falling back to the embedded ones if built with them.
+fn registered_verifying_key(identifier: &str) -> Result<Option<Arc<Bls12VerifyingKey>>> {
+    if let Some(vk) = REGISTERED_VERIFYING_KEYS.read().unwrap().get(identifier) {
+        return Ok(Some(vk.clone()));
+    }
+
+    #[cfg(feature = "embedded-verifying-keys")]
+    {
+        let path = parameter_cache_verifying_key_path(identifier);
+        let embedded = EMBEDDED_VERIFYING_KEYS
+            .iter()
+            .find(|(name, _)| path.file_name() == Some(std::ffi::OsStr::new(name)));
+
+        if let Some((_, bytes)) = embedded {
+            register_verifying_key(identifier, bytes)?;
+            return Ok(REGISTERED_VERIFYING_KEYS
+                .read()
+                .unwrap()
+                .get(identifier)
+                .cloned());
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the verifying key registered for `config`. Only on a miss is the cache identifier
+/// derived, to look for a key registered under it, embedded or in the parameter cache, in that
+/// order. `generator` reads the key from the parameter cache.
+fn get_verifying_key<I, G>(
+    config: VerifyingKeyConfig,
+    identifier: I,
+    generator: G,
+) -> Result<Arc<Bls12VerifyingKey>>
+where
+    I: FnOnce() -> Result<String>,
+    G: FnOnce() -> Result<Bls12VerifyingKey>,
+{
+    if let Some(vk) = CONFIG_VERIFYING_KEYS.read().unwrap().get(&config) {
+        return Ok(vk.clone());
+    }
+
+    let identifier = identifier()?;
+    if let Some(vk) = registered_verifying_key(&identifier)? {
+        return Ok(vk);
+    }
+
+    lookup_verifying_key(identifier.clone(), generator).map_err(parameter_error(identifier))
+}
+
+pub fn get_stacked_verifying_key(porep_config: PoRepConfig) -> Result<Arc<Bls12VerifyingKey>> {
+    get_verifying_key(
+        VerifyingKeyConfig::PoRep(porep_config),
+        || porep_config.get_cache_identifier(),
+        || {
+            let public_params = public_params(
+                PaddedBytesAmount::from(porep_config),
+                usize::from(PoRepProofPartitions::from(porep_config)),
+                porep_config.params,
+            )?;
+
+            <StackedCompound as CompoundProof<
+                Bls12,
+                StackedDrg<DefaultTreeHasher, DefaultPieceHasher>,
+                _,
+            >>::verifying_key::<rand::rngs::OsRng>(None, &public_params)
+            .map_err(Into::into)
+        },
+    )
+}
+
+pub fn get_post_verifying_key(post_config: PoStConfig) -> Result<Arc<Bls12VerifyingKey>> {
+    get_verifying_key(
+        VerifyingKeyConfig::PoSt(post_config),
+        || post_config.get_cache_identifier(),
+        || {
+            let post_public_params = post_public_params(post_config)?;
+
+            <ElectionPoStCompound<DefaultTreeHasher> as CompoundProof<
+                Bls12,
+                ElectionPoSt<DefaultTreeHasher>,
+                ElectionPoStCircuit<Bls12, DefaultTreeHasher>,
+            >>::verifying_key::<rand::rngs::OsRng>(None, &post_public_params)
+            .map_err(Into::into)
+        },
+    )
+}
+
+pub fn get_window_post_verifying_key(
+    window_post_config: WindowPoStConfig,
+) -> Result<Arc<Bls12VerifyingKey>> {
+    get_verifying_key(
+        VerifyingKeyConfig::WindowPoSt(window_post_config),
+        || window_post_config.get_cache_identifier(),
+        || {
+            let window_post_public_params = window_post_public_params(window_post_config)?;
+
+            <WindowPoStCompound<DefaultTreeHasher> as CompoundProof<
+                Bls12,
+                WindowPoSt<DefaultTreeHasher>,
+                WindowPoStCircuit<Bls12, DefaultTreeHasher>,
+            >>::verifying_key::<rand::rngs::OsRng>(None, &window_post_public_params)
+            .map_err(Into::into)
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+    use ff::Field;
+    use paired::bls12_381::Fr;
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use crate::TEST_SEED;
+
+    struct Entry(u64);
+
+    impl CacheEntrySize for Entry {
+        fn cache_entry_size(&self) -> u64 {
+            self.0
+        }
+    }
+
+    fn lookup(cache: &Mutex<Cache<Entry>>, id: &str, size: u64, limits: CacheLimits) {
+        cache_lookup(cache, id.to_string(), limits, || Ok(Entry(size))).unwrap();
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache: Mutex<Cache<Entry>> = Default::default();
+        let limits = CacheLimits {
+            max_entries: Some(2),
+            max_bytes: None,
+        };
+
+        lookup(&cache, "a", 1, limits);
+        lookup(&cache, "b", 1, limits);
+        // touch "a" so that "b" becomes the oldest entry
+        lookup(&cache, "a", 1, limits);
+        lookup(&cache, "c", 1, limits);
+
+        let cache = cache.lock().unwrap();
+        assert!(cache.contains_key("a"));
+        assert!(!cache.contains_key("b"));
+        assert!(cache.contains_key("c"));
+    }
+
+    #[test]
+    fn test_evicts_under_memory_cap() {
+        let cache: Mutex<Cache<Entry>> = Default::default();
+        let limits = CacheLimits {
+            max_entries: None,
+            max_bytes: Some(100),
+        };
+
+        lookup(&cache, "a", 40, limits);
+        lookup(&cache, "b", 40, limits);
+        lookup(&cache, "c", 40, limits);
+        assert_eq!(cache.lock().unwrap().len(), 2);
+
+        // an entry larger than the cap evicts everything else, but is kept itself
+        lookup(&cache, "d", 200, limits);
+        let cache = cache.lock().unwrap();
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains_key("d"));
+    }
+
+    struct One;
+
+    impl Circuit<Bls12> for One {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> std::result::Result<(), SynthesisError> {
+            let x = cs.alloc_input(|| "x", || Ok(Fr::one()))?;
+            cs.enforce(
+                || "x = 1",
+                |lc| lc + x,
+                |lc| lc + CS::one(),
+                |lc| lc + CS::one(),
+            );
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_register_verifying_key() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+        let params = groth16::generate_random_parameters::<Bls12, _, _>(One, rng).unwrap();
+        let mut bytes = Vec::new();
+        params.vk.write(&mut bytes).unwrap();
+
+        let identifier = "test-register-verifying-key";
+        assert!(registered_verifying_key(identifier).unwrap().is_none());
+
+        register_verifying_key(identifier, &bytes).unwrap();
+        let vk = registered_verifying_key(identifier).unwrap().unwrap();
+        assert!(*vk == params.vk);
+
+        assert!(register_verifying_key("test-invalid-verifying-key", &bytes[1..]).is_err());
+        assert!(registered_verifying_key("test-invalid-verifying-key")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_register_config_verifying_key() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+        let params = groth16::generate_random_parameters::<Bls12, _, _>(One, rng).unwrap();
+        let mut bytes = Vec::new();
+        params.vk.write(&mut bytes).unwrap();
+
+        let post_config = PoStConfig {
+            sector_size: SectorSize(1024),
+            challenge_count: 3,
+            challenged_nodes: 5,
+        };
+        register_post_verifying_key(post_config, &bytes).unwrap();
+
+        // a registered key is found without deriving the cache identifier or reading the cache
+        let vk = get_verifying_key(
+            VerifyingKeyConfig::PoSt(post_config),
+            || panic!("cache identifier derived"),
+            || panic!("parameter cache read"),
+        )
+        .unwrap();
+        assert!(*vk == params.vk);
+        assert!(*get_post_verifying_key(post_config).unwrap() == params.vk);
+
+        let other_config = PoStConfig {
+            challenge_count: 4,
+            ..post_config
+        };
+        let err = get_verifying_key(
+            VerifyingKeyConfig::PoSt(other_config),
+            || Err(anyhow::anyhow!("no identifier")),
+            || panic!("parameter cache read"),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "no identifier");
+    }
+}
@@ -0,0 +1,134 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use storage_proofs::sector::SectorId;
+
+use crate::types::Commitment;
+
+pub use storage_proofs::error::Error as StorageProofsError;
+
+/// Errors raised by the filecoin-proofs API.
+///
+/// The API returns `anyhow::Error`, so callers branch on the failure type with
+/// `err.downcast_ref::<Error>()`. Errors raised further down, in storage-proofs, downcast to
+/// `StorageProofsError` instead (e.g. `StorageProofsError::Cancelled`).
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid all zero commitment ({0})")]
+    ZeroCommitment(&'static str),
+    #[error("invalid commitment ({0})")]
+    InvalidCommitment(String),
+    #[error("pieces and comm_d do not match: the pieces give {expected:?}, comm_d is {actual:?}")]
+    PieceMismatch {
+        expected: Commitment,
+        actual: Commitment,
+    },
+    #[error("no pieces given")]
+    NoPieces,
+    #[error("too many pieces: {actual}, a sector holds at most {max}")]
+    TooManyPieces { max: u64, actual: u64 },
+    #[error("pieces of {actual} bytes do not fit in a sector of {max} bytes")]
+    PiecesTooLarge { max: u64, actual: u64 },
+    #[error("piece size {0} is not a power of two")]
+    InvalidPieceSize(u64),
+    #[error("piece source gave {actual} bytes, expected {expected}")]
+    PieceSizeMismatch { expected: u64, actual: u64 },
+    #[error("must supply at least one replica")]
+    NoReplicas,
+    #[error("missing replica for sector {0}")]
+    MissingReplica(SectorId),
+    #[error("missing cache artifact {0:?}")]
+    MissingCacheArtifact(PathBuf),
+    #[error("invalid cache artifacts in {0:?}")]
+    CorruptCacheArtifact(PathBuf),
+    #[error("no parameters found for {0}")]
+    ParameterNotFound(String),
+    #[error("could not load the parameters for {0}")]
+    CorruptParameters(String),
+    #[error("invalid proof length: expected {expected} bytes, got {actual}")]
+    InvalidProofLength { expected: usize, actual: usize },
+    #[error("io error on {path:?}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// Returns a function which wraps an `io::Error` raised while accessing `path`.
+pub(crate) fn io_error<P: AsRef<Path>>(path: P) -> impl FnOnce(io::Error) -> Error {
+    move |source| Error::Io {
+        path: path.as_ref().to_path_buf(),
+        source,
+    }
+}
+
+/// Whether `err` was caused by a file which does not exist.
+pub(crate) fn is_not_found(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|cause| match cause.downcast_ref::<io::Error>() {
+            Some(err) => err.kind() == io::ErrorKind::NotFound,
+            None => false,
+        })
+}
+
+/// Returns a function which classifies an error raised while loading the parameters or the
+/// verifying key identified by `identifier`: parameters missing from the cache are reported as
+/// `ParameterNotFound`, any other failure, such as a truncated file, as `CorruptParameters`.
+pub(crate) fn parameter_error(identifier: String) -> impl FnOnce(anyhow::Error) -> anyhow::Error {
+    move |err| {
+        let missing = is_not_found(&err)
+            || err
+                .chain()
+                .any(|cause| match cause.downcast_ref::<StorageProofsError>() {
+                    Some(StorageProofsError::MissingParameters(_)) => true,
+                    _ => false,
+                });
+
+        if missing {
+            err.context(Error::ParameterNotFound(identifier))
+        } else {
+            err.context(Error::CorruptParameters(identifier))
+        }
+    }
+}
+
+/// Like `io_error`, but reports files which do not exist as `MissingCacheArtifact`.
+pub(crate) fn cache_artifact_error<P: AsRef<Path>>(path: P) -> impl FnOnce(io::Error) -> Error {
+    move |source| {
+        if source.kind() == io::ErrorKind::NotFound {
+            Error::MissingCacheArtifact(path.as_ref().to_path_buf())
+        } else {
+            io_error(path)(source)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameter_error() {
+        let missing_file = io::Error::new(io::ErrorKind::NotFound, "no such file");
+        let err = parameter_error("v1-abc".into())(anyhow::Error::new(missing_file));
+        match err.downcast_ref::<Error>() {
+            Some(Error::ParameterNotFound(id)) => assert_eq!(id, "v1-abc"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let missing_params = StorageProofsError::MissingParameters("v1-abc".into());
+        let err = parameter_error("v1-abc".into())(missing_params.into());
+        match err.downcast_ref::<Error>() {
+            Some(Error::ParameterNotFound(_)) => {}
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let truncated = io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer");
+        let err = parameter_error("v1-abc".into())(truncated.into());
+        match err.downcast_ref::<Error>() {
+            Some(Error::CorruptParameters(id)) => assert_eq!(id, "v1-abc"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+}
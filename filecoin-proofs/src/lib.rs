@@ -4,16 +4,43 @@ mod api;
 mod caches;
 
 pub mod constants;
+pub mod error;
 pub mod fr32;
 pub mod param;
 pub mod parameters;
 pub mod pieces;
+#[cfg(feature = "prover")]
+pub mod resources;
+pub mod sector_sizes;
 pub mod serde_big_array;
 pub mod singletons;
 pub mod types;
+pub mod verifier;
 
 pub use api::*;
+#[cfg(feature = "prover")]
+pub use caches::shrink_cached_parameters;
+pub use caches::{
+    clear_cached_parameters, evict_cached_parameters, list_cached_parameters, preload_parameters,
+    register_post_verifying_key, register_stacked_verifying_key, register_verifying_key,
+    register_window_post_verifying_key, CacheLimits, CachedParameterInfo, CachedParameterKind,
+};
 pub use constants::SINGLE_PARTITION_PROOF_LEN;
+pub use error::{Error, StorageProofsError};
+#[cfg(feature = "prover")]
+pub use resources::{
+    estimate_resources, estimate_resources_with_pools, CircuitSize, ResourceEstimate,
+};
+pub use sector_sizes::{
+    published_sector_sizes, register_sector_size, registered_sector_sizes, sector_size_info,
+    SectorSizeInfo,
+};
+pub use storage_proofs::cancellation::{is_cancelled, CancellationToken};
+pub use storage_proofs::progress::{NoProgress, Progress, ProgressObserver, RunOptions, Stage};
+#[cfg(feature = "prover")]
+pub use storage_proofs::thread_pools::{
+    with_thread_pools, Pool, PoolConfig, ThreadPools, ThreadPoolsConfig,
+};
 pub use types::*;
 
 #[cfg(test)]
@@ -1,11 +1,14 @@
 use std::collections::BTreeMap;
 use std::ffi::OsStr;
 use std::fs::File;
-use std::io::{stdin, stdout, Write};
+use std::io::{stdin, BufReader};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
 use blake2b_simd::State as Blake2b;
+#[cfg(feature = "manifest-signing")]
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature};
 use serde::{Deserialize, Serialize};
 use storage_proofs::parameter_cache::{
     parameter_cache_dir, CacheEntryMetadata, PARAMETER_METADATA_EXT,
@@ -13,6 +16,89 @@ use storage_proofs::parameter_cache::{
 
 const ERROR_STRING: &str = "invalid string";
 
+/// Extension of the signature file written next to a manifest, e.g. `parameters.json.sig`.
+pub const SIGNATURE_EXT: &str = "sig";
+
+/// Extension of the file a parameter is downloaded to by `paramfetch`, e.g.
+/// `v20-….params.partial`, which is renamed into place once complete.
+pub const PARTIAL_EXT: &str = "partial";
+
+/// Infix of the directory `paramfetch` has ipget download a parameter to, next to its partial
+/// file, e.g. `v20-….params.ipget-Xa3bc9`.
+pub const IPGET_DIR_INFIX: &str = ".ipget-";
+
+/// Suffix `paramfetch` appends to the name of a cached file whose digest does not match the
+/// manifest, e.g. `v20-….params-invalid-digest`.
+pub const INVALID_DIGEST_SUFFIX: &str = "-invalid-digest";
+
+/// Environment variable holding the keys trusted to sign a manifest, see `--trusted-key`.
+pub const TRUSTED_KEYS_ENV_VAR: &str = "FIL_PROOFS_TRUSTED_MANIFEST_KEYS";
+
+/// Exit code of `paramfetch` and `parampublish` on success. With `--non-interactive` or
+/// `--output json` the exit code also tells whether anything was done:
+///
+/// | code | outcome                                                         |
+/// |------|-----------------------------------------------------------------|
+/// | 0    | every file was already present, or there was nothing to publish |
+/// | 1    | some file failed, or the run failed altogether                  |
+/// | 2    | files were fetched or published, none failing                   |
+///
+/// Otherwise the exit code is 0 on success and 1 on failure.
+pub const EXIT_ALL_PRESENT: i32 = 0;
+/// Exit code when some file failed to be fetched or published, or on any other error.
+pub const EXIT_FAILED: i32 = 1;
+/// Exit code of `--non-interactive` or `--output json` runs which fetched or published files,
+/// none failing.
+pub const EXIT_FETCHED: i32 = 2;
+
+/// The exit codes, as listed by `paramfetch --help` and `parampublish --help`.
+pub const EXIT_CODES_HELP: &str = "EXIT CODES:
+    0    success
+    1    some file failed, or the run failed altogether
+
+    With --non-interactive or --output json, success is further split into:
+    0    every file was already present, or there was nothing to publish
+    2    files were fetched or published, none failing";
+
+/// Set by `--output json` of `paramfetch` and `parampublish`, which sends the messages meant for
+/// the user to stderr, leaving stdout to the report.
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Sends the messages of `say!` and `say_inline!` to stderr if `json_output` is set.
+pub fn set_json_output(json_output: bool) {
+    JSON_OUTPUT.store(json_output, Ordering::Relaxed);
+}
+
+/// Whether the messages of `say!` and `say_inline!` go to stderr, see `set_json_output`.
+pub fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Prints a message for the user of `paramfetch` or `parampublish`, see `set_json_output`.
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::param::json_output() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// Like `say!`, without the newline.
+#[macro_export]
+macro_rules! say_inline {
+    ($($arg:tt)*) => {
+        if $crate::param::json_output() {
+            eprint!($($arg)*)
+        } else {
+            print!($($arg)*);
+            std::io::Write::flush(&mut std::io::stdout()).unwrap();
+        }
+    };
+}
+
 pub type ParameterMap = BTreeMap<String, ParameterData>;
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -22,6 +108,179 @@ pub struct ParameterData {
     pub sector_size: u64,
 }
 
+/// An ed25519 signature of the bytes of a manifest, stored next to it in a `.sig` file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ManifestSignature {
+    /// The hex encoded public key of the signer.
+    pub public_key: String,
+    /// The hex encoded signature.
+    pub signature: String,
+}
+
+/// The path of the signature of the manifest at `manifest_path`.
+pub fn signature_path<P: AsRef<Path>>(manifest_path: P) -> PathBuf {
+    let mut path = manifest_path.as_ref().as_os_str().to_owned();
+    path.push(".");
+    path.push(SIGNATURE_EXT);
+    PathBuf::from(path)
+}
+
+/// Parses a hex encoded ed25519 public key.
+#[cfg(feature = "manifest-signing")]
+pub fn parse_public_key(key: &str) -> Result<PublicKey> {
+    let bytes = hex::decode(key.trim()).with_context(|| format!("invalid public key {}", key))?;
+    PublicKey::from_bytes(&bytes).with_context(|| format!("invalid public key {}", key))
+}
+
+/// Reads the hex encoded ed25519 secret key in the file at `path`.
+#[cfg(feature = "manifest-signing")]
+pub fn read_signing_key<P: AsRef<Path>>(path: P) -> Result<Keypair> {
+    let path = path.as_ref();
+    let key = std::fs::read_to_string(path)
+        .with_context(|| format!("could not read signing key {:?}", path))?;
+    let bytes =
+        hex::decode(key.trim()).with_context(|| format!("invalid signing key in {:?}", path))?;
+    let secret = SecretKey::from_bytes(&bytes)
+        .with_context(|| format!("invalid signing key in {:?}", path))?;
+    let public = PublicKey::from(&secret);
+
+    Ok(Keypair { secret, public })
+}
+
+/// Signs the bytes of a manifest, as written to disk.
+#[cfg(feature = "manifest-signing")]
+pub fn sign_manifest(manifest: &[u8], keypair: &Keypair) -> ManifestSignature {
+    ManifestSignature {
+        public_key: hex::encode(keypair.public.as_bytes()),
+        signature: hex::encode(&keypair.sign(manifest).to_bytes()[..]),
+    }
+}
+
+/// Checks that `signature` is a valid signature of `manifest` by one of `trusted_keys`.
+#[cfg(feature = "manifest-signing")]
+pub fn verify_manifest(
+    manifest: &[u8],
+    signature: &ManifestSignature,
+    trusted_keys: &[PublicKey],
+) -> Result<()> {
+    let public_key = parse_public_key(&signature.public_key)?;
+    ensure!(
+        trusted_keys.contains(&public_key),
+        "manifest is signed by untrusted key {}",
+        signature.public_key
+    );
+
+    let bytes = hex::decode(&signature.signature).context("invalid manifest signature")?;
+    let signature = Signature::from_bytes(&bytes).context("invalid manifest signature")?;
+    public_key
+        .verify(manifest, &signature)
+        .context("manifest signature does not match the manifest")?;
+
+    Ok(())
+}
+
+/// Checks the signature next to the manifest at `json_path`, whose contents are `manifest`,
+/// against `trusted_keys`. Returns the key of the signer, or `None` if the manifest is not signed,
+/// which is up to the caller to accept.
+#[cfg(feature = "manifest-signing")]
+pub fn check_manifest_signature(
+    json_path: &Path,
+    manifest: &[u8],
+    trusted_keys: &[PublicKey],
+) -> Result<Option<String>> {
+    let sig_path = signature_path(json_path);
+    if !sig_path.exists() {
+        return Ok(None);
+    }
+
+    ensure!(
+        !trusted_keys.is_empty(),
+        "no trusted keys to verify the signature of '{}' with, see --trusted-key",
+        json_path.display()
+    );
+
+    let file = File::open(&sig_path)?;
+    let signature: ManifestSignature = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("signature file {:?} did not parse correctly", sig_path))?;
+    verify_manifest(manifest, &signature, trusted_keys)?;
+
+    Ok(Some(signature.public_key))
+}
+
+/// Parses the keys given with `--trusted-key`.
+#[cfg(feature = "manifest-signing")]
+pub fn parse_trusted_keys<'a, I>(keys: Option<I>) -> Result<Vec<PublicKey>>
+where
+    I: Iterator<Item = &'a str>,
+{
+    match keys {
+        Some(keys) => keys.map(parse_public_key).collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// What was done with a file listed in a manifest or found in the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterAction {
+    /// A valid copy was already in the cache.
+    Present,
+    Fetched,
+    Published,
+    /// Left out by a sector size filter or by the user.
+    Skipped,
+    Failed,
+}
+
+/// The outcome for one file, as printed by `--output json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParameterReport {
+    pub id: String,
+    pub sector_size: Option<u64>,
+    pub expected_digest: Option<String>,
+    pub actual_digest: Option<String>,
+    pub cid: Option<String>,
+    pub action: ParameterAction,
+    pub error: Option<String>,
+}
+
+impl ParameterReport {
+    pub fn new(id: &str, action: ParameterAction) -> Self {
+        ParameterReport {
+            id: id.to_string(),
+            sector_size: None,
+            expected_digest: None,
+            actual_digest: None,
+            cid: None,
+            action,
+            error: None,
+        }
+    }
+}
+
+/// The exit code summarizing `reports`, see `EXIT_ALL_PRESENT`. `detailed` is set for
+/// `--non-interactive` and `--output json` runs, only those exit with `EXIT_FETCHED`.
+pub fn exit_code(reports: &[ParameterReport], detailed: bool) -> i32 {
+    let action = |action| reports.iter().any(|r| r.action == action);
+
+    if action(ParameterAction::Failed) {
+        EXIT_FAILED
+    } else if detailed && (action(ParameterAction::Fetched) || action(ParameterAction::Published)) {
+        EXIT_FETCHED
+    } else {
+        EXIT_ALL_PRESENT
+    }
+}
+
+/// Reports an error which failed the run altogether. Under `--output json` the report on stdout
+/// is an object with the error in place of the list of files.
+pub fn say_fatal_error(err: &anyhow::Error) {
+    say!("fatal error: {}", err);
+    if json_output() {
+        println!("{}", serde_json::json!({ "error": format!("{:#}", err) }));
+    }
+}
+
 // Produces an absolute path to a file within the cache
 pub fn get_full_path_for_file_within_cache(filename: &str) -> PathBuf {
     let mut path = parameter_cache_dir();
@@ -37,15 +296,19 @@ pub fn get_digest_for_file_within_cache(filename: &str) -> Result<String> {
 
     std::io::copy(&mut file, &mut hasher)?;
 
-    Ok(hasher.finalize().to_hex()[..32].into())
+    Ok(digest_from_state(&hasher))
+}
+
+/// Formats the BLAKE2b checksum of the data written to `hasher` as a manifest digest.
+pub fn digest_from_state(hasher: &Blake2b) -> String {
+    hasher.finalize().to_hex()[..32].into()
 }
 
-// Prompts the user to approve/reject the message
+// Prompts the user to approve/reject the message, on stderr under `--output json`
 pub fn choose(message: &str) -> bool {
     loop {
-        print!("[y/n] {}: ", message);
+        say_inline!("[y/n] {}: ", message);
 
-        let _ = stdout().flush();
         let mut s = String::new();
         stdin().read_line(&mut s).expect(ERROR_STRING);
 
@@ -121,3 +384,58 @@ pub fn filename_to_parameter_id<'a, P: AsRef<Path> + 'a>(filename: P) -> Option<
         .and_then(OsStr::to_str)
         .map(ToString::to_string)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "manifest-signing")]
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_exit_code() {
+        let report = |action| ParameterReport::new("v20-aaa.vk", action);
+        let present = [report(ParameterAction::Present)];
+        let fetched = [
+            report(ParameterAction::Present),
+            report(ParameterAction::Fetched),
+        ];
+        let failed = [
+            report(ParameterAction::Fetched),
+            report(ParameterAction::Failed),
+        ];
+
+        assert_eq!(exit_code(&present, true), EXIT_ALL_PRESENT);
+        assert_eq!(exit_code(&fetched, true), EXIT_FETCHED);
+        assert_eq!(exit_code(&failed, true), EXIT_FAILED);
+
+        // a successful fetch exits with 0 unless the detailed codes were asked for
+        assert_eq!(exit_code(&present, false), 0);
+        assert_eq!(exit_code(&fetched, false), 0);
+        assert_eq!(exit_code(&failed, false), EXIT_FAILED);
+    }
+
+    #[test]
+    #[cfg(feature = "manifest-signing")]
+    fn test_verify_manifest() {
+        let keypair = Keypair::generate(&mut OsRng);
+        let other = Keypair::generate(&mut OsRng);
+        let manifest = br#"{"v20-aaa.vk":{"cid":"Qm","digest":"abc","sector_size":1024}}"#;
+
+        let signature = sign_manifest(manifest, &keypair);
+        assert!(verify_manifest(manifest, &signature, &[keypair.public]).is_ok());
+
+        // signed by a key which is not trusted
+        assert!(verify_manifest(manifest, &signature, &[other.public]).is_err());
+
+        // the manifest was changed after signing
+        let mut tampered = manifest.to_vec();
+        tampered[20] ^= 1;
+        assert!(verify_manifest(&tampered, &signature, &[keypair.public]).is_err());
+
+        assert_eq!(
+            signature_path("/tmp/parameters.json"),
+            PathBuf::from("/tmp/parameters.json.sig")
+        );
+    }
+}
@@ -1,16 +1,12 @@
-use std::sync::atomic::Ordering;
-
-use anyhow::{anyhow, ensure, Result};
+use anyhow::{ensure, Result};
 use storage_proofs::drgraph::DefaultTreeHasher;
 use storage_proofs::election_post::{self, ElectionPoSt};
 use storage_proofs::proof::ProofScheme;
 use storage_proofs::stacked::{self, LayerChallenges, StackedConfig, StackedDrg};
+use storage_proofs::window_post::{self, WindowPoSt};
 
-use crate::constants::{
-    DefaultPieceHasher, LAYERS, POREP_WINDOW_MINIMUM_CHALLENGES, POREP_WRAPPER_MINIMUM_CHALLENGES,
-    WINDOW_DRG_DEGREE, WINDOW_EXP_DEGREE, WRAPPER_EXP_DEGREE,
-};
-use crate::types::{PaddedBytesAmount, PoStConfig};
+use crate::constants::DefaultPieceHasher;
+use crate::types::{PaddedBytesAmount, PoStConfig, ProofParameters, WindowPoStConfig};
 
 const DRG_SEED: [u8; 28] = [
     0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
@@ -19,25 +15,20 @@ const DRG_SEED: [u8; 28] = [
 
 type PostSetupParams = election_post::SetupParams;
 pub type PostPublicParams = election_post::PublicParams;
+pub type WindowPostPublicParams = window_post::PublicParams;
 
 pub fn public_params(
     sector_bytes: PaddedBytesAmount,
     partitions: usize,
+    proof_params: ProofParameters,
 ) -> Result<stacked::PublicParams<DefaultTreeHasher>> {
     StackedDrg::<DefaultTreeHasher, DefaultPieceHasher>::setup(&setup_params(
         sector_bytes,
         partitions,
+        proof_params,
     )?)
 }
 
-pub fn window_size_nodes_for_sector_bytes(sector_size: PaddedBytesAmount) -> Result<u64> {
-    use crate::constants::DEFAULT_WINDOWS;
-    match DEFAULT_WINDOWS.read().unwrap().get(&u64::from(sector_size)) {
-        Some(info) => Ok(info.window_size_nodes()),
-        None => Err(anyhow!("Unknown sector size {:?}", sector_size)),
-    }
-}
-
 pub fn post_public_params(post_config: PoStConfig) -> Result<PostPublicParams> {
     ElectionPoSt::<DefaultTreeHasher>::setup(&post_setup_params(post_config))
 }
@@ -47,26 +38,43 @@ pub fn post_setup_params(post_config: PoStConfig) -> PostSetupParams {
 
     election_post::SetupParams {
         sector_size: size.into(),
-        challenge_count: crate::constants::POST_CHALLENGE_COUNT,
-        challenged_nodes: crate::constants::POST_CHALLENGED_NODES,
+        challenge_count: post_config.challenge_count,
+        challenged_nodes: post_config.challenged_nodes,
+    }
+}
+
+pub fn window_post_public_params(
+    window_post_config: WindowPoStConfig,
+) -> Result<WindowPostPublicParams> {
+    WindowPoSt::<DefaultTreeHasher>::setup(&window_post_setup_params(window_post_config))
+}
+
+pub fn window_post_setup_params(window_post_config: WindowPoStConfig) -> window_post::SetupParams {
+    let size = PaddedBytesAmount::from(window_post_config);
+
+    window_post::SetupParams {
+        sector_size: size.into(),
+        challenge_count: window_post_config.challenge_count,
+        sector_count: window_post_config.sector_count,
     }
 }
 
 pub fn setup_params(
     sector_bytes: PaddedBytesAmount,
     partitions: usize,
+    proof_params: ProofParameters,
 ) -> Result<stacked::SetupParams> {
     let window_challenges = select_challenges(
         partitions,
-        POREP_WINDOW_MINIMUM_CHALLENGES.load(Ordering::Relaxed) as usize,
-        LAYERS.load(Ordering::Relaxed) as usize,
+        proof_params.window_minimum_challenges,
+        proof_params.layers,
     )?;
     let wrapper_challenges = select_challenges(
         partitions,
-        POREP_WRAPPER_MINIMUM_CHALLENGES.load(Ordering::Relaxed) as usize,
-        LAYERS.load(Ordering::Relaxed) as usize,
+        proof_params.wrapper_minimum_challenges,
+        proof_params.layers,
     )?;
-    let window_size_nodes = window_size_nodes_for_sector_bytes(sector_bytes)?;
+    let window_size_nodes = proof_params.window_size_nodes(sector_bytes)?;
     let sector_bytes = u64::from(sector_bytes);
 
     let config = StackedConfig {
@@ -80,19 +88,12 @@ pub fn setup_params(
         sector_bytes,
     );
 
-    ensure!(
-        sector_bytes % window_size_nodes * 32 == 0,
-        "sector_bytes ({}) must be a multiple of the window size ({})",
-        sector_bytes,
-        window_size_nodes * 32
-    );
-
     let nodes = (sector_bytes / 32) as usize;
     Ok(stacked::SetupParams {
         nodes,
-        window_drg_degree: WINDOW_DRG_DEGREE.load(Ordering::Relaxed) as usize,
-        window_expansion_degree: WINDOW_EXP_DEGREE.load(Ordering::Relaxed) as usize,
-        wrapper_expansion_degree: WRAPPER_EXP_DEGREE.load(Ordering::Relaxed) as usize,
+        window_drg_degree: proof_params.window_drg_degree,
+        window_expansion_degree: proof_params.window_expansion_degree,
+        wrapper_expansion_degree: proof_params.wrapper_expansion_degree,
         seed: DRG_SEED,
         config,
         window_size_nodes: window_size_nodes as usize,
@@ -117,10 +118,13 @@ fn select_challenges(
 mod tests {
     use super::*;
 
+    use crate::constants::{LAYERS, SECTOR_SIZE_ONE_KIB};
+    use crate::types::{PoRepConfig, PoRepProofPartitions, SectorSize};
+
     #[test]
     fn partition_layer_challenges_test() {
         let f = |partitions| {
-            select_challenges(partitions, 12, LAYERS.load(Ordering::Relaxed) as usize)
+            select_challenges(partitions, 12, LAYERS)
                 .unwrap()
                 .challenges_count_all()
         };
@@ -131,4 +135,49 @@ mod tests {
         assert_eq!(6, f(2));
         assert_eq!(3, f(4));
     }
+
+    #[test]
+    fn proof_parameters_are_per_config() {
+        let production = PoRepConfig {
+            sector_size: SectorSize(SECTOR_SIZE_ONE_KIB),
+            partitions: PoRepProofPartitions(2),
+            params: ProofParameters::default(),
+        };
+        let mut custom = production;
+        custom.params.layers = 2;
+        custom.params.window_size = Some(256);
+
+        let setup = setup_params(PaddedBytesAmount::from(custom), 2, custom.params).unwrap();
+        assert_eq!(setup.window_size_nodes, 8);
+        assert_eq!(setup.config.window_challenges.layers(), 2);
+
+        let setup =
+            setup_params(PaddedBytesAmount::from(production), 2, production.params).unwrap();
+        assert_eq!(setup.window_size_nodes, 16);
+        assert_eq!(setup.config.window_challenges.layers(), LAYERS);
+
+        assert_ne!(
+            production.get_cache_identifier().unwrap(),
+            custom.get_cache_identifier().unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_window_size_is_rejected() {
+        let mut config = PoRepConfig {
+            sector_size: SectorSize(SECTOR_SIZE_ONE_KIB),
+            partitions: PoRepProofPartitions(2),
+            params: ProofParameters::default(),
+        };
+
+        for window_size in &[0, 16, 96, 2 * SECTOR_SIZE_ONE_KIB] {
+            config.params.window_size = Some(*window_size);
+            assert!(
+                setup_params(PaddedBytesAmount::from(config), 2, config.params).is_err(),
+                "window size {} accepted",
+                window_size
+            );
+            assert!(config.get_cache_identifier().is_err());
+        }
+    }
 }
@@ -11,10 +11,17 @@ use crate::constants::{
     DefaultPieceHasher,
     MINIMUM_RESERVED_BYTES_FOR_PIECE_IN_FULLY_ALIGNED_SECTOR as MINIMUM_PIECE_SIZE,
 };
+use crate::error::Error;
 use crate::types::{
     Commitment, PaddedBytesAmount, PieceInfo, SectorSize, UnpaddedByteIndex, UnpaddedBytesAmount,
 };
 
+/// Returns the piece infos of a committed capacity sector, a single piece of zeros filling the
+/// whole sector. They are to be passed to `seal_commit` for sectors sealed by `seal_pre_commit_cc`.
+pub fn cc_piece_infos(sector_size: SectorSize) -> Result<Vec<PieceInfo>> {
+    Ok(vec![zero_padding(sector_size.into())?])
+}
+
 /// Verify that the provided `piece_infos` and `comm_d` match.
 pub fn verify_pieces(
     comm_d: &Commitment,
@@ -26,15 +33,37 @@ pub fn verify_pieces(
     Ok(&comm_d_calculated == comm_d)
 }
 
+/// Checks that `piece_infos` add up to `comm_d`, failing with `Error::PieceMismatch` otherwise.
+pub(crate) fn ensure_pieces_match(
+    comm_d: &Commitment,
+    piece_infos: &[PieceInfo],
+    sector_size: SectorSize,
+) -> Result<()> {
+    let expected = compute_comm_d(sector_size, piece_infos)?;
+    ensure!(
+        &expected == comm_d,
+        Error::PieceMismatch {
+            expected,
+            actual: *comm_d,
+        }
+    );
+
+    Ok(())
+}
+
 pub fn compute_comm_d(sector_size: SectorSize, piece_infos: &[PieceInfo]) -> Result<Commitment> {
     info!("verifying {} pieces", piece_infos.len());
-    ensure!(!piece_infos.is_empty(), "Missing piece infos");
+    ensure!(!piece_infos.is_empty(), Error::NoPieces);
 
     let unpadded_sector: UnpaddedBytesAmount = sector_size.into();
 
+    let max_pieces = u64::from(unpadded_sector) / MINIMUM_PIECE_SIZE;
     ensure!(
-        piece_infos.len() as u64 <= u64::from(unpadded_sector) / MINIMUM_PIECE_SIZE,
-        "Too many pieces"
+        piece_infos.len() as u64 <= max_pieces,
+        Error::TooManyPieces {
+            max: max_pieces,
+            actual: piece_infos.len() as u64,
+        }
     );
 
     // make sure the piece sizes are at most a sector size large
@@ -45,7 +74,10 @@ pub fn compute_comm_d(sector_size: SectorSize, piece_infos: &[PieceInfo]) -> Res
 
     ensure!(
         piece_size <= u64::from(sector_size),
-        "Piece is larger than sector."
+        Error::PiecesTooLarge {
+            max: u64::from(sector_size),
+            actual: piece_size,
+        }
     );
 
     let mut stack = Stack::new();
@@ -53,16 +85,14 @@ pub fn compute_comm_d(sector_size: SectorSize, piece_infos: &[PieceInfo]) -> Res
     let first = piece_infos.first().unwrap().clone();
     ensure!(
         u64::from(PaddedBytesAmount::from(first.size)).is_power_of_two(),
-        "Piece size ({:?}) must be a power of 2.",
-        PaddedBytesAmount::from(first.size)
+        Error::InvalidPieceSize(u64::from(PaddedBytesAmount::from(first.size)))
     );
     stack.shift(first);
 
     for piece_info in piece_infos.iter().skip(1) {
         ensure!(
             u64::from(PaddedBytesAmount::from(piece_info.size)).is_power_of_two(),
-            "Piece size ({:?}) must be a power of 2.",
-            PaddedBytesAmount::from(piece_info.size)
+            Error::InvalidPieceSize(u64::from(PaddedBytesAmount::from(piece_info.size)))
         );
 
         while stack.peek().size < piece_info.size {
@@ -182,7 +212,7 @@ fn join_piece_infos(mut left: PieceInfo, right: PieceInfo) -> Result<PieceInfo>
     Ok(left)
 }
 
-fn piece_hash(a: &[u8], b: &[u8]) -> <DefaultPieceHasher as Hasher>::Domain {
+pub(crate) fn piece_hash(a: &[u8], b: &[u8]) -> <DefaultPieceHasher as Hasher>::Domain {
     let mut buf = [0u8; NODE_SIZE * 2];
     buf[..NODE_SIZE].copy_from_slice(a);
     buf[NODE_SIZE..].copy_from_slice(b);
@@ -295,18 +325,9 @@ pub fn get_aligned_source<T: Read>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::api::util::commitment_from_fr;
-    use crate::constants::{WINDOW_DRG_DEGREE, WINDOW_EXP_DEGREE};
-
-    use std::sync::atomic::Ordering;
 
-    use paired::bls12_381::{Bls12, Fr};
-    use rand::{Rng, RngCore, SeedableRng};
+    use rand::{Rng, SeedableRng};
     use rand_xorshift::XorShiftRng;
-    use storage_proofs::drgraph::{new_seed, Graph};
-    use storage_proofs::stacked::StackedBucketGraph;
-
-    use std::io::{Seek, SeekFrom};
 
     #[test]
     fn test_get_piece_alignment() {
@@ -436,6 +457,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_typed_piece_errors() {
+        let sector_size = SectorSize(4 * 128);
+        let piece = |size| PieceInfo::new([1; 32], UnpaddedBytesAmount(size)).unwrap();
+
+        let err = compute_comm_d(sector_size, &[piece(508), piece(127)]).unwrap_err();
+        match err.downcast_ref::<Error>() {
+            Some(Error::PiecesTooLarge { max, actual }) => {
+                assert_eq!(*max, 512);
+                assert_eq!(*actual, 640);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let err = compute_comm_d(sector_size, &[piece(381)]).unwrap_err();
+        match err.downcast_ref::<Error>() {
+            Some(Error::InvalidPieceSize(size)) => assert_eq!(*size, 384),
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let pieces = [piece(508)];
+        let err = ensure_pieces_match(&[2; 32], &pieces, sector_size).unwrap_err();
+        match err.downcast_ref::<Error>() {
+            Some(Error::PieceMismatch { expected, actual }) => {
+                assert_eq!(*expected, [1; 32]);
+                assert_eq!(*actual, [2; 32]);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
     #[test]
     fn test_verify_padded_pieces() {
         // [
@@ -530,6 +582,8 @@ mod tests {
         assert!(verify_pieces(&comm_d, &pieces, sector_size).unwrap());
     }
 
+    // builds sectors with `add_piece`
+    #[cfg(feature = "prover")]
     #[ignore] // slow test
     #[test]
     fn test_verify_random_pieces() -> Result<()> {
@@ -615,15 +669,26 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(feature = "prover")]
     fn build_sector(
         piece_sizes: &[UnpaddedBytesAmount],
         sector_size: SectorSize,
     ) -> Result<([u8; 32], Vec<PieceInfo>)> {
+        use std::io::{Seek, SeekFrom};
+
+        use paired::bls12_381::{Bls12, Fr};
+        use rand::RngCore;
+        use storage_proofs::drgraph::{new_seed, Graph};
+        use storage_proofs::stacked::StackedBucketGraph;
+
+        use crate::api::util::commitment_from_fr;
+        use crate::constants::{WINDOW_DRG_DEGREE, WINDOW_EXP_DEGREE};
+
         let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
         let graph = StackedBucketGraph::<DefaultPieceHasher>::new_stacked(
             u64::from(sector_size) as usize / NODE_SIZE,
-            WINDOW_DRG_DEGREE.load(Ordering::Relaxed) as usize,
-            WINDOW_EXP_DEGREE.load(Ordering::Relaxed) as usize,
+            WINDOW_DRG_DEGREE,
+            WINDOW_EXP_DEGREE,
             new_seed(),
         )?;
 
@@ -659,6 +724,7 @@ mod tests {
         Ok((comm_d, piece_infos))
     }
 
+    #[cfg(feature = "prover")]
     fn prev_power_of_two(mut x: u32) -> u32 {
         x |= x >> 1;
         x |= x >> 2;
@@ -0,0 +1,272 @@
+use anyhow::Result;
+use bellperson::Circuit;
+use paired::bls12_381::Bls12;
+use storage_proofs::circuit::bench::BenchCS;
+use storage_proofs::circuit::election_post::{ElectionPoStCircuit, ElectionPoStCompound};
+use storage_proofs::circuit::stacked::StackedCompound;
+use storage_proofs::compound_proof::CompoundProof;
+use storage_proofs::drgraph::DefaultTreeHasher;
+use storage_proofs::election_post::ElectionPoSt;
+use storage_proofs::settings::SETTINGS;
+use storage_proofs::stacked::{CacheKey, StackedDrg};
+use storage_proofs::thread_pools::ThreadPoolsConfig;
+use storage_proofs::util::NODE_SIZE;
+
+use crate::constants::DefaultPieceHasher;
+use crate::parameters::{post_public_params, public_params};
+use crate::types::*;
+
+/// Size of an uncompressed G1 point, as written to groth parameter files.
+const G1_SIZE: u64 = 96;
+/// Size of an uncompressed G2 point, as written to groth parameter files.
+const G2_SIZE: u64 = 192;
+/// Size of a field element.
+const FR_SIZE: u64 = 32;
+
+/// The size of a circuit, as counted by synthesizing its blank circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitSize {
+    pub constraints: u64,
+    pub inputs: u64,
+    pub aux: u64,
+}
+
+impl CircuitSize {
+    fn of<C: Circuit<Bls12>>(circuit: C) -> Result<Self> {
+        let mut cs = BenchCS::<Bls12>::new();
+        circuit.synthesize(&mut cs)?;
+
+        Ok(CircuitSize {
+            constraints: cs.num_constraints() as u64,
+            inputs: cs.num_inputs() as u64,
+            aux: cs.num_aux() as u64,
+        })
+    }
+
+    /// Number of points of the evaluation domain used by the prover.
+    pub fn domain_size(&self) -> u64 {
+        (self.constraints + self.inputs).next_power_of_two()
+    }
+
+    /// Estimated size of the groth parameter file of this circuit.
+    pub fn groth_parameters_size(&self) -> u64 {
+        let variables = self.inputs + self.aux;
+
+        // h, l, a, b_g1 and b_g2, a and b dropping the points at infinity which this ignores
+        G1_SIZE * (self.domain_size() + self.aux + 2 * variables) + G2_SIZE * variables
+    }
+
+    /// Estimated memory used to generate one proof, besides the groth parameters: the witness
+    /// and the evaluation domains of a, b and c, plus one more for the FFTs.
+    pub fn proving_memory(&self) -> u64 {
+        FR_SIZE * (self.inputs + self.aux + 4 * self.domain_size())
+    }
+}
+
+/// Estimated resources needed to seal and prove a sector. All sizes are in bytes.
+///
+/// The memory figures are estimates of the largest buffers held at once, they do not account for
+/// the page cache used by memory mapped files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceEstimate {
+    pub sector_size: u64,
+    /// Size of the replica written by `seal_pre_commit`.
+    pub sealed_size: u64,
+    /// Size of each artifact written to the cache directory, by name.
+    pub cache_artifacts: Vec<(String, u64)>,
+    /// Number of windows labeled at once, one per thread of the labeling pool.
+    pub labeling_threads: u64,
+    pub pre_commit_memory: u64,
+    /// Size of one porep partition circuit.
+    pub porep_circuit: CircuitSize,
+    pub porep_parameters_size: u64,
+    pub commit_memory: u64,
+    pub post_circuit: CircuitSize,
+    pub post_parameters_size: u64,
+    pub post_memory: u64,
+}
+
+impl ResourceEstimate {
+    /// Total scratch disk used by the cache directory.
+    pub fn cache_size(&self) -> u64 {
+        self.cache_artifacts.iter().map(|(_, size)| size).sum()
+    }
+}
+
+/// Estimates the memory and disk needed to seal sectors with `porep_config` and to prove them
+/// with the PoSt parameters registered for the sector size, on the thread pools configured in the
+/// settings.
+///
+/// This synthesizes the porep and PoSt circuits to count their constraints, which takes a while
+/// for large sectors.
+pub fn estimate_resources(porep_config: PoRepConfig) -> Result<ResourceEstimate> {
+    let pools = ThreadPoolsConfig::from_settings(&SETTINGS.lock().unwrap())?;
+    estimate_resources_with_pools(porep_config, &pools)
+}
+
+/// Same as `estimate_resources`, for sealing on `pools` (see `with_thread_pools`). A stage
+/// without a pool of its own is assumed to use all threads of the global rayon pool.
+pub fn estimate_resources_with_pools(
+    porep_config: PoRepConfig,
+    pools: &ThreadPoolsConfig,
+) -> Result<ResourceEstimate> {
+    let sector_size = u64::from(PaddedBytesAmount::from(porep_config));
+    let nodes = sector_size / NODE_SIZE as u64;
+    let partitions = usize::from(PoRepProofPartitions::from(porep_config)) as u64;
+    let layers = porep_config.params.layers;
+    let window_size = porep_config
+        .params
+        .window_size_nodes(PaddedBytesAmount::from(porep_config))?
+        * NODE_SIZE as u64;
+    let num_windows = sector_size / window_size;
+
+    // Every tree is stored in full, leaves included.
+    let tree_size = (2 * nodes - 1) * NODE_SIZE as u64;
+    let cache_artifacts = (0..layers)
+        .map(|layer| (CacheKey::label_layer(layer), sector_size))
+        .chain(
+            vec![
+                CacheKey::CommDTree,
+                CacheKey::CommCTree,
+                CacheKey::CommQTree,
+                CacheKey::CommRLastTree,
+            ]
+            .into_iter()
+            .map(|key| (key.to_string(), tree_size)),
+        )
+        .collect();
+
+    // Each window being labeled holds two layers of labels. Later, the column hashes are held
+    // while tree_c is built over them.
+    let pool_threads = pools
+        .labeling
+        .threads()
+        .unwrap_or_else(rayon::current_num_threads);
+    let labeling_threads = std::cmp::min(pool_threads as u64, num_windows);
+    let labeling_memory = labeling_threads * 2 * window_size;
+    let pre_commit_memory = std::cmp::max(labeling_memory, sector_size + tree_size);
+
+    let porep_public_params = public_params(
+        PaddedBytesAmount::from(porep_config),
+        usize::from(PoRepProofPartitions::from(porep_config)),
+        porep_config.params,
+    )?;
+    let porep_circuit = CircuitSize::of(<StackedCompound as CompoundProof<
+        _,
+        StackedDrg<DefaultTreeHasher, DefaultPieceHasher>,
+        _,
+    >>::blank_circuit(&porep_public_params))?;
+    let porep_parameters_size = porep_circuit.groth_parameters_size();
+
+    // All partitions are proven in parallel.
+    let commit_memory = porep_parameters_size + partitions * porep_circuit.proving_memory();
+
+    let post_config = PoStConfig::new(porep_config.sector_size)?;
+    let post_public_params = post_public_params(post_config)?;
+    let post_circuit =
+        CircuitSize::of(<ElectionPoStCompound<DefaultTreeHasher> as CompoundProof<
+            Bls12,
+            ElectionPoSt<DefaultTreeHasher>,
+            ElectionPoStCircuit<Bls12, DefaultTreeHasher>,
+        >>::blank_circuit(&post_public_params))?;
+    let post_parameters_size = post_circuit.groth_parameters_size();
+    let post_memory = post_parameters_size + post_circuit.proving_memory();
+
+    Ok(ResourceEstimate {
+        sector_size,
+        sealed_size: sector_size,
+        cache_artifacts,
+        labeling_threads,
+        pre_commit_memory,
+        porep_circuit,
+        porep_parameters_size,
+        commit_memory,
+        post_circuit,
+        post_parameters_size,
+        post_memory,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use storage_proofs::thread_pools::PoolConfig;
+
+    use crate::constants::SECTOR_SIZE_ONE_KIB;
+
+    #[test]
+    fn test_circuit_size() {
+        let circuit = CircuitSize {
+            constraints: 1000,
+            inputs: 24,
+            aux: 2000,
+        };
+
+        assert_eq!(circuit.domain_size(), 1024);
+        // 96 * (1024 + 2000 + 2 * 2024) + 192 * 2024
+        assert_eq!(circuit.groth_parameters_size(), 1_067_520);
+        // 32 * (24 + 2000 + 4 * 1024)
+        assert_eq!(circuit.proving_memory(), 195_840);
+    }
+
+    #[test]
+    fn test_estimate_sealing_resources() {
+        // 32 nodes in 4 windows of 8 nodes
+        let config = PoRepConfig {
+            sector_size: SectorSize(SECTOR_SIZE_ONE_KIB),
+            partitions: PoRepProofPartitions(1),
+            params: ProofParameters {
+                window_size: Some(256),
+                ..Default::default()
+            },
+        };
+        let pools = |labeling_threads| ThreadPoolsConfig {
+            labeling: PoolConfig::new(labeling_threads),
+            ..Default::default()
+        };
+
+        let estimate = estimate_resources_with_pools(config, &pools(2)).unwrap();
+        assert_eq!(estimate.labeling_threads, 2);
+        // each tree holds 2 * 32 - 1 nodes
+        assert_eq!(
+            estimate.cache_size(),
+            config.params.layers as u64 * 1024 + 4 * 63 * 32
+        );
+        // the replica and one tree outweigh 2 threads labeling 2 layers of 256 bytes
+        assert_eq!(estimate.pre_commit_memory, 1024 + 63 * 32);
+
+        // no more threads label at once than there are windows
+        let estimate = estimate_resources_with_pools(config, &pools(8)).unwrap();
+        assert_eq!(estimate.labeling_threads, 4);
+        assert_eq!(estimate.pre_commit_memory, 1024 + 63 * 32);
+
+        // all partitions are proven at once
+        assert_eq!(
+            estimate.commit_memory,
+            estimate.porep_parameters_size + estimate.porep_circuit.proving_memory()
+        );
+    }
+
+    #[test]
+    fn test_estimate_resources() {
+        let config = PoRepConfig::new(SectorSize(SECTOR_SIZE_ONE_KIB)).unwrap();
+        let estimate = estimate_resources(config).unwrap();
+
+        assert_eq!(estimate.sealed_size, SECTOR_SIZE_ONE_KIB);
+        assert_eq!(
+            estimate.cache_artifacts.len(),
+            config.params.layers + 4,
+            "one artifact per layer and per tree"
+        );
+        assert_eq!(
+            estimate.cache_size(),
+            config.params.layers as u64 * SECTOR_SIZE_ONE_KIB + 4 * (2 * 32 - 1) * 32
+        );
+
+        assert!(estimate.porep_circuit.constraints > 0);
+        assert!(estimate.porep_circuit.domain_size() >= estimate.porep_circuit.constraints);
+        assert!(estimate.commit_memory > estimate.porep_parameters_size);
+        assert!(estimate.post_memory > estimate.post_parameters_size);
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use anyhow::{anyhow, ensure, Result};
+use lazy_static::lazy_static;
+use storage_proofs::util::NODE_SIZE;
+
+use crate::constants::{
+    DEFAULT_POREP_PROOF_PARTITIONS, POST_CHALLENGED_NODES, POST_CHALLENGE_COUNT,
+    SECTOR_SIZE_16_MIB, SECTOR_SIZE_1_GIB, SECTOR_SIZE_256_MIB, SECTOR_SIZE_32_GIB,
+    SECTOR_SIZE_ONE_KIB,
+};
+
+/// The parameters used to seal and prove sectors of one size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectorSizeInfo {
+    /// Sector size in bytes.
+    pub sector_size: u64,
+    /// Window size in bytes.
+    pub window_size: u64,
+    /// Number of porep partitions.
+    pub partitions: u8,
+    pub post_challenge_count: usize,
+    pub post_challenged_nodes: usize,
+    /// Whether parameters for this size are published in `parameters.json`.
+    pub published: bool,
+}
+
+impl SectorSizeInfo {
+    /// Returns the production parameters for a sector of `sector_size` bytes with windows of
+    /// `window_size` bytes, which still have to be registered.
+    pub fn new(sector_size: u64, window_size: u64) -> Self {
+        SectorSizeInfo {
+            sector_size,
+            window_size,
+            partitions: DEFAULT_POREP_PROOF_PARTITIONS,
+            post_challenge_count: POST_CHALLENGE_COUNT,
+            post_challenged_nodes: POST_CHALLENGED_NODES,
+            published: false,
+        }
+    }
+
+    pub fn window_size_nodes(&self) -> u64 {
+        self.window_size / NODE_SIZE as u64
+    }
+
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            self.sector_size.is_power_of_two(),
+            "sector size ({}) must be a power of two",
+            self.sector_size
+        );
+        ensure!(
+            self.window_size.is_power_of_two() && self.window_size >= NODE_SIZE as u64,
+            "window size ({}) must be a power of two of at least {} bytes",
+            self.window_size,
+            NODE_SIZE
+        );
+        ensure!(
+            self.sector_size % self.window_size == 0,
+            "sector size ({}) must be a multiple of the window size ({})",
+            self.sector_size,
+            self.window_size
+        );
+        ensure!(self.partitions > 0, "partitions must be greater than zero");
+        ensure!(
+            self.post_challenge_count > 0 && self.post_challenged_nodes > 0,
+            "post challenge count and challenged nodes must be greater than zero"
+        );
+
+        Ok(())
+    }
+}
+
+fn published(sector_size: u64, window_size: u64) -> (u64, SectorSizeInfo) {
+    let info = SectorSizeInfo {
+        published: true,
+        ..SectorSizeInfo::new(sector_size, window_size)
+    };
+
+    (sector_size, info)
+}
+
+lazy_static! {
+    static ref SECTOR_SIZES: RwLock<BTreeMap<u64, SectorSizeInfo>> = RwLock::new(
+        vec![
+            published(SECTOR_SIZE_ONE_KIB, 512),
+            published(SECTOR_SIZE_16_MIB, 4 * 1024 * 1024),
+            published(SECTOR_SIZE_256_MIB, 64 * 1024 * 1024),
+            published(SECTOR_SIZE_1_GIB, 128 * 1024 * 1024),
+            published(SECTOR_SIZE_32_GIB, 128 * 1024 * 1024),
+        ]
+        .into_iter()
+        .collect()
+    );
+}
+
+/// Registers a sector size, making it usable by the API. Registering a size again is only
+/// allowed with identical parameters.
+pub fn register_sector_size(info: SectorSizeInfo) -> Result<()> {
+    info.validate()?;
+
+    let mut sizes = SECTOR_SIZES.write().unwrap();
+    if let Some(existing) = sizes.get(&info.sector_size) {
+        ensure!(
+            *existing == info,
+            "sector size {} is already registered as {:?}",
+            info.sector_size,
+            existing
+        );
+    }
+    sizes.insert(info.sector_size, info);
+
+    Ok(())
+}
+
+/// Returns the registered parameters for sectors of `sector_size` bytes.
+pub fn sector_size_info(sector_size: u64) -> Result<SectorSizeInfo> {
+    SECTOR_SIZES
+        .read()
+        .unwrap()
+        .get(&sector_size)
+        .copied()
+        .ok_or_else(|| anyhow!("Unknown sector size {}", sector_size))
+}
+
+/// Returns every registered sector size, smallest first.
+pub fn registered_sector_sizes() -> Vec<SectorSizeInfo> {
+    SECTOR_SIZES.read().unwrap().values().copied().collect()
+}
+
+/// Returns the sector sizes whose parameters are published in `parameters.json`.
+pub fn published_sector_sizes() -> Vec<u64> {
+    registered_sector_sizes()
+        .into_iter()
+        .filter(|info| info.published)
+        .map(|info| info.sector_size)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_sector_size() {
+        let size = 1 << 29;
+        assert!(sector_size_info(size).is_err());
+
+        // not a power of two
+        assert!(register_sector_size(SectorSizeInfo::new(size + 1, 1 << 20)).is_err());
+        // window larger than the sector
+        assert!(register_sector_size(SectorSizeInfo::new(size, size << 1)).is_err());
+        // window not a power of two
+        assert!(register_sector_size(SectorSizeInfo::new(size, 3 << 20)).is_err());
+        assert!(sector_size_info(size).is_err());
+
+        let info = SectorSizeInfo::new(size, 128 << 20);
+        register_sector_size(info).unwrap();
+        assert_eq!(sector_size_info(size).unwrap(), info);
+        assert!(!published_sector_sizes().contains(&size));
+
+        // registering the same size again only works with the same parameters
+        register_sector_size(info).unwrap();
+        assert!(register_sector_size(SectorSizeInfo::new(size, 64 << 20)).is_err());
+    }
+
+    #[test]
+    fn test_published_sector_sizes() {
+        assert_eq!(
+            published_sector_sizes(),
+            vec![
+                SECTOR_SIZE_ONE_KIB,
+                SECTOR_SIZE_16_MIB,
+                SECTOR_SIZE_256_MIB,
+                SECTOR_SIZE_1_GIB,
+                SECTOR_SIZE_32_GIB
+            ]
+        );
+    }
+}
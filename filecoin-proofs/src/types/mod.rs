@@ -1,6 +1,9 @@
+use std::path::PathBuf;
+
 use storage_proofs::hasher::pedersen::{PedersenDomain, PedersenHasher};
 use storage_proofs::hasher::Hasher;
 use storage_proofs::merkle::MerkleTree;
+use storage_proofs::sector::SectorId;
 use storage_proofs::stacked;
 
 mod bytes_amount;
@@ -9,8 +12,10 @@ mod porep_config;
 mod porep_proof_partitions;
 mod post_config;
 mod post_proof_partitions;
+mod proof_parameters;
 mod sector_class;
 mod sector_size;
+mod window_post_config;
 
 pub use self::bytes_amount::*;
 pub use self::piece_info::*;
@@ -18,8 +23,10 @@ pub use self::porep_config::*;
 pub use self::porep_proof_partitions::*;
 pub use self::post_config::*;
 pub use self::post_proof_partitions::*;
+pub use self::proof_parameters::*;
 pub use self::sector_class::*;
 pub use self::sector_size::*;
+pub use self::window_post_config::*;
 
 pub type Commitment = [u8; 32];
 pub type ChallengeSeed = [u8; 32];
@@ -37,6 +44,18 @@ pub struct SealPreCommitOutput {
     pub comm_d: Commitment,
 }
 
+/// One of the sectors sealed together by `seal_pre_commit_many`. The fields are the arguments of
+/// the same name of `seal_pre_commit`.
+#[derive(Clone, Debug)]
+pub struct SealPreCommitSector {
+    pub cache_path: PathBuf,
+    pub in_path: PathBuf,
+    pub out_path: PathBuf,
+    pub sector_id: SectorId,
+    pub ticket: Ticket,
+    pub piece_infos: Vec<PieceInfo>,
+}
+
 #[derive(Clone, Debug)]
 pub struct SealCommitOutput {
     pub proof: Vec<u8>,
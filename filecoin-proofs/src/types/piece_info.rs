@@ -2,6 +2,7 @@ use std::fmt;
 
 use anyhow::{ensure, Result};
 
+use crate::error::Error;
 use crate::types::{Commitment, UnpaddedBytesAmount};
 
 #[derive(Clone, Default, PartialEq, Eq)]
@@ -21,7 +22,7 @@ impl fmt::Debug for PieceInfo {
 
 impl PieceInfo {
     pub fn new(commitment: Commitment, size: UnpaddedBytesAmount) -> Result<Self> {
-        ensure!(commitment != [0; 32], "Invalid all zero commitment");
+        ensure!(commitment != [0; 32], Error::ZeroCommitment("comm_p"));
         Ok(PieceInfo { commitment, size })
     }
 }
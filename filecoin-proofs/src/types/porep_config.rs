@@ -10,10 +10,11 @@ use storage_proofs::parameter_cache::{self, CacheableParameters};
 use crate::constants::DefaultPieceHasher;
 use crate::types::*;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct PoRepConfig {
     pub sector_size: SectorSize,
     pub partitions: PoRepProofPartitions,
+    pub params: ProofParameters,
 }
 
 impl From<PoRepConfig> for PaddedBytesAmount {
@@ -48,10 +49,23 @@ impl From<PoRepConfig> for SectorSize {
 }
 
 impl PoRepConfig {
+    /// Returns the config for sealing sectors of a registered size, using the registered number
+    /// of partitions and the production proof parameters.
+    pub fn new(sector_size: SectorSize) -> Result<Self> {
+        Ok(PoRepConfig {
+            sector_size,
+            partitions: PoRepProofPartitions(sector_size.info()?.partitions),
+            params: ProofParameters::default(),
+        })
+    }
+
     /// Returns the cache identifier as used by `storage-proofs::paramater_cache`.
     pub fn get_cache_identifier(&self) -> Result<String> {
-        let params =
-            crate::parameters::public_params(self.sector_size.into(), self.partitions.into())?;
+        let params = crate::parameters::public_params(
+            self.sector_size.into(),
+            self.partitions.into(),
+            self.params,
+        )?;
 
         Ok(<StackedCompound as CacheableParameters<
             Bls12,
@@ -9,7 +9,7 @@ use storage_proofs::parameter_cache::{self, CacheableParameters};
 
 use crate::types::*;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct PoStConfig {
     pub sector_size: SectorSize,
     pub challenge_count: usize,
@@ -33,6 +33,17 @@ impl From<PoStConfig> for UnpaddedBytesAmount {
 }
 
 impl PoStConfig {
+    /// Returns the config for proving sectors of a registered size.
+    pub fn new(sector_size: SectorSize) -> Result<Self> {
+        let info = sector_size.info()?;
+
+        Ok(PoStConfig {
+            sector_size,
+            challenge_count: info.post_challenge_count,
+            challenged_nodes: info.post_challenged_nodes,
+        })
+    }
+
     /// Returns the cache identifier as used by `storage-proofs::paramater_cache`.
     pub fn get_cache_identifier(self) -> Result<String> {
         let params = crate::parameters::post_public_params(self)?;
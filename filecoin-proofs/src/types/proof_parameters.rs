@@ -0,0 +1,65 @@
+use anyhow::{ensure, Result};
+use storage_proofs::util::NODE_SIZE;
+
+use crate::constants::{
+    LAYERS, POREP_WINDOW_MINIMUM_CHALLENGES, POREP_WRAPPER_MINIMUM_CHALLENGES, WINDOW_DRG_DEGREE,
+    WINDOW_EXP_DEGREE, WRAPPER_EXP_DEGREE,
+};
+use crate::sector_sizes::sector_size_info;
+use crate::types::*;
+
+/// The stacked DRG parameters which, together with the sector size and the number of partitions,
+/// determine the porep circuit. `ProofParameters::default()` is the production profile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ProofParameters {
+    pub layers: usize,
+    pub window_minimum_challenges: usize,
+    pub wrapper_minimum_challenges: usize,
+    pub window_drg_degree: usize,
+    pub window_expansion_degree: usize,
+    pub wrapper_expansion_degree: usize,
+    /// Window size in bytes. `None` selects the window size registered for the sector size.
+    pub window_size: Option<u64>,
+}
+
+impl Default for ProofParameters {
+    fn default() -> Self {
+        ProofParameters {
+            layers: LAYERS,
+            window_minimum_challenges: POREP_WINDOW_MINIMUM_CHALLENGES,
+            wrapper_minimum_challenges: POREP_WRAPPER_MINIMUM_CHALLENGES,
+            window_drg_degree: WINDOW_DRG_DEGREE,
+            window_expansion_degree: WINDOW_EXP_DEGREE,
+            wrapper_expansion_degree: WRAPPER_EXP_DEGREE,
+            window_size: None,
+        }
+    }
+}
+
+impl ProofParameters {
+    /// Returns the number of nodes in a window of a sector of `sector_size` bytes. Fails if
+    /// `window_size` is set to a size which is not a power of two of at least one node, or which
+    /// does not divide the sector.
+    pub fn window_size_nodes(&self, sector_size: PaddedBytesAmount) -> Result<u64> {
+        let sector_size = u64::from(sector_size);
+        let window_size = match self.window_size {
+            Some(window_size) => window_size,
+            None => sector_size_info(sector_size)?.window_size,
+        };
+
+        ensure!(
+            window_size.is_power_of_two() && window_size >= NODE_SIZE as u64,
+            "window size ({}) must be a power of two of at least {} bytes",
+            window_size,
+            NODE_SIZE
+        );
+        ensure!(
+            sector_size % window_size == 0,
+            "sector size ({}) must be a multiple of the window size ({})",
+            sector_size,
+            window_size
+        );
+
+        Ok(window_size / NODE_SIZE as u64)
+    }
+}
@@ -1,9 +1,19 @@
+use anyhow::Result;
+
 use crate::fr32::unpadded_bytes;
+use crate::sector_sizes::{sector_size_info, SectorSizeInfo};
 use crate::types::*;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct SectorSize(pub u64);
 
+impl SectorSize {
+    /// Returns the parameters registered for this sector size.
+    pub fn info(self) -> Result<SectorSizeInfo> {
+        sector_size_info(self.0)
+    }
+}
+
 impl From<SectorSize> for UnpaddedBytesAmount {
     fn from(x: SectorSize) -> Self {
         UnpaddedBytesAmount(unpadded_bytes(x.0))
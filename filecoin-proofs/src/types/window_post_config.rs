@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use paired::bls12_381::Bls12;
+use storage_proofs::circuit::window_post::{WindowPoStCircuit, WindowPoStCompound};
+use storage_proofs::drgraph::DefaultTreeHasher;
+use storage_proofs::parameter_cache::{self, CacheableParameters};
+
+use crate::constants::{WINDOW_POST_CHALLENGE_COUNT, WINDOW_POST_SECTOR_COUNT};
+use crate::types::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WindowPoStConfig {
+    pub sector_size: SectorSize,
+    /// Leaf challenges per sector.
+    pub challenge_count: usize,
+    /// Sectors proven by each partition.
+    pub sector_count: usize,
+}
+
+impl From<WindowPoStConfig> for PaddedBytesAmount {
+    fn from(x: WindowPoStConfig) -> Self {
+        PaddedBytesAmount::from(x.sector_size)
+    }
+}
+
+impl WindowPoStConfig {
+    /// Returns the config for proving sectors of a registered size.
+    pub fn new(sector_size: SectorSize) -> Result<Self> {
+        sector_size.info()?;
+
+        Ok(WindowPoStConfig {
+            sector_size,
+            challenge_count: WINDOW_POST_CHALLENGE_COUNT,
+            sector_count: WINDOW_POST_SECTOR_COUNT,
+        })
+    }
+
+    /// Returns the number of partitions needed to prove `sector_total` sectors.
+    pub fn partitions(self, sector_total: usize) -> usize {
+        (sector_total + self.sector_count - 1) / self.sector_count
+    }
+
+    /// Returns the cache identifier as used by `storage-proofs::paramater_cache`.
+    pub fn get_cache_identifier(self) -> Result<String> {
+        let params = crate::parameters::window_post_public_params(self)?;
+
+        Ok(
+            <WindowPoStCompound<DefaultTreeHasher> as CacheableParameters<
+                Bls12,
+                WindowPoStCircuit<_, DefaultTreeHasher>,
+                _,
+            >>::cache_identifier(&params),
+        )
+    }
+
+    pub fn get_cache_metadata_path(self) -> Result<PathBuf> {
+        let id = self.get_cache_identifier()?;
+        Ok(parameter_cache::parameter_cache_metadata_path(&id))
+    }
+
+    pub fn get_cache_verifying_key_path(self) -> Result<PathBuf> {
+        let id = self.get_cache_identifier()?;
+        Ok(parameter_cache::parameter_cache_verifying_key_path(&id))
+    }
+
+    pub fn get_cache_params_path(self) -> Result<PathBuf> {
+        let id = self.get_cache_identifier()?;
+        Ok(parameter_cache::parameter_cache_params_path(&id))
+    }
+}
@@ -0,0 +1,39 @@
+//! Everything needed to verify seal and PoSt proofs, and nothing more.
+//!
+//! Verifiers can depend on this crate with `default-features = false`, which leaves out the
+//! `prover` feature and with it sealing, PoSt generation and piece preprocessing. Verification
+//! only needs the `.vk` files in the parameter cache, verifying keys registered from bytes with
+//! the `register_*_verifying_key` functions (or embedded with the `embedded-verifying-keys`
+//! feature), or keys passed directly to the `*_with_verifying_key` functions.
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+pub use crate::api::{
+    compute_comm_d, finalize_ticket, is_winning_ticket, select_winners, verify_pieces, verify_post,
+    verify_post_with_verifying_key, verify_seal, verify_seal_with_verifying_key,
+    verify_window_post, verify_window_post_with_verifying_key, Candidate, PublicReplicaInfo,
+    WinnerTargetParams,
+};
+pub use crate::caches::{
+    get_post_verifying_key, get_stacked_verifying_key, get_window_post_verifying_key,
+    register_post_verifying_key, register_stacked_verifying_key, register_verifying_key,
+    register_window_post_verifying_key, Bls12VerifyingKey,
+};
+
+/// Reads a verifying key in the format of the `.vk` files in the parameter cache.
+pub fn read_verifying_key<R: Read>(reader: R) -> Result<Bls12VerifyingKey> {
+    Bls12VerifyingKey::read(reader).context("could not read verifying key")
+}
+
+/// Reads the verifying key stored in the `.vk` file at `path`.
+pub fn read_verifying_key_file<P: AsRef<Path>>(path: P) -> Result<Bls12VerifyingKey> {
+    let path = path.as_ref();
+    let file = File::open(path).with_context(|| format!("could not open {:?}", path))?;
+
+    read_verifying_key(BufReader::new(file))
+        .with_context(|| format!("invalid verifying key in {:?}", path))
+}
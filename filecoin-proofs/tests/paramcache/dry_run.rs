@@ -0,0 +1,72 @@
+use std::fs::File;
+
+use failure::{Error as FailureError, SyncFailure};
+
+use crate::support::{cargo_bin, spawn_bash_with_retries};
+use filecoin_proofs::constants::SECTOR_SIZE_ONE_KIB;
+use filecoin_proofs::types::{PoStConfig, SectorSize};
+use storage_proofs::parameter_cache::PARAMETER_CACHE_ENV_VAR;
+
+#[test]
+fn dry_run_prints_cache_entries_without_generating() -> Result<(), FailureError> {
+    let cache_dir = tempfile::tempdir()?;
+
+    // the verifying key of election-post is already in the cache
+    let post_config = PoStConfig::new(SectorSize(SECTOR_SIZE_ONE_KIB))?;
+    let id = post_config.get_cache_identifier()?;
+    let vk_name = post_config
+        .get_cache_verifying_key_path()?
+        .file_name()
+        .and_then(|f| f.to_str())
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    File::create(cache_dir.path().join(&vk_name))?;
+
+    let mut p = spawn_bash_with_retries(10, Some(5000)).map_err(SyncFailure::new)?;
+
+    let cmd = format!(
+        "{}={:?} {:?} --dry-run -z {} --circuits=election-post,rational-post",
+        PARAMETER_CACHE_ENV_VAR,
+        cache_dir.path(),
+        cargo_bin("paramcache"),
+        SECTOR_SIZE_ONE_KIB
+    );
+    p.execute(&cmd, ".*").map_err(SyncFailure::new)?;
+
+    p.exp_string(&format!("election-post (1024 bytes): {}", id))
+        .map_err(SyncFailure::new)?;
+    p.exp_string(&format!(
+        "exists   {}",
+        cache_dir.path().join(&vk_name).display()
+    ))
+    .map_err(SyncFailure::new)?;
+    p.exp_string("rational-post (1024 bytes): ")
+        .map_err(SyncFailure::new)?;
+    p.exp_string("missing").map_err(SyncFailure::new)?;
+
+    // nothing was generated
+    assert_eq!(std::fs::read_dir(cache_dir.path())?.count(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn dry_run_accepts_registered_sector_sizes() -> Result<(), FailureError> {
+    let cache_dir = tempfile::tempdir()?;
+
+    let mut p = spawn_bash_with_retries(10, Some(5000)).map_err(SyncFailure::new)?;
+
+    let cmd = format!(
+        "{}={:?} {:?} --dry-run --register-sector-size 2048:1024 -z 2048 --circuits=election-post",
+        PARAMETER_CACHE_ENV_VAR,
+        cache_dir.path(),
+        cargo_bin("paramcache"),
+    );
+    p.execute(&cmd, ".*").map_err(SyncFailure::new)?;
+
+    p.exp_string("election-post (2048 bytes): ")
+        .map_err(SyncFailure::new)?;
+    p.exp_string("missing").map_err(SyncFailure::new)?;
+
+    Ok(())
+}
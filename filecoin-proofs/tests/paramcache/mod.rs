@@ -0,0 +1,2 @@
+pub mod dry_run;
+pub mod status_and_gc;
@@ -0,0 +1,82 @@
+use std::collections::btree_map::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+
+use failure::{Error as FailureError, SyncFailure};
+
+use crate::paramfetch::support::rand_bytes_with_blake2b;
+use crate::support::{cargo_bin, spawn_bash_with_retries, tmp_manifest};
+use filecoin_proofs::param::ParameterData;
+use storage_proofs::parameter_cache::{PARAMETER_CACHE_ENV_VAR, VERSION};
+
+#[test]
+fn classifies_and_removes_cache_entries() -> Result<(), FailureError> {
+    let current = format!("v{}-aaa.vk", VERSION);
+    let corrupt = format!("v{}-bbb.vk", VERSION);
+    let stale = format!("v{}-ccc.vk", VERSION - 1);
+    let unknown = format!("v{}-ddd.vk", VERSION);
+    let ipget_dir = format!("v{}-eee.vk.ipget-abc123", VERSION);
+
+    let (aaa_bytes, aaa_checksum) = rand_bytes_with_blake2b()?;
+    let (bbb_bytes, _) = rand_bytes_with_blake2b()?;
+
+    let mut manifest: BTreeMap<String, ParameterData> = BTreeMap::new();
+    manifest.insert(
+        current.clone(),
+        ParameterData {
+            cid: "".to_string(),
+            digest: aaa_checksum,
+            sector_size: 1234,
+        },
+    );
+    manifest.insert(
+        corrupt.clone(),
+        ParameterData {
+            cid: "".to_string(),
+            digest: "obviouslywrong".to_string(),
+            sector_size: 1234,
+        },
+    );
+
+    let cache_dir = tempfile::tempdir()?;
+    File::create(cache_dir.path().join(&current))?.write_all(&aaa_bytes)?;
+    File::create(cache_dir.path().join(&corrupt))?.write_all(&bbb_bytes)?;
+    File::create(cache_dir.path().join(&stale))?.write_all(&bbb_bytes)?;
+    File::create(cache_dir.path().join(&unknown))?.write_all(&bbb_bytes)?;
+    // left behind by an interrupted ipget download, without a partial file being written
+    fs::create_dir(cache_dir.path().join(&ipget_dir))?;
+    File::create(cache_dir.path().join(&ipget_dir).join("cid"))?.write_all(&bbb_bytes)?;
+
+    let manifest_pbuf = tmp_manifest(Some(manifest))?;
+
+    let mut p = spawn_bash_with_retries(10, Some(1000)).map_err(SyncFailure::new)?;
+
+    let cmd = format!(
+        "{}={:?} {:?} gc --json={:?}",
+        PARAMETER_CACHE_ENV_VAR,
+        cache_dir.path(),
+        cargo_bin("paramcache"),
+        manifest_pbuf
+    );
+    p.execute(&cmd, ".*").map_err(SyncFailure::new)?;
+
+    p.exp_string(&format!("current  {:>14} {}", aaa_bytes.len(), current))
+        .map_err(SyncFailure::new)?;
+    p.exp_string(&format!("corrupt  {:>14} {}", bbb_bytes.len(), corrupt))
+        .map_err(SyncFailure::new)?;
+    p.exp_string(&format!("stale    {:>14} {}", bbb_bytes.len(), stale))
+        .map_err(SyncFailure::new)?;
+    p.exp_string(&format!("unknown  {:>14} {}", bbb_bytes.len(), unknown))
+        .map_err(SyncFailure::new)?;
+    p.exp_string(&format!("stale    {:>14} {}", bbb_bytes.len(), ipget_dir))
+        .map_err(SyncFailure::new)?;
+    p.exp_string("removed 3 files").map_err(SyncFailure::new)?;
+
+    assert!(cache_dir.path().join(&current).exists());
+    assert!(!cache_dir.path().join(&corrupt).exists());
+    assert!(!cache_dir.path().join(&stale).exists());
+    assert!(cache_dir.path().join(&unknown).exists());
+    assert!(!cache_dir.path().join(&ipget_dir).exists());
+
+    Ok(())
+}
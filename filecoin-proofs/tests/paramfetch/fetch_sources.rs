@@ -0,0 +1,162 @@
+use std::collections::btree_map::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+
+use failure::Error as FailureError;
+
+use crate::paramfetch::support::rand_bytes_with_blake2b;
+use crate::paramfetch::support::session::ParamFetchSessionBuilder;
+use crate::support::tmp_manifest;
+use filecoin_proofs::param::ParameterData;
+
+#[test]
+fn fetches_from_dir_source() -> Result<(), FailureError> {
+    let mut manifest: BTreeMap<String, ParameterData> = BTreeMap::new();
+
+    let (aaa_bytes, aaa_checksum) = rand_bytes_with_blake2b()?;
+    let (bbb_bytes, _) = rand_bytes_with_blake2b()?;
+
+    manifest.insert(
+        "aaa.vk".to_string(),
+        ParameterData {
+            cid: "".to_string(),
+            digest: aaa_checksum,
+            sector_size: 1234,
+        },
+    );
+
+    // the mirror holds a file which does not match the manifest
+    manifest.insert(
+        "bbb.vk".to_string(),
+        ParameterData {
+            cid: "".to_string(),
+            digest: "obviouslywrong".to_string(),
+            sector_size: 1234,
+        },
+    );
+
+    let mirror_dir = tempfile::tempdir()?;
+    File::create(mirror_dir.path().join("aaa.vk"))?.write_all(&aaa_bytes)?;
+    File::create(mirror_dir.path().join("bbb.vk"))?.write_all(&bbb_bytes)?;
+
+    let manifest_pbuf = tmp_manifest(Some(manifest))?;
+
+    let mut session = ParamFetchSessionBuilder::new(Some(manifest_pbuf))
+        .with_session_timeout_ms(1000)
+        .with_source(format!("dir:{}", mirror_dir.path().display()))
+        .with_jobs(1)
+        .fetch_all()
+        .build();
+
+    session.exp_string("fetching from directory")?;
+    session.exp_string("fetching: aaa.vk")?;
+    session.exp_string("aaa.vk: ok")?;
+    session.exp_string("fetching: bbb.vk")?;
+    session.exp_string("bbb.vk: error: digest of bbb.vk does not match the manifest")?;
+    session.exp_string("1 files failed to be fetched")?;
+
+    Ok(())
+}
+
+#[test]
+fn resumes_partial_download() -> Result<(), FailureError> {
+    let mut manifest: BTreeMap<String, ParameterData> = BTreeMap::new();
+
+    let (aaa_bytes, aaa_checksum) = rand_bytes_with_blake2b()?;
+
+    manifest.insert(
+        "aaa.vk".to_string(),
+        ParameterData {
+            cid: "".to_string(),
+            digest: aaa_checksum,
+            sector_size: 1234,
+        },
+    );
+
+    let mirror_dir = tempfile::tempdir()?;
+    File::create(mirror_dir.path().join("aaa.vk"))?.write_all(&aaa_bytes)?;
+
+    let manifest_pbuf = tmp_manifest(Some(manifest))?;
+
+    // an earlier download stopped half way through
+    let mut partial: &[u8] = &aaa_bytes[..16];
+
+    let mut session = ParamFetchSessionBuilder::new(Some(manifest_pbuf))
+        .with_session_timeout_ms(1000)
+        .with_source(format!("dir:{}", mirror_dir.path().display()))
+        .with_file_and_bytes("aaa.vk.partial", &mut partial)
+        .fetch_all()
+        .build();
+
+    session.exp_string("fetching: aaa.vk")?;
+    session.exp_string("aaa.vk: resuming from byte 16")?;
+    session.exp_string("aaa.vk: ok")?;
+    session.exp_string("done")?;
+
+    Ok(())
+}
+
+#[test]
+fn reports_json_without_prompting() -> Result<(), FailureError> {
+    let mut manifest: BTreeMap<String, ParameterData> = BTreeMap::new();
+
+    let (aaa_bytes, aaa_checksum) = rand_bytes_with_blake2b()?;
+    let (bbb_bytes, bbb_checksum) = rand_bytes_with_blake2b()?;
+
+    manifest.insert(
+        "aaa.vk".to_string(),
+        ParameterData {
+            cid: "".to_string(),
+            digest: aaa_checksum.clone(),
+            sector_size: 1234,
+        },
+    );
+    manifest.insert(
+        "bbb.vk".to_string(),
+        ParameterData {
+            cid: "".to_string(),
+            digest: bbb_checksum,
+            sector_size: 1234,
+        },
+    );
+
+    let mirror_dir = tempfile::tempdir()?;
+    File::create(mirror_dir.path().join("bbb.vk"))?.write_all(&bbb_bytes)?;
+
+    let manifest_pbuf = tmp_manifest(Some(manifest))?;
+
+    // aaa.vk is already in the cache, bbb.vk is fetched
+    let mut session = ParamFetchSessionBuilder::new(Some(manifest_pbuf))
+        .with_session_timeout_ms(1000)
+        .with_source(format!("dir:{}", mirror_dir.path().display()))
+        .with_file_and_bytes("aaa.vk", &mut &aaa_bytes[..])
+        .with_arg("--non-interactive")
+        .with_arg("--output=json")
+        .build();
+
+    session.exp_string("\"id\": \"aaa.vk\"")?;
+    session.exp_string(&format!("\"actual_digest\": \"{}\"", aaa_checksum))?;
+    session.exp_string("\"action\": \"present\"")?;
+    session.exp_string("\"id\": \"bbb.vk\"")?;
+    session.exp_string("\"action\": \"fetched\"")?;
+
+    session.send_line("echo \"exit code: $?\"")?;
+    session.exp_string("exit code: 2")?;
+
+    Ok(())
+}
+
+#[test]
+fn unknown_source_produces_error() -> Result<(), FailureError> {
+    let manifest_pbuf = tmp_manifest(Some(BTreeMap::new()))?;
+
+    let mut session = ParamFetchSessionBuilder::new(Some(manifest_pbuf))
+        .with_session_timeout_ms(1000)
+        .with_source("carrier-pigeon")
+        .fetch_all()
+        .build();
+
+    session.exp_string("fatal error: unknown source \"carrier-pigeon\"")?;
+
+    Ok(())
+}
@@ -1,3 +1,5 @@
-mod support;
+pub mod support;
 
+pub mod fetch_sources;
 pub mod prompts_to_fetch;
+pub mod signed_manifests;
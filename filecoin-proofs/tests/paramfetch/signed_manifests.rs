@@ -0,0 +1,121 @@
+use std::collections::btree_map::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use ed25519_dalek::Keypair;
+use failure::Error as FailureError;
+use rand::rngs::OsRng;
+
+use crate::paramfetch::support::rand_bytes_with_blake2b;
+use crate::paramfetch::support::session::ParamFetchSessionBuilder;
+use crate::support::tmp_manifest;
+use filecoin_proofs::param::{sign_manifest, signature_path, ParameterData};
+
+/// Writes a manifest of one file, `aaa.vk`, to a temp directory, and the file itself to a mirror
+/// directory, returning the paths of the manifest and of the mirror.
+fn manifest_and_mirror() -> Result<(PathBuf, tempfile::TempDir), FailureError> {
+    let (aaa_bytes, aaa_checksum) = rand_bytes_with_blake2b()?;
+
+    let mut manifest: BTreeMap<String, ParameterData> = BTreeMap::new();
+    manifest.insert(
+        "aaa.vk".to_string(),
+        ParameterData {
+            cid: "".to_string(),
+            digest: aaa_checksum,
+            sector_size: 1234,
+        },
+    );
+
+    let mirror_dir = tempfile::tempdir()?;
+    File::create(mirror_dir.path().join("aaa.vk"))?.write_all(&aaa_bytes)?;
+
+    Ok((tmp_manifest(Some(manifest))?, mirror_dir))
+}
+
+fn sign<P: AsRef<Path>>(manifest_path: P, keypair: &Keypair) -> Result<(), FailureError> {
+    let signature = sign_manifest(&fs::read(manifest_path.as_ref())?, keypair);
+    let file = File::create(signature_path(manifest_path))?;
+    serde_json::to_writer(file, &signature)?;
+
+    Ok(())
+}
+
+#[test]
+fn accepts_manifest_signed_by_trusted_key() -> Result<(), FailureError> {
+    let (manifest_pbuf, mirror_dir) = manifest_and_mirror()?;
+    let keypair = Keypair::generate(&mut OsRng);
+    sign(&manifest_pbuf, &keypair)?;
+
+    let mut session = ParamFetchSessionBuilder::new(Some(manifest_pbuf))
+        .with_session_timeout_ms(1000)
+        .with_source(format!("dir:{}", mirror_dir.path().display()))
+        .with_trusted_key(hex::encode(keypair.public.as_bytes()))
+        .fetch_all()
+        .build();
+
+    session.exp_string("manifest signed by trusted key")?;
+    session.exp_string("aaa.vk: ok")?;
+    session.exp_string("done")?;
+
+    Ok(())
+}
+
+#[test]
+fn rejects_manifest_signed_by_untrusted_key() -> Result<(), FailureError> {
+    let (manifest_pbuf, mirror_dir) = manifest_and_mirror()?;
+    let keypair = Keypair::generate(&mut OsRng);
+    let trusted = Keypair::generate(&mut OsRng);
+    sign(&manifest_pbuf, &keypair)?;
+
+    let mut session = ParamFetchSessionBuilder::new(Some(manifest_pbuf))
+        .with_session_timeout_ms(1000)
+        .with_source(format!("dir:{}", mirror_dir.path().display()))
+        .with_trusted_key(hex::encode(trusted.public.as_bytes()))
+        .fetch_all()
+        .build();
+
+    session.exp_string("fatal error: manifest is signed by untrusted key")?;
+
+    Ok(())
+}
+
+#[test]
+fn rejects_tampered_manifest() -> Result<(), FailureError> {
+    let (manifest_pbuf, mirror_dir) = manifest_and_mirror()?;
+    let keypair = Keypair::generate(&mut OsRng);
+    sign(&manifest_pbuf, &keypair)?;
+
+    // change the manifest after it was signed
+    let tampered =
+        fs::read_to_string(&manifest_pbuf)?.replace("\"sector_size\":1234", "\"sector_size\":4321");
+    fs::write(&manifest_pbuf, tampered)?;
+
+    let mut session = ParamFetchSessionBuilder::new(Some(manifest_pbuf))
+        .with_session_timeout_ms(1000)
+        .with_source(format!("dir:{}", mirror_dir.path().display()))
+        .with_trusted_key(hex::encode(keypair.public.as_bytes()))
+        .fetch_all()
+        .build();
+
+    session.exp_string("fatal error: manifest signature does not match the manifest")?;
+
+    Ok(())
+}
+
+#[test]
+fn rejects_unsigned_manifest_without_override() -> Result<(), FailureError> {
+    let (manifest_pbuf, mirror_dir) = manifest_and_mirror()?;
+    let keypair = Keypair::generate(&mut OsRng);
+
+    let mut session = ParamFetchSessionBuilder::new(Some(manifest_pbuf))
+        .with_session_timeout_ms(1000)
+        .with_source(format!("dir:{}", mirror_dir.path().display()))
+        .with_trusted_key(hex::encode(keypair.public.as_bytes()))
+        .fetch_all()
+        .build();
+
+    session.exp_string("is not signed, pass --allow-unsigned to use it anyway")?;
+
+    Ok(())
+}
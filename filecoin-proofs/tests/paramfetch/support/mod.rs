@@ -1 +1,23 @@
+use blake2b_simd::State as Blake2b;
+use failure::Error as FailureError;
+use rand::Rng;
+
 pub mod session;
+
+/// Produce a random sequence of bytes and first 32 characters of hex encoded
+/// BLAKE2b checksum. This helper function must be kept up-to-date with the
+/// parampublish implementation.
+pub fn rand_bytes_with_blake2b() -> Result<(Vec<u8>, String), FailureError> {
+    let bytes = rand::thread_rng().gen::<[u8; 32]>();
+
+    let mut hasher = Blake2b::new();
+
+    let mut as_slice = &bytes[..];
+
+    std::io::copy(&mut as_slice, &mut hasher)?;
+
+    Ok((
+        bytes.iter().cloned().collect(),
+        hasher.finalize().to_hex()[..32].into(),
+    ))
+}
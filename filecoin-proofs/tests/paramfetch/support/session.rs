@@ -16,6 +16,10 @@ pub struct ParamFetchSessionBuilder {
     whitelisted_sector_sizes: Option<Vec<String>>,
     manifest: Option<PathBuf>,
     prompt_enabled: bool,
+    source: Option<String>,
+    jobs: Option<usize>,
+    trusted_key: Option<String>,
+    extra_args: Vec<String>,
 }
 
 impl ParamFetchSessionBuilder {
@@ -28,6 +32,10 @@ impl ParamFetchSessionBuilder {
             manifest,
             prompt_enabled: true,
             whitelisted_sector_sizes: None,
+            source: None,
+            jobs: None,
+            trusted_key: None,
+            extra_args: vec![],
         }
     }
 
@@ -46,6 +54,37 @@ impl ParamFetchSessionBuilder {
         self
     }
 
+    /// Fetch every file in the manifest without prompting.
+    pub fn fetch_all(mut self) -> ParamFetchSessionBuilder {
+        self.prompt_enabled = false;
+        self
+    }
+
+    /// Fetch from the given source (see `paramfetch --source`) instead of the fake ipget.
+    pub fn with_source<S: Into<String>>(mut self, source: S) -> ParamFetchSessionBuilder {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Download this many files at once.
+    pub fn with_jobs(mut self, jobs: usize) -> ParamFetchSessionBuilder {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Verify the signature of the manifest with the given hex encoded public key, instead of
+    /// accepting unsigned manifests.
+    pub fn with_trusted_key<S: Into<String>>(mut self, key: S) -> ParamFetchSessionBuilder {
+        self.trusted_key = Some(key.into());
+        self
+    }
+
+    /// Pass an additional argument to paramfetch.
+    pub fn with_arg<S: Into<String>>(mut self, arg: S) -> ParamFetchSessionBuilder {
+        self.extra_args.push(arg.into());
+        self
+    }
+
     /// Create a file with the provided bytes in the cache directory.
     pub fn with_file_and_bytes<P: AsRef<Path>, R: Read>(
         self,
@@ -86,15 +125,33 @@ impl ParamFetchSessionBuilder {
             "".to_string()
         };
 
+        let source_argument = match self.source {
+            Some(source) => format!("--source={:?}", source),
+            None => format!("--ipget-bin={:?}", "true"),
+        };
+
+        let signature_argument = match self.trusted_key {
+            Some(key) => format!("--trusted-key={}", key),
+            None => "--allow-unsigned".to_string(),
+        };
+
+        let jobs_argument = self
+            .jobs
+            .map(|jobs| format!("--jobs={}", jobs))
+            .unwrap_or_default();
+
         let cmd = format!(
-            "{}={} {:?} {} {} {} --ipget-bin={:?}",
+            "{}={} {:?} {} {} {} {} {} {} {}",
             PARAMETER_CACHE_ENV_VAR,
             cache_dir_path,
             paramfetch_path,
             if self.prompt_enabled { "" } else { "--all" },
             json_argument,
+            signature_argument,
             whitelist,
-            "true"
+            source_argument,
+            jobs_argument,
+            self.extra_args.join(" ")
         );
 
         p.execute(&cmd, ".*").expect("could not execute paramfetch");
@@ -113,6 +170,11 @@ pub struct ParamFetchSession {
 }
 
 impl ParamFetchSession {
+    /// Send provided string and trailing newline to the shell running paramfetch.
+    pub fn send_line(&mut self, line: &str) -> Result<usize, SyncFailure<rexpect::errors::Error>> {
+        self.pty_session.send_line(line).map_err(SyncFailure::new)
+    }
+
     /// Block until provided string is seen on stdout from paramfetch and
     /// return remaining output.
     pub fn exp_string(
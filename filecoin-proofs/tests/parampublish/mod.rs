@@ -1,5 +1,6 @@
 pub mod prompts_to_publish;
 pub mod read_metadata_files;
 pub mod write_json_manifest;
+pub mod write_mirror_dir;
 
 pub mod support;
@@ -19,6 +19,8 @@ pub struct ParamPublishSessionBuilder {
     manifest: PathBuf,
     ipfs_bin_path: PathBuf,
     prompt_enabled: bool,
+    target: Option<String>,
+    signing_key: Option<PathBuf>,
 }
 
 impl ParamPublishSessionBuilder {
@@ -37,6 +39,8 @@ impl ParamPublishSessionBuilder {
             manifest: pbuf,
             ipfs_bin_path: cargo_bin("fakeipfsadd"),
             prompt_enabled: true,
+            target: None,
+            signing_key: None,
         }
     }
 
@@ -110,6 +114,18 @@ impl ParamPublishSessionBuilder {
         self
     }
 
+    /// Publish to the given target (see `parampublish --target`) instead of the fake ipfs.
+    pub fn with_target<S: Into<String>>(mut self, target: S) -> ParamPublishSessionBuilder {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Sign the manifest with the secret key in the given file (see `parampublish --signing-key`).
+    pub fn with_signing_key(mut self, key_path: PathBuf) -> ParamPublishSessionBuilder {
+        self.signing_key = Some(key_path);
+        self
+    }
+
     /// When publishing, write JSON manifest to provided path.
     pub fn write_manifest_to(mut self, manifest_dest: PathBuf) -> ParamPublishSessionBuilder {
         self.manifest = manifest_dest;
@@ -132,14 +148,26 @@ impl ParamPublishSessionBuilder {
 
         let parampublish_path = cargo_bin("parampublish");
 
+        let target_argument = self
+            .target
+            .map(|target| format!("--target={:?}", target))
+            .unwrap_or_default();
+
+        let signing_key_argument = self
+            .signing_key
+            .map(|key_path| format!("--signing-key={:?}", key_path))
+            .unwrap_or_default();
+
         let cmd = format!(
-            "{}={} {:?} {} --ipfs-bin={:?} --json={:?}",
+            "{}={} {:?} {} --ipfs-bin={:?} --json={:?} {} {}",
             PARAMETER_CACHE_ENV_VAR,
             cache_dir_path,
             parampublish_path,
             if self.prompt_enabled { "" } else { "--all" },
             self.ipfs_bin_path,
-            self.manifest
+            self.manifest,
+            target_argument,
+            signing_key_argument
         );
 
         p.execute(&cmd, ".*")
@@ -0,0 +1,95 @@
+use std::collections::btree_map::BTreeMap;
+use std::fs::{self, File};
+
+use failure::Error as FailureError;
+
+use filecoin_proofs::param::ParameterData;
+use storage_proofs::parameter_cache::CacheEntryMetadata;
+
+use crate::paramfetch::support::session::ParamFetchSessionBuilder;
+use crate::parampublish::support::session::ParamPublishSessionBuilder;
+
+#[test]
+fn writes_mirror_dir_consumable_by_paramfetch() -> Result<(), FailureError> {
+    let filenames = vec!["aaa.vk", "aaa.params"];
+
+    let mirror_dir = tempfile::tempdir()?;
+    let manifest_path = mirror_dir.path().join("parameters.json");
+
+    let (mut session, files_in_cache) = ParamPublishSessionBuilder::new()
+        .with_session_timeout_ms(1000)
+        .with_files(&filenames)
+        .with_metadata("aaa.meta", &CacheEntryMetadata { sector_size: 1234 })
+        .write_manifest_to(manifest_path.clone())
+        .with_target(format!("dir:{}", mirror_dir.path().display()))
+        .with_prompt_disabled()
+        .build();
+
+    session.exp_string("publishing 2 files")?;
+    session.exp_string("done")?;
+
+    // each file is stored under its digest, next to the manifest
+    let manifest_map: BTreeMap<String, ParameterData> =
+        serde_json::from_reader(File::open(&manifest_path)?)?;
+
+    for path in files_in_cache.iter() {
+        let filename = path.file_name().and_then(|f| f.to_str()).unwrap_or("");
+        if !filenames.contains(&filename) {
+            continue;
+        }
+
+        let entry = manifest_map
+            .get(filename)
+            .unwrap_or_else(|| panic!("{} must be present in the manifest", filename));
+        assert_eq!(
+            fs::read(mirror_dir.path().join(&entry.digest))?,
+            fs::read(path)?,
+            "mirror does not hold {} under its digest",
+            filename
+        );
+    }
+
+    // the mirror can be fetched from as it is
+    let mut session = ParamFetchSessionBuilder::new(Some(manifest_path))
+        .with_session_timeout_ms(1000)
+        .with_source(format!("dir:{}", mirror_dir.path().display()))
+        .with_jobs(1)
+        .fetch_all()
+        .build();
+
+    session.exp_string("aaa.params: ok")?;
+    session.exp_string("aaa.vk: ok")?;
+    session.exp_string("done")?;
+
+    Ok(())
+}
+
+#[test]
+fn refuses_to_resign_unverified_mirror_manifest() -> Result<(), FailureError> {
+    let mirror_dir = tempfile::tempdir()?;
+    let manifest_path = mirror_dir.path().join("parameters.json");
+
+    // a manifest which nobody signed, e.g. written by hand into the mirror
+    fs::write(&manifest_path, "{}")?;
+
+    let key_path = mirror_dir.path().join("mirror.key");
+    fs::write(&key_path, hex::encode([7u8; 32]))?;
+
+    let (mut session, _) = ParamPublishSessionBuilder::new()
+        .with_session_timeout_ms(1000)
+        .with_files(&["aaa.vk"])
+        .with_metadata("aaa.meta", &CacheEntryMetadata { sector_size: 1234 })
+        .write_manifest_to(manifest_path.clone())
+        .with_target(format!("dir:{}", mirror_dir.path().display()))
+        .with_signing_key(key_path)
+        .with_prompt_disabled()
+        .build();
+
+    session.exp_string("is not signed, not merging it")?;
+
+    // the manifest is left as it was, and stays unsigned
+    assert_eq!(fs::read_to_string(&manifest_path)?, "{}");
+    assert!(!mirror_dir.path().join("parameters.json.sig").exists());
+
+    Ok(())
+}
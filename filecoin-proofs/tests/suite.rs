@@ -1,3 +1,4 @@
+mod paramcache;
 mod paramfetch;
 mod parampublish;
 mod support;
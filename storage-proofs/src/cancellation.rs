@@ -0,0 +1,63 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+
+/// A flag shared between a long-running operation and its caller, which the caller sets to ask
+/// the operation to stop. Operations check it at convenient points and fail with
+/// `Error::Cancelled` once it is set.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Asks every operation holding a clone of this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Returns `Error::Cancelled` if the token has been cancelled.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled.into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns true if `err` was caused by a cancelled `CancellationToken`.
+pub fn is_cancelled(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<Error>() {
+        Some(Error::Cancelled) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use anyhow::Context;
+
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(clone.check().is_ok());
+
+        token.cancel();
+        assert!(clone.is_cancelled());
+
+        let err = clone.check().context("while sealing").unwrap_err();
+        assert!(is_cancelled(&err));
+        assert!(!is_cancelled(&anyhow::anyhow!("cancelled")));
+    }
+}
@@ -25,6 +25,10 @@ impl<E: Engine> BenchCS<E> {
     pub fn num_inputs(&self) -> usize {
         self.inputs
     }
+
+    pub fn num_aux(&self) -> usize {
+        self.aux
+    }
 }
 
 impl<E: Engine> Default for BenchCS<E> {
@@ -9,9 +9,11 @@ pub mod pedersen;
 pub mod por;
 pub mod rational_post;
 pub mod stacked;
+#[cfg(feature = "prover")]
 pub mod stacked_old;
 pub mod uint64;
 pub mod variables;
+pub mod window_post;
 pub mod xor;
 
 // FIXME: Can we make a config like for test?
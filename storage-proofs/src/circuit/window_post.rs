@@ -0,0 +1,480 @@
+use std::marker::PhantomData;
+
+use bellperson::gadgets::num;
+use bellperson::{Circuit, ConstraintSystem, SynthesisError};
+use fil_sapling_crypto::jubjub::JubjubEngine;
+use paired::bls12_381::{Bls12, Fr};
+
+use crate::circuit::constraint;
+use crate::circuit::por::{PoRCircuit, PoRCompound};
+use crate::circuit::stacked::hash::hash3;
+use crate::circuit::variables::Root;
+use crate::compound_proof::{CircuitComponent, CompoundProof};
+use crate::crypto::pedersen::JJ_PARAMS;
+use crate::drgraph;
+use crate::error::Result;
+use crate::hasher::Hasher;
+use crate::merklepor;
+use crate::parameter_cache::{CacheableParameters, ParameterSetMetadata};
+use crate::proof::ProofScheme;
+use crate::stacked;
+use crate::util::NODE_SIZE;
+use crate::window_post::{self, WindowPoSt};
+
+/// The witness of a single sector in the `WindowPoSt` circuit.
+pub struct Sector<E: JubjubEngine> {
+    pub comm_r: Option<E::Fr>,
+    pub comm_c: Option<E::Fr>,
+    pub comm_q: Option<E::Fr>,
+    pub comm_r_last: Option<E::Fr>,
+    pub leafs: Vec<Option<E::Fr>>,
+    #[allow(clippy::type_complexity)]
+    pub paths: Vec<Vec<Option<(E::Fr, bool)>>>,
+}
+
+impl<E: JubjubEngine> Sector<E> {
+    fn blank(challenge_count: usize, height: usize) -> Self {
+        Sector {
+            comm_r: None,
+            comm_c: None,
+            comm_q: None,
+            comm_r_last: None,
+            leafs: vec![None; challenge_count],
+            paths: vec![vec![None; height]; challenge_count],
+        }
+    }
+}
+
+/// This is the `WindowPoSt` circuit, proving a single partition.
+pub struct WindowPoStCircuit<'a, E: JubjubEngine, H: Hasher> {
+    /// Paramters for the engine.
+    pub params: &'a E::Params,
+    pub sectors: Vec<Sector<E>>,
+    _h: PhantomData<H>,
+}
+
+pub struct WindowPoStCompound<H>
+where
+    H: Hasher,
+{
+    _h: PhantomData<H>,
+}
+
+impl<E: JubjubEngine, C: Circuit<E>, P: ParameterSetMetadata, H: Hasher>
+    CacheableParameters<E, C, P> for WindowPoStCompound<H>
+{
+    fn cache_prefix() -> String {
+        String::from("proof-of-spacetime-window")
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ComponentPrivateInputs {}
+
+impl<'a, E: JubjubEngine, H: Hasher> CircuitComponent for WindowPoStCircuit<'a, E, H> {
+    type ComponentPrivateInputs = ComponentPrivateInputs;
+}
+
+impl<'a, H> CompoundProof<'a, Bls12, WindowPoSt<'a, H>, WindowPoStCircuit<'a, Bls12, H>>
+    for WindowPoStCompound<H>
+where
+    H: 'a + Hasher,
+{
+    fn generate_public_inputs(
+        pub_inputs: &<WindowPoSt<'a, H> as ProofScheme<'a>>::PublicInputs,
+        pub_params: &<WindowPoSt<'a, H> as ProofScheme<'a>>::PublicParams,
+        partition_k: Option<usize>,
+    ) -> Result<Vec<Fr>> {
+        let mut inputs = Vec::new();
+
+        let por_pub_params = merklepor::PublicParams {
+            leaves: (pub_params.sector_size as usize / NODE_SIZE),
+            private: true,
+        };
+
+        let indices = window_post::partition_sector_indices(
+            pub_params,
+            pub_inputs.sectors.len(),
+            partition_k.or(pub_inputs.k).unwrap_or(0),
+        )?;
+
+        for i in indices {
+            let sector = &pub_inputs.sectors[i];
+
+            // 1. Inputs for verifying comm_r = H(comm_c || comm_q || comm_r_last)
+
+            inputs.push(sector.comm_r.into());
+
+            // 2. Inputs for verifying inclusion paths
+
+            for challenge in
+                window_post::generate_leaf_challenges(pub_params, &pub_inputs.randomness, sector.id)
+            {
+                let por_pub_inputs = merklepor::PublicInputs {
+                    commitment: None,
+                    challenge: challenge as usize,
+                };
+                let por_inputs = PoRCompound::<H>::generate_public_inputs(
+                    &por_pub_inputs,
+                    &por_pub_params,
+                    None,
+                )?;
+
+                inputs.extend(por_inputs);
+            }
+        }
+
+        Ok(inputs)
+    }
+
+    fn circuit(
+        _pub_in: &<WindowPoSt<'a, H> as ProofScheme<'a>>::PublicInputs,
+        _priv_in: <WindowPoStCircuit<'a, Bls12, H> as CircuitComponent>::ComponentPrivateInputs,
+        vanilla_proof: &<WindowPoSt<'a, H> as ProofScheme<'a>>::Proof,
+        _pub_params: &<WindowPoSt<'a, H> as ProofScheme<'a>>::PublicParams,
+    ) -> Result<WindowPoStCircuit<'a, Bls12, H>> {
+        // The public inputs are not split into partitions here, so comm_r is taken from the
+        // partition's vanilla proof. It is checked against the public inputs by the verifier.
+        let sectors = vanilla_proof
+            .sectors
+            .iter()
+            .map(|sector_proof| {
+                let comm_r_last = sector_proof.comm_r_last();
+                let comm_r: Fr =
+                    stacked::hash::hash3(sector_proof.comm_c, sector_proof.comm_q, comm_r_last)
+                        .into();
+
+                let leafs: Vec<_> = sector_proof
+                    .leafs()
+                    .iter()
+                    .map(|c| Some((**c).into()))
+                    .collect();
+
+                let paths: Vec<Vec<_>> = sector_proof
+                    .paths()
+                    .iter()
+                    .map(|v| v.iter().map(|p| Some(((*p).0.into(), p.1))).collect())
+                    .collect();
+
+                Sector {
+                    comm_r: Some(comm_r),
+                    comm_c: Some(sector_proof.comm_c.into()),
+                    comm_q: Some(sector_proof.comm_q.into()),
+                    comm_r_last: Some(comm_r_last.into()),
+                    leafs,
+                    paths,
+                }
+            })
+            .collect();
+
+        Ok(WindowPoStCircuit {
+            params: &*JJ_PARAMS,
+            sectors,
+            _h: PhantomData,
+        })
+    }
+
+    fn blank_circuit(
+        pub_params: &<WindowPoSt<'a, H> as ProofScheme<'a>>::PublicParams,
+    ) -> WindowPoStCircuit<'a, Bls12, H> {
+        let height = drgraph::graph_height(pub_params.sector_size as usize / NODE_SIZE);
+
+        let sectors = (0..pub_params.sector_count)
+            .map(|_| Sector::blank(pub_params.challenge_count, height))
+            .collect();
+
+        WindowPoStCircuit {
+            params: &*JJ_PARAMS,
+            sectors,
+            _h: PhantomData,
+        }
+    }
+}
+
+impl<'a, E: JubjubEngine, H: Hasher> Circuit<E> for WindowPoStCircuit<'a, E, H> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let params = self.params;
+
+        for (i, sector) in self.sectors.into_iter().enumerate() {
+            let cs = &mut cs.namespace(|| format!("sector_{}", i));
+
+            assert_eq!(sector.paths.len(), sector.leafs.len());
+
+            // 1. Verify comm_r
+
+            let comm_r_last_num = num::AllocatedNum::alloc(cs.namespace(|| "comm_r_last"), || {
+                sector
+                    .comm_r_last
+                    .ok_or_else(|| SynthesisError::AssignmentMissing)
+            })?;
+
+            let comm_c_num = num::AllocatedNum::alloc(cs.namespace(|| "comm_c"), || {
+                sector
+                    .comm_c
+                    .ok_or_else(|| SynthesisError::AssignmentMissing)
+            })?;
+
+            let comm_q_num = num::AllocatedNum::alloc(cs.namespace(|| "comm_q"), || {
+                sector
+                    .comm_q
+                    .ok_or_else(|| SynthesisError::AssignmentMissing)
+            })?;
+
+            let comm_r_num = num::AllocatedNum::alloc(cs.namespace(|| "comm_r"), || {
+                sector
+                    .comm_r
+                    .ok_or_else(|| SynthesisError::AssignmentMissing)
+            })?;
+
+            comm_r_num.inputize(cs.namespace(|| "comm_r_input"))?;
+
+            // Verify H(Comm_C || comm_q || comm_r_last) == comm_r
+            {
+                let comm_c_bits = comm_c_num.to_bits_le(cs.namespace(|| "comm_c_bits"))?;
+                let comm_q_bits = comm_q_num.to_bits_le(cs.namespace(|| "comm_q_bits"))?;
+                let comm_r_last_bits =
+                    comm_r_last_num.to_bits_le(cs.namespace(|| "comm_r_last_bits"))?;
+
+                let hash_num = hash3(
+                    cs.namespace(|| "H_comm_c_comm_q_comm_r_last"),
+                    params,
+                    &comm_c_bits,
+                    &comm_q_bits,
+                    &comm_r_last_bits,
+                )?;
+
+                // Check actual equality
+                constraint::equal(
+                    cs,
+                    || "enforce_comm_c_comm_q_comm_r_last_hash_comm_r",
+                    &comm_r_num,
+                    &hash_num,
+                );
+            }
+
+            // 2. Verify Inclusion Paths
+            for (j, (leaf, path)) in sector.leafs.iter().zip(sector.paths.iter()).enumerate() {
+                PoRCircuit::<E, H>::synthesize(
+                    cs.namespace(|| format!("challenge_inclusion{}", j)),
+                    &params,
+                    Root::Val(*leaf),
+                    path.clone(),
+                    Root::from_allocated::<CS>(comm_r_last_num.clone()),
+                    true,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ff::Field;
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use crate::circuit::test::*;
+    use crate::compound_proof;
+    use crate::drgraph::{new_seed, BucketGraph, Graph, BASE_DEGREE};
+    use crate::fr32::fr_into_bytes;
+    use crate::hasher::{pedersen::*, Domain};
+    use crate::merkle::MerkleTree;
+    use crate::proof::NoRequirements;
+    use crate::stacked::hash::hash3;
+    use crate::window_post::{PrivateSector, PublicSector};
+
+    type Tree = MerkleTree<PedersenDomain, PedersenFunction>;
+
+    fn make_trees<R: Rng>(rng: &mut R, leaves: usize, count: usize) -> Vec<Tree> {
+        (0..count)
+            .map(|_| {
+                let data: Vec<u8> = (0..leaves)
+                    .flat_map(|_| fr_into_bytes::<Bls12>(&Fr::random(rng)))
+                    .collect();
+
+                let graph =
+                    BucketGraph::<PedersenHasher>::new(leaves, BASE_DEGREE, 0, new_seed()).unwrap();
+                graph.merkle_tree(data.as_slice()).unwrap()
+            })
+            .collect()
+    }
+
+    fn make_inputs<'a, R: Rng>(
+        rng: &mut R,
+        trees: &'a [Tree],
+    ) -> (
+        Vec<PublicSector<PedersenDomain>>,
+        Vec<PrivateSector<'a, PedersenHasher>>,
+    ) {
+        trees
+            .iter()
+            .enumerate()
+            .map(|(i, tree)| {
+                let comm_c = PedersenDomain::random(rng);
+                let comm_q = PedersenDomain::random(rng);
+                let comm_r = Fr::from(hash3(comm_c, comm_q, tree.root())).into();
+
+                (
+                    PublicSector {
+                        id: (i as u64).into(),
+                        comm_r,
+                    },
+                    PrivateSector {
+                        tree,
+                        comm_c,
+                        comm_q,
+                    },
+                )
+            })
+            .unzip()
+    }
+
+    #[test]
+    fn test_window_post_circuit() {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        let leaves = 32;
+        let sector_size = (leaves * 32) as u64;
+
+        let pub_params = window_post::PublicParams {
+            sector_size,
+            challenge_count: 5,
+            sector_count: 2,
+        };
+
+        let trees = make_trees(rng, leaves, 3);
+        let (pub_sectors, priv_sectors) = make_inputs(rng, &trees);
+
+        let pub_inputs = window_post::PublicInputs {
+            randomness: rng.gen(),
+            sectors: pub_sectors,
+            k: Some(1),
+        };
+        let priv_inputs = window_post::PrivateInputs::<PedersenHasher> {
+            sectors: &priv_sectors,
+        };
+
+        let proof = WindowPoSt::<PedersenHasher>::prove(&pub_params, &pub_inputs, &priv_inputs)
+            .expect("proving failed");
+
+        let is_valid = WindowPoSt::<PedersenHasher>::verify(&pub_params, &pub_inputs, &proof)
+            .expect("verification failed");
+        assert!(is_valid);
+
+        // actual circuit test
+
+        let mut cs = TestConstraintSystem::<Bls12>::new();
+
+        let instance = WindowPoStCompound::<PedersenHasher>::circuit(
+            &pub_inputs,
+            ComponentPrivateInputs {},
+            &proof,
+            &pub_params,
+        )
+        .expect("failed to build circuit");
+
+        instance
+            .synthesize(&mut cs)
+            .expect("failed to synthesize circuit");
+
+        assert!(cs.is_satisfied(), "constraints not satisfied");
+
+        // comm_r and one packed auth path per challenge, for each of the two sectors
+        assert_eq!(cs.num_inputs(), 1 + 2 * (1 + 5), "wrong number of inputs");
+        assert_eq!(cs.get_input(0, "ONE"), Fr::one());
+
+        let generated_inputs = WindowPoStCompound::<PedersenHasher>::generate_public_inputs(
+            &pub_inputs,
+            &pub_params,
+            Some(1),
+        )
+        .unwrap();
+        let expected_inputs = cs.get_inputs();
+
+        for ((input, label), generated_input) in
+            expected_inputs.iter().skip(1).zip(generated_inputs.iter())
+        {
+            assert_eq!(input, generated_input, "{}", label);
+        }
+
+        assert_eq!(
+            generated_inputs.len(),
+            expected_inputs.len() - 1,
+            "inputs are not the same length"
+        );
+    }
+
+    #[ignore] // Slow test – run only when compiled for release.
+    #[test]
+    fn window_post_test_compound() {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        let leaves = 32;
+        let sector_size = (leaves * 32) as u64;
+
+        let setup_params = compound_proof::SetupParams {
+            vanilla_params: window_post::SetupParams {
+                sector_size,
+                challenge_count: 5,
+                sector_count: 2,
+            },
+            partitions: Some(2),
+        };
+
+        let pub_params =
+            WindowPoStCompound::<PedersenHasher>::setup(&setup_params).expect("setup failed");
+
+        let trees = make_trees(rng, leaves, 3);
+        let (pub_sectors, priv_sectors) = make_inputs(rng, &trees);
+
+        let pub_inputs = window_post::PublicInputs {
+            randomness: rng.gen(),
+            sectors: pub_sectors,
+            k: None,
+        };
+        let priv_inputs = window_post::PrivateInputs::<PedersenHasher> {
+            sectors: &priv_sectors,
+        };
+
+        {
+            let (circuit, inputs) =
+                WindowPoStCompound::circuit_for_test(&pub_params, &pub_inputs, &priv_inputs)
+                    .unwrap();
+
+            let mut cs = TestConstraintSystem::new();
+
+            circuit.synthesize(&mut cs).expect("failed to synthesize");
+
+            if !cs.is_satisfied() {
+                panic!(
+                    "failed to satisfy: {:?}",
+                    cs.which_is_unsatisfied().unwrap()
+                );
+            }
+            assert!(
+                cs.verify(&inputs),
+                "verification failed with TestContraintSystem and generated inputs"
+            );
+        }
+
+        let blank_groth_params = WindowPoStCompound::<PedersenHasher>::groth_params(
+            Some(rng),
+            &pub_params.vanilla_params,
+        )
+        .expect("failed to generate groth params");
+
+        let proof =
+            WindowPoStCompound::prove(&pub_params, &pub_inputs, &priv_inputs, &blank_groth_params)
+                .expect("failed while proving");
+
+        let verified =
+            WindowPoStCompound::verify(&pub_params, &pub_inputs, &proof, &NoRequirements)
+                .expect("failed while verifying");
+
+        assert!(verified);
+    }
+}
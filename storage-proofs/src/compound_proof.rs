@@ -1,17 +1,25 @@
-use rayon::prelude::*;
-
 use anyhow::{ensure, Context};
 use bellperson::{groth16, Circuit};
 use fil_sapling_crypto::jubjub::JubjubEngine;
-use log::info;
-use rand::{rngs::OsRng, RngCore};
+use rand::RngCore;
 
 use crate::circuit::multi_proof::MultiProof;
 use crate::error::Result;
 use crate::parameter_cache::{CacheableParameters, ParameterSetMetadata};
 use crate::partitions;
 use crate::proof::ProofScheme;
-use crate::settings;
+
+/// What the proving methods of `CompoundProof` use, beyond what verification needs.
+#[cfg(feature = "prover")]
+mod prover {
+    pub use log::info;
+    pub use rand::rngs::OsRng;
+    pub use rayon::prelude::*;
+
+    pub use crate::mapped_parameters::{GrothParameters, MappedParameters};
+    pub use crate::progress::{report_stage, Progress, RunOptions, Stage};
+    pub use crate::thread_pools::{self, Pool};
+}
 
 #[derive(Clone)]
 pub struct SetupParams<'a, S: ProofScheme<'a>> {
@@ -38,6 +46,7 @@ pub trait CircuitComponent {
 /// See documentation at proof::ProofScheme for details.
 /// Implementations should generally only need to supply circuit and generate_public_inputs.
 /// The remaining trait methods are used internally and implement the necessary plumbing.
+/// The proving methods are only built with the `prover` feature.
 pub trait CompoundProof<'a, E: JubjubEngine, S: ProofScheme<'a>, C: Circuit<E> + CircuitComponent>
 where
     S::Proof: Sync + Send,
@@ -65,24 +74,59 @@ where
     }
 
     /// prove is equivalent to ProofScheme::prove.
-    fn prove<'b>(
+    /// `groth_params` may be fully loaded `groth16::Parameters` or `MappedParameters`.
+    #[cfg(feature = "prover")]
+    fn prove<'b, P>(
+        pub_params: &PublicParams<'a, S>,
+        pub_in: &S::PublicInputs,
+        priv_in: &S::PrivateInputs,
+        groth_params: &'b P,
+    ) -> Result<MultiProof<'b, E>>
+    where
+        E::Params: Sync,
+        P: prover::GrothParameters<E>,
+        &'b P: groth16::ParameterSource<E>,
+    {
+        Self::prove_with_options(
+            pub_params,
+            pub_in,
+            priv_in,
+            groth_params,
+            prover::RunOptions::default(),
+        )
+    }
+
+    /// Same as `prove`, reporting the vanilla and circuit proof stages and each proven
+    /// partition as set in `options`. Fails with `Error::Cancelled` once cancelled through
+    /// `options`, which is checked before each partition is proven.
+    #[cfg(feature = "prover")]
+    fn prove_with_options<'b, P>(
         pub_params: &PublicParams<'a, S>,
         pub_in: &S::PublicInputs,
         priv_in: &S::PrivateInputs,
-        groth_params: &'b groth16::Parameters<E>,
+        groth_params: &'b P,
+        options: prover::RunOptions,
     ) -> Result<MultiProof<'b, E>>
     where
         E::Params: Sync,
+        P: prover::GrothParameters<E>,
+        &'b P: groth16::ParameterSource<E>,
     {
+        use prover::*;
+
+        let progress = options.progress();
+
         let partitions = Self::partition_count(pub_params);
         let partition_count = Self::partition_count(pub_params);
 
         // This will always run at least once, since there cannot be zero partitions.
         ensure!(partition_count > 0, "There must be partitions");
 
+        options.check_cancelled()?;
         info!("vanilla_proof:start");
-        let vanilla_proofs =
-            S::prove_all_partitions(&pub_params.vanilla_params, &pub_in, priv_in, partitions)?;
+        let vanilla_proofs = report_stage(progress, Stage::VanillaProofs, 0, || {
+            S::prove_all_partitions(&pub_params.vanilla_params, &pub_in, priv_in, partitions)
+        })?;
 
         info!("vanilla_proof:finish");
 
@@ -90,29 +134,43 @@ where
             S::verify_all_partitions(&pub_params.vanilla_params, &pub_in, &vanilla_proofs)?;
         ensure!(sanity_check, "sanity check failed");
 
-        // Use a custom pool for this, so we can control the number of threads being used.
-        let pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(settings::SETTINGS.lock().unwrap().num_proving_threads)
-            .build()
-            .context("failed to build thread pool")?;
-
         info!("snark_proof:start");
-        let groth_proofs: Result<Vec<_>> = pool.install(|| {
+        progress.on_progress(Progress::StageStarted {
+            stage: Stage::SnarkProofs,
+            steps: partition_count,
+        });
+        // Held until every partition is proven, so that they share one decoded copy of mapped
+        // parameters.
+        let _shared = groth_params.share_across_partitions()?;
+        // Use the proving pool, so we can control the number of threads being used.
+        let groth_proofs: Result<Vec<_>> = thread_pools::install(Pool::Proving, || {
             vanilla_proofs
                 .par_iter()
-                .map(|vanilla_proof| {
-                    Self::circuit_proof(
+                .enumerate()
+                .map(|(partition, vanilla_proof)| {
+                    options.check_cancelled()?;
+                    let proof = Self::circuit_proof(
                         pub_in,
                         &vanilla_proof,
                         &pub_params.vanilla_params,
                         groth_params,
-                    )
+                    )?;
+                    progress.on_progress(Progress::PartitionProven {
+                        partition,
+                        partitions: partition_count,
+                    });
+
+                    Ok(proof)
                 })
                 .collect()
         });
+        let groth_proofs = groth_proofs?;
+        progress.on_progress(Progress::StageFinished {
+            stage: Stage::SnarkProofs,
+        });
         info!("snark_proof:finish");
 
-        Ok(MultiProof::new(groth_proofs?, &groth_params.vk))
+        Ok(MultiProof::new(groth_proofs, groth_params.vk()))
     }
 
     // verify is equivalent to ProofScheme::verify.
@@ -151,13 +209,18 @@ where
     /// groth proof from it. It returns a groth proof.
     /// circuit_proof is used internally and should neither be called nor implemented outside of
     /// default trait methods.
-    fn circuit_proof(
+    #[cfg(feature = "prover")]
+    fn circuit_proof<'b, P>(
         pub_in: &S::PublicInputs,
         vanilla_proof: &S::Proof,
         pub_params: &S::PublicParams,
-        groth_params: &groth16::Parameters<E>,
-    ) -> Result<groth16::Proof<E>> {
-        let mut rng = OsRng;
+        groth_params: &'b P,
+    ) -> Result<groth16::Proof<E>>
+    where
+        P: prover::GrothParameters<E>,
+        &'b P: groth16::ParameterSource<E>,
+    {
+        let mut rng = prover::OsRng;
 
         // We need to make the circuit repeatedly because we can't clone it.
         // Fortunately, doing so is cheap.
@@ -208,6 +271,14 @@ where
         Self::get_groth_params(rng, Self::blank_circuit(public_params), public_params)
     }
 
+    #[cfg(feature = "prover")]
+    fn mapped_groth_params<R: RngCore>(
+        rng: Option<&mut R>,
+        public_params: &S::PublicParams,
+    ) -> Result<prover::MappedParameters<E>> {
+        Self::get_mapped_groth_params(rng, Self::blank_circuit(public_params), public_params)
+    }
+
     fn verifying_key<R: RngCore>(
         rng: Option<&mut R>,
         public_params: &S::PublicParams,
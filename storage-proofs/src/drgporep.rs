@@ -3,7 +3,6 @@ use std::marker::PhantomData;
 use anyhow::{ensure, Context};
 use byteorder::{LittleEndian, WriteBytesExt};
 use merkletree::store::StoreConfig;
-use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
@@ -13,6 +12,7 @@ use crate::error::Result;
 use crate::fr32::bytes_into_fr_repr_safe;
 use crate::hasher::{Domain, Hasher};
 use crate::merkle::{MerkleProof, MerkleTree};
+use crate::par::*;
 use crate::parameter_cache::ParameterSetMetadata;
 use crate::porep::{self, PoRep};
 use crate::proof::{NoRequirements, ProofScheme};
@@ -5,7 +5,6 @@ use std::marker::PhantomData;
 use anyhow::{bail, ensure, Context};
 use byteorder::{ByteOrder, LittleEndian};
 use paired::bls12_381::{Bls12, Fr};
-use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
@@ -16,6 +15,7 @@ use crate::fr32::fr_into_bytes;
 use crate::hasher::{Domain, Hasher};
 use crate::measurements::{measure_op, Operation};
 use crate::merkle::{MerkleProof, MerkleTree};
+use crate::par::*;
 use crate::parameter_cache::ParameterSetMetadata;
 use crate::proof::{NoRequirements, ProofScheme};
 use crate::sector::*;
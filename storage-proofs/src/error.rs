@@ -37,6 +37,10 @@ pub enum Error {
     Unclassified(String),
     #[error("Missing Private Input {0} for sector {1}")]
     MissingPrivateInput(&'static str, u64),
+    #[error("operation was cancelled")]
+    Cancelled,
+    #[error("no cached parameters found for {0}")]
+    MissingParameters(String),
 }
 
 impl From<Box<dyn Any + Send>> for Error {
@@ -7,6 +7,7 @@ pub mod test_helper;
 
 pub mod example_helper;
 
+pub mod cancellation;
 pub mod circuit;
 pub mod compound_proof;
 pub mod crypto;
@@ -17,20 +18,28 @@ mod encode;
 pub mod error;
 pub mod fr32;
 pub mod hasher;
+#[cfg(feature = "prover")]
+pub mod mapped_parameters;
 pub mod measurements;
 pub mod merkle;
 pub mod merklepor;
+mod par;
 pub mod parameter_cache;
 pub mod partitions;
 pub mod pieces;
 pub mod porep;
+pub mod progress;
 pub mod proof;
 pub mod rational_post;
 pub mod sector;
 pub mod settings;
 pub mod stacked;
+#[cfg(feature = "prover")]
 pub mod stacked_old;
+#[cfg(feature = "prover")]
+pub mod thread_pools;
 pub mod util;
+pub mod window_post;
 
 pub(crate) const TEST_SEED: [u8; 16] = [
     0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc, 0xe5,
@@ -0,0 +1,412 @@
+use std::fs::File;
+use std::io::{self, Cursor};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+
+use anyhow::{ensure, Context};
+use bellperson::groth16::{self, ParameterSource, VerifyingKey};
+use bellperson::SynthesisError;
+use byteorder::{BigEndian, ReadBytesExt};
+use log::info;
+use memmap::{Mmap, MmapOptions};
+use paired::{CurveAffine, EncodedPoint, Engine};
+use rayon::prelude::*;
+
+use crate::error::Result;
+
+/// Anything that can be handed to `CompoundProof::prove` as Groth parameters.
+pub trait GrothParameters<E: Engine>: Send + Sync {
+    fn vk(&self) -> &VerifyingKey<E>;
+
+    /// Called before the partitions of a proof are proven. The returned value is held until all
+    /// of them are done, so that they can share whatever it keeps alive.
+    fn share_across_partitions(&self) -> Result<Option<Arc<dyn Send + Sync>>> {
+        Ok(None)
+    }
+}
+
+impl<E: Engine> GrothParameters<E> for groth16::Parameters<E> {
+    fn vk(&self) -> &VerifyingKey<E> {
+        &self.vk
+    }
+}
+
+/// Groth parameters backed by a memory map of a file written by `groth16::Parameters::write`.
+///
+/// Only the verifying key is held in memory. The `h`, `l`, `a`, `b_g1` and `b_g2` queries are
+/// decoded from the map when `CompoundProof::prove` starts proving partitions, shared by all of
+/// them and by concurrent proofs using the same parameters, and dropped once the last of those
+/// proofs is done. Peak memory is therefore about one decoded copy of the parameters, the same
+/// as `groth16::Parameters`, but only while proving. A proof created directly through
+/// `ParameterSource` outside of that decodes each query it asks for on its own.
+pub struct MappedParameters<E: Engine> {
+    path: PathBuf,
+    map: Mmap,
+    vk: VerifyingKey<E>,
+    h: Range<usize>,
+    l: Range<usize>,
+    a: Range<usize>,
+    b_g1: Range<usize>,
+    b_g2: Range<usize>,
+    checked: bool,
+    shared: Mutex<Weak<Queries<E>>>,
+}
+
+/// The decoded queries of a `MappedParameters`.
+struct Queries<E: Engine> {
+    h: Arc<Vec<E::G1Affine>>,
+    l: Arc<Vec<E::G1Affine>>,
+    a: Arc<Vec<E::G1Affine>>,
+    b_g1: Arc<Vec<E::G1Affine>>,
+    b_g2: Arc<Vec<E::G2Affine>>,
+}
+
+impl<E: Engine> std::fmt::Debug for MappedParameters<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("MappedParameters")
+            .field("path", &self.path)
+            .field("mapped_bytes", &self.map.len())
+            .field("checked", &self.checked)
+            .finish()
+    }
+}
+
+impl<E: Engine> MappedParameters<E> {
+    /// Maps the parameter file at `path`, reading only the verifying key and the query offsets.
+    /// When `checked` is set, every point is validated as it is decoded.
+    pub fn build<P: AsRef<Path>>(path: P, checked: bool) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path).with_context(|| format!("could not open {:?}", path))?;
+        let map = unsafe { MmapOptions::new().map(&file) }
+            .with_context(|| format!("could not mmap {:?}", path))?;
+
+        let mut cursor = Cursor::new(&map[..]);
+        let vk = VerifyingKey::<E>::read(&mut cursor)?;
+
+        let g1_size = <E::G1Affine as CurveAffine>::Uncompressed::size();
+        let g2_size = <E::G2Affine as CurveAffine>::Uncompressed::size();
+
+        let h = query_range(&mut cursor, g1_size)?;
+        let l = query_range(&mut cursor, g1_size)?;
+        let a = query_range(&mut cursor, g1_size)?;
+        let b_g1 = query_range(&mut cursor, g1_size)?;
+        let b_g2 = query_range(&mut cursor, g2_size)?;
+
+        info!("mapped groth parameters from {:?}", path);
+
+        Ok(MappedParameters {
+            path,
+            map,
+            vk,
+            h,
+            l,
+            a,
+            b_g1,
+            b_g2,
+            checked,
+            shared: Mutex::new(Weak::new()),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Size of the underlying parameter file.
+    pub fn mapped_len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// The queries decoded by `share_across_partitions`, while any proof still holds them.
+    fn shared(&self) -> Option<Arc<Queries<E>>> {
+        self.shared.lock().unwrap().upgrade()
+    }
+
+    fn read_points<G: CurveAffine>(
+        &self,
+        range: &Range<usize>,
+    ) -> std::result::Result<Arc<Vec<G>>, SynthesisError> {
+        let checked = self.checked;
+
+        self.map[range.clone()]
+            .par_chunks(G::Uncompressed::size())
+            .map(|chunk| {
+                let mut repr = G::Uncompressed::empty();
+                repr.as_mut().copy_from_slice(chunk);
+
+                let point = if checked {
+                    repr.into_affine()
+                } else {
+                    repr.into_affine_unchecked()
+                }
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+                if point.is_zero() {
+                    Err(SynthesisError::UnexpectedIdentity)
+                } else {
+                    Ok(point)
+                }
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map(Arc::new)
+    }
+}
+
+impl<E: Engine> GrothParameters<E> for MappedParameters<E> {
+    fn vk(&self) -> &VerifyingKey<E> {
+        &self.vk
+    }
+
+    fn share_across_partitions(&self) -> Result<Option<Arc<dyn Send + Sync>>> {
+        if let Some(queries) = self.shared() {
+            return Ok(Some(queries));
+        }
+
+        // Decode without holding the lock: decoding runs on the thread pool, which may start
+        // another proof that asks for the same parameters on this very thread.
+        info!("decoding groth parameters from {:?}", self.path);
+        let queries = Arc::new(Queries {
+            h: self.read_points(&self.h)?,
+            l: self.read_points(&self.l)?,
+            a: self.read_points(&self.a)?,
+            b_g1: self.read_points(&self.b_g1)?,
+            b_g2: self.read_points(&self.b_g2)?,
+        });
+
+        let mut shared = self.shared.lock().unwrap();
+        // keep the copy of a proof that finished decoding first, so only one stays alive
+        let queries = match shared.upgrade() {
+            Some(existing) => existing,
+            None => {
+                *shared = Arc::downgrade(&queries);
+                queries
+            }
+        };
+
+        Ok(Some(queries))
+    }
+}
+
+/// Reads the length prefix of a query and returns the byte range of its points, leaving the
+/// cursor just past the query.
+fn query_range(cursor: &mut Cursor<&[u8]>, point_size: usize) -> Result<Range<usize>> {
+    let len = cursor.read_u32::<BigEndian>()? as usize;
+    let start = cursor.position() as usize;
+    let end = start + len * point_size;
+
+    ensure!(
+        end <= cursor.get_ref().len(),
+        "parameter file truncated: query ends at {} but file is {} bytes",
+        end,
+        cursor.get_ref().len()
+    );
+    cursor.set_position(end as u64);
+
+    Ok(start..end)
+}
+
+impl<'a, E: Engine> ParameterSource<E> for &'a MappedParameters<E> {
+    type G1Builder = (Arc<Vec<E::G1Affine>>, usize);
+    type G2Builder = (Arc<Vec<E::G2Affine>>, usize);
+
+    fn get_vk(&mut self, _: usize) -> std::result::Result<VerifyingKey<E>, SynthesisError> {
+        Ok(self.vk.clone())
+    }
+
+    fn get_h(&mut self, _: usize) -> std::result::Result<Self::G1Builder, SynthesisError> {
+        let h = match self.shared() {
+            Some(queries) => queries.h.clone(),
+            None => self.read_points(&self.h)?,
+        };
+        Ok((h, 0))
+    }
+
+    fn get_l(&mut self, _: usize) -> std::result::Result<Self::G1Builder, SynthesisError> {
+        let l = match self.shared() {
+            Some(queries) => queries.l.clone(),
+            None => self.read_points(&self.l)?,
+        };
+        Ok((l, 0))
+    }
+
+    fn get_a(
+        &mut self,
+        num_inputs: usize,
+        _: usize,
+    ) -> std::result::Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
+        let a = match self.shared() {
+            Some(queries) => queries.a.clone(),
+            None => self.read_points(&self.a)?,
+        };
+        Ok(((a.clone(), 0), (a, num_inputs)))
+    }
+
+    fn get_b_g1(
+        &mut self,
+        num_inputs: usize,
+        _: usize,
+    ) -> std::result::Result<(Self::G1Builder, Self::G1Builder), SynthesisError> {
+        let b_g1 = match self.shared() {
+            Some(queries) => queries.b_g1.clone(),
+            None => self.read_points(&self.b_g1)?,
+        };
+        Ok(((b_g1.clone(), 0), (b_g1, num_inputs)))
+    }
+
+    fn get_b_g2(
+        &mut self,
+        num_inputs: usize,
+        _: usize,
+    ) -> std::result::Result<(Self::G2Builder, Self::G2Builder), SynthesisError> {
+        let b_g2 = match self.shared() {
+            Some(queries) => queries.b_g2.clone(),
+            None => self.read_points(&self.b_g2)?,
+        };
+        Ok(((b_g2.clone(), 0), (b_g2, num_inputs)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bellperson::groth16::{create_random_proof, prepare_verifying_key, verify_proof};
+    use bellperson::{Circuit, ConstraintSystem};
+    use ff::Field;
+    use paired::bls12_381::{Bls12, Fr};
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use tempfile::NamedTempFile;
+
+    use crate::TEST_SEED;
+
+    struct Square(Option<Fr>);
+
+    impl Circuit<Bls12> for Square {
+        fn synthesize<CS: ConstraintSystem<Bls12>>(
+            self,
+            cs: &mut CS,
+        ) -> std::result::Result<(), SynthesisError> {
+            let x_val = self.0;
+            let x = cs.alloc(|| "x", || x_val.ok_or(SynthesisError::AssignmentMissing))?;
+            let y = cs.alloc_input(
+                || "y",
+                || {
+                    let mut y = x_val.ok_or(SynthesisError::AssignmentMissing)?;
+                    y.square();
+                    Ok(y)
+                },
+            )?;
+            cs.enforce(|| "x * x = y", |lc| lc + x, |lc| lc + x, |lc| lc + y);
+
+            Ok(())
+        }
+    }
+
+    fn write_params(rng: &mut XorShiftRng) -> (groth16::Parameters<Bls12>, NamedTempFile) {
+        let params = groth16::generate_random_parameters::<Bls12, _, _>(Square(None), rng).unwrap();
+        let mut file = NamedTempFile::new().unwrap();
+        params.write(file.as_file_mut()).unwrap();
+
+        (params, file)
+    }
+
+    #[test]
+    fn mapped_parameters_match_read() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+        let (_, file) = write_params(rng);
+
+        let params =
+            groth16::Parameters::<Bls12>::read(File::open(file.path()).unwrap(), true).unwrap();
+        let mapped = MappedParameters::<Bls12>::build(file.path(), true).unwrap();
+        let mut source = &mapped;
+
+        assert!(params.vk == source.get_vk(0).unwrap());
+        assert_eq!(*source.get_h(0).unwrap().0, *params.h);
+        assert_eq!(*source.get_l(0).unwrap().0, *params.l);
+        assert_eq!(*(source.get_a(0, 0).unwrap().0).0, *params.a);
+        assert_eq!(*(source.get_b_g1(0, 0).unwrap().0).0, *params.b_g1);
+        assert_eq!(*(source.get_b_g2(0, 0).unwrap().0).0, *params.b_g2);
+    }
+
+    #[test]
+    fn mapped_parameters_shared() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+        let (_, file) = write_params(rng);
+        let mapped = MappedParameters::<Bls12>::build(file.path(), true).unwrap();
+        let mut source = &mapped;
+
+        // without a shared copy, every request decodes its own
+        let h = source.get_h(0).unwrap().0;
+        assert!(!Arc::ptr_eq(&h, &source.get_h(0).unwrap().0));
+
+        let shared = mapped.share_across_partitions().unwrap();
+        assert!(mapped.share_across_partitions().unwrap().is_some());
+        let h = source.get_h(0).unwrap().0;
+        assert!(Arc::ptr_eq(&h, &source.get_h(0).unwrap().0));
+
+        // the copy is dropped with the last holder
+        drop(shared);
+        drop(h);
+        assert!(mapped.shared().is_none());
+    }
+
+    #[test]
+    fn mapped_parameters_truncated() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+        let (_, file) = write_params(rng);
+        let len = file.as_file().metadata().unwrap().len();
+
+        for truncated_len in &[len - 1, len / 2, 10, 0] {
+            file.as_file().set_len(*truncated_len).unwrap();
+            assert!(
+                MappedParameters::<Bls12>::build(file.path(), false).is_err(),
+                "truncated to {} bytes",
+                truncated_len
+            );
+        }
+    }
+
+    #[test]
+    fn mapped_parameters_corrupt() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+        let (_, file) = write_params(rng);
+        let mut bytes = std::fs::read(file.path()).unwrap();
+
+        // a corrupt verifying key is rejected when mapping
+        let mut corrupt_vk = bytes.clone();
+        corrupt_vk[10] ^= 0xff;
+        std::fs::write(file.path(), &corrupt_vk).unwrap();
+        assert!(MappedParameters::<Bls12>::build(file.path(), true).is_err());
+
+        // a corrupt query point is only decoded, and rejected, when proving
+        let last = bytes.len() - 10;
+        bytes[last] ^= 0xff;
+        std::fs::write(file.path(), &bytes).unwrap();
+        let mapped = MappedParameters::<Bls12>::build(file.path(), true).unwrap();
+
+        let mut x = Fr::one();
+        x.double();
+        assert!(create_random_proof(Square(Some(x)), &mapped, rng).is_err());
+    }
+
+    #[test]
+    fn mapped_parameters_prove() {
+        let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+
+        let (params, file) = write_params(rng);
+
+        let mapped = MappedParameters::<Bls12>::build(file.path(), true).unwrap();
+        assert!(params.vk == *mapped.vk());
+
+        let mut x = Fr::one();
+        x.double();
+        let mut y = x;
+        y.square();
+
+        let proof = create_random_proof(Square(Some(x)), &mapped, rng).unwrap();
+        let pvk = prepare_verifying_key(mapped.vk());
+        assert!(verify_proof(&pvk, &proof, &[y]).unwrap());
+    }
+}
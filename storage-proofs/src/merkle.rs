@@ -8,14 +8,18 @@ use merkletree::merkle;
 use merkletree::proof;
 use merkletree::store::StoreConfig;
 use paired::bls12_381::Fr;
-use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::error::*;
 use crate::hasher::{Domain, Hasher};
+#[cfg(feature = "prover")]
+use crate::par::*;
+#[cfg(feature = "prover")]
+use crate::thread_pools::{self, Pool};
 use crate::util::{data_at_node, NODE_SIZE};
 
 // Reexport here, so we don't depend on merkletree directly in other places.
+#[cfg(feature = "prover")]
 use merkletree::merkle::FromIndexedParallelIterator;
 pub use merkletree::store::Store;
 
@@ -241,9 +245,68 @@ pub fn create_merkle_tree<H: Hasher>(
         H::Domain::try_from_bytes(d).expect("failed to convert node data to domain element")
     };
 
-    match config {
-        Some(x) => MerkleTree::from_par_iter_with_config((0..size).into_par_iter().map(f), x),
-        None => MerkleTree::from_par_iter((0..size).into_par_iter().map(f)),
+    build_tree::<H, _>(config, size, f)
+}
+
+/// Construct a new merkle tree over `size` nodes, of which `data` holds a prefix. The nodes past
+/// the end of `data` are zero.
+pub fn create_merkle_tree_zero_padded<H: Hasher>(
+    config: Option<StoreConfig>,
+    size: usize,
+    data: &[u8],
+) -> Result<MerkleTree<H::Domain, H::Function>> {
+    ensure!(
+        data.len() <= NODE_SIZE * size,
+        Error::InvalidMerkleTreeArgs(data.len(), NODE_SIZE, size)
+    );
+
+    let f = |i| {
+        let mut node = [0u8; NODE_SIZE];
+        copy_zero_padded(data, i * NODE_SIZE, &mut node);
+        // TODO Replace `expect()` with `context()` (problem is the parallel iterator)
+        H::Domain::try_from_bytes(&node).expect("failed to convert node data to domain element")
+    };
+
+    build_tree::<H, _>(config, size, f)
+}
+
+/// Builds a tree over the `size` leaves returned by `leaf`. With the `prover` feature the leaves
+/// are computed and hashed in parallel, on the `Merkle` pool.
+#[allow(clippy::let_and_return)]
+fn build_tree<H, F>(
+    config: Option<StoreConfig>,
+    size: usize,
+    leaf: F,
+) -> Result<MerkleTree<H::Domain, H::Function>>
+where
+    H: Hasher,
+    F: Fn(usize) -> H::Domain + Send + Sync,
+{
+    #[cfg(feature = "prover")]
+    let tree = thread_pools::install(Pool::Merkle, || match config {
+        Some(x) => MerkleTree::from_par_iter_with_config((0..size).into_par_iter().map(leaf), x),
+        None => MerkleTree::from_par_iter((0..size).into_par_iter().map(leaf)),
+    });
+
+    #[cfg(not(feature = "prover"))]
+    let tree = match config {
+        Some(x) => MerkleTree::new_with_config((0..size).map(leaf), x),
+        None => MerkleTree::new((0..size).map(leaf)),
+    };
+
+    tree
+}
+
+/// Fills `target` with the bytes of `source` starting at `offset`, padding with zeros where
+/// `source` ends early.
+pub fn copy_zero_padded(source: &[u8], offset: usize, target: &mut [u8]) {
+    let start = std::cmp::min(offset, source.len());
+    let end = std::cmp::min(offset + target.len(), source.len());
+    let (head, tail) = target.split_at_mut(end - start);
+
+    head.copy_from_slice(&source[start..end]);
+    for b in tail {
+        *b = 0;
     }
 }
 
@@ -301,4 +364,25 @@ mod tests {
     fn merklepath_blake2s() {
         merklepath::<Blake2sHasher>();
     }
+
+    #[test]
+    fn zero_padded_tree() {
+        let mut rng = rand::thread_rng();
+        let mut data = Vec::new();
+        for _ in 0..5 {
+            let elt = <PedersenHasher as Hasher>::Domain::random(&mut rng);
+            data.write(&elt.into_bytes()).unwrap();
+        }
+        // a trailing partial node
+        data.write(&[7u8; 5]).unwrap();
+
+        let mut padded = data.clone();
+        padded.resize(16 * NODE_SIZE, 0);
+
+        let tree = create_merkle_tree_zero_padded::<PedersenHasher>(None, 16, &data).unwrap();
+        let expected = create_merkle_tree::<PedersenHasher>(None, 16, &padded).unwrap();
+        assert_eq!(tree.root(), expected.root());
+
+        assert!(create_merkle_tree_zero_padded::<PedersenHasher>(None, 4, &data).is_err());
+    }
 }
@@ -0,0 +1,55 @@
+//! Parallel iterators for the code that is built with and without the `prover` feature.
+//!
+//! With `prover` these are the iterators of rayon. Without it the same methods return the
+//! sequential iterators of the standard library, so that verifiers do not depend on rayon.
+
+#[cfg(feature = "prover")]
+pub use rayon::prelude::*;
+
+#[cfg(not(feature = "prover"))]
+pub use self::sequential::*;
+
+#[cfg(not(feature = "prover"))]
+mod sequential {
+    use std::slice::{Chunks, ChunksMut, Iter, IterMut};
+
+    pub trait IntoParallelIterator: IntoIterator + Sized {
+        fn into_par_iter(self) -> Self::IntoIter {
+            self.into_iter()
+        }
+    }
+
+    impl<I: IntoIterator> IntoParallelIterator for I {}
+
+    pub trait ParallelSlice<T> {
+        fn par_iter(&self) -> Iter<T>;
+
+        fn par_chunks(&self, chunk_size: usize) -> Chunks<T>;
+    }
+
+    impl<T> ParallelSlice<T> for [T] {
+        fn par_iter(&self) -> Iter<T> {
+            self.iter()
+        }
+
+        fn par_chunks(&self, chunk_size: usize) -> Chunks<T> {
+            self.chunks(chunk_size)
+        }
+    }
+
+    pub trait ParallelSliceMut<T> {
+        fn par_iter_mut(&mut self) -> IterMut<T>;
+
+        fn par_chunks_mut(&mut self, chunk_size: usize) -> ChunksMut<T>;
+    }
+
+    impl<T> ParallelSliceMut<T> for [T] {
+        fn par_iter_mut(&mut self) -> IterMut<T> {
+            self.iter_mut()
+        }
+
+        fn par_chunks_mut(&mut self, chunk_size: usize) -> ChunksMut<T> {
+            self.chunks_mut(chunk_size)
+        }
+    }
+}
@@ -37,27 +37,6 @@ impl LockedFile {
 
         Ok(LockedFile(f))
     }
-
-    pub fn open_exclusive<P: AsRef<Path>>(p: P) -> io::Result<Self> {
-        let f = fs::OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(p)?;
-        f.lock_exclusive()?;
-
-        Ok(LockedFile(f))
-    }
-}
-
-impl io::Write for LockedFile {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.0.write(buf)
-    }
-
-    fn flush(&mut self) -> io::Result<()> {
-        self.0.flush()
-    }
 }
 
 impl io::Read for LockedFile {
@@ -115,6 +94,34 @@ pub fn parameter_cache_verifying_key_path(parameter_set_identifier: &str) -> Pat
     ))
 }
 
+/// Returns the circuit version of a file in the parameter cache, `None` if its name does not
+/// start with a version like `v20-`.
+pub fn parameter_cache_entry_version(filename: &str) -> Option<usize> {
+    if !filename.starts_with('v') {
+        return None;
+    }
+
+    filename[1..].splitn(2, '-').next()?.parse().ok()
+}
+
+/// Removes a file from the parameter cache, taking the same lock as the readers and writers of
+/// the cache. Returns `false`, leaving the file in place, if another process holds the lock.
+pub fn remove_cache_entry<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let file = fs::OpenOptions::new().read(true).open(path.as_ref())?;
+    if let Err(err) = file.try_lock_exclusive() {
+        if err.kind() == fs2::lock_contended_error().kind() {
+            return Ok(false);
+        }
+        return Err(err.into());
+    }
+
+    // the lock is held until the file is removed
+    let _locked = LockedFile(file);
+    fs::remove_file(path.as_ref())?;
+
+    Ok(true)
+}
+
 fn ensure_ancestor_dirs_exist(cache_entry_path: PathBuf) -> Result<PathBuf> {
     info!(
         "ensuring that all ancestor directories for: {:?} exist",
@@ -200,13 +207,49 @@ where
                 );
                 Ok(parameters)
             } else {
-                bail!("No cached parameters found for {}", id);
+                Err(Error::MissingParameters(id.clone()).into())
             }
         };
 
-        // generate (or load) Groth parameters
+        // generate (or load) Groth parameters; a file which exists but cannot be read is an error
         let cache_path = ensure_ancestor_dirs_exist(parameter_cache_params_path(&id))?;
-        read_cached_params(&cache_path).or_else(|_| write_cached_params(&cache_path, generate()?))
+        read_cached_params(&cache_path).or_else(|err| {
+            if is_not_found(&err) {
+                write_cached_params(&cache_path, generate()?)
+            } else {
+                Err(err.context(format!("could not read parameters {:?}", cache_path)))
+            }
+        })
+    }
+
+    /// Like `get_groth_params`, but maps the cached parameter file instead of reading it into
+    /// memory. The file is generated first if it is missing and an `rng` is given. A file which
+    /// exists but cannot be mapped is an error; it is never read into memory instead.
+    #[cfg(feature = "prover")]
+    fn get_mapped_groth_params<R: RngCore>(
+        rng: Option<&mut R>,
+        circuit: C,
+        pub_params: &P,
+    ) -> Result<crate::mapped_parameters::MappedParameters<E>> {
+        let id = Self::cache_identifier(pub_params);
+        let cache_path = ensure_ancestor_dirs_exist(parameter_cache_params_path(&id))?;
+
+        match read_cached_mapped_params(&cache_path) {
+            Ok(params) => Ok(params),
+            Err(err) if is_not_found(&err) => {
+                let rng = match rng {
+                    Some(rng) => rng,
+                    None => return Err(Error::MissingParameters(id).into()),
+                };
+
+                info!("Actually generating groth params. (id: {})", &id);
+                let parameters = groth16::generate_random_parameters::<E, _, _>(circuit, rng)?;
+                write_cached_params(&cache_path, parameters)?;
+
+                read_cached_mapped_params(&cache_path)
+            }
+            Err(err) => Err(err.context(format!("could not map parameters {:?}", cache_path))),
+        }
     }
 
     fn get_verifying_key<R: RngCore>(
@@ -222,13 +265,27 @@ where
             Ok(groth_params.vk)
         };
 
-        // generate (or load) verifying key
+        // generate (or load) verifying key; a file which exists but cannot be read is an error
         let cache_path = ensure_ancestor_dirs_exist(parameter_cache_verifying_key_path(&id))?;
-        read_cached_verifying_key(&cache_path)
-            .or_else(|_| write_cached_verifying_key(&cache_path, generate()?))
+        read_cached_verifying_key(&cache_path).or_else(|err| {
+            if is_not_found(&err) {
+                write_cached_verifying_key(&cache_path, generate()?)
+            } else {
+                Err(err.context(format!("could not read verifying key {:?}", cache_path)))
+            }
+        })
     }
 }
 
+/// Whether `err` was caused by a missing file.
+fn is_not_found(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|cause| match cause.downcast_ref::<io::Error>() {
+            Some(err) => err.kind() == io::ErrorKind::NotFound,
+            None => false,
+        })
+}
+
 fn ensure_parent(path: &PathBuf) -> Result<()> {
     match path.parent() {
         Some(dir) => {
@@ -251,6 +308,21 @@ fn read_cached_params<E: JubjubEngine>(
     })
 }
 
+#[cfg(feature = "prover")]
+fn read_cached_mapped_params<E: JubjubEngine>(
+    cache_entry_path: &PathBuf,
+) -> Result<crate::mapped_parameters::MappedParameters<E>> {
+    info!("checking cache_path: {:?} for parameters", cache_entry_path);
+    // The lock is only held while the file is mapped; writers rename a completely written file
+    // into place (see `with_replaced_file`), so a mapped file never changes.
+    with_exclusive_read_lock(cache_entry_path, |_| {
+        let params = crate::mapped_parameters::MappedParameters::build(cache_entry_path, false)?;
+        info!("mapped parameters from cache {:?} ", cache_entry_path);
+
+        Ok(params)
+    })
+}
+
 fn read_cached_verifying_key<E: JubjubEngine>(
     cache_entry_path: &PathBuf,
 ) -> Result<groth16::VerifyingKey<E>> {
@@ -280,7 +352,7 @@ fn write_cached_metadata(
     cache_entry_path: &PathBuf,
     value: CacheEntryMetadata,
 ) -> Result<CacheEntryMetadata> {
-    with_exclusive_lock(cache_entry_path, |file| {
+    with_replaced_file(cache_entry_path, |file| {
         serde_json::to_writer(file, &value)?;
         info!("wrote metadata to cache {:?} ", cache_entry_path);
 
@@ -292,7 +364,7 @@ fn write_cached_verifying_key<E: JubjubEngine>(
     cache_entry_path: &PathBuf,
     value: groth16::VerifyingKey<E>,
 ) -> Result<groth16::VerifyingKey<E>> {
-    with_exclusive_lock(cache_entry_path, |file| {
+    with_replaced_file(cache_entry_path, |file| {
         value.write(file)?;
         info!("wrote verifying key to cache {:?} ", cache_entry_path);
 
@@ -304,7 +376,7 @@ fn write_cached_params<E: JubjubEngine>(
     cache_entry_path: &PathBuf,
     value: groth16::Parameters<E>,
 ) -> Result<groth16::Parameters<E>> {
-    with_exclusive_lock(cache_entry_path, |file| {
+    with_replaced_file(cache_entry_path, |file| {
         value.write(file)?;
         info!("wrote groth parameters to cache {:?} ", cache_entry_path);
 
@@ -312,11 +384,23 @@ fn write_cached_params<E: JubjubEngine>(
     })
 }
 
-fn with_exclusive_lock<T>(
-    file_path: &PathBuf,
-    f: impl FnOnce(&mut LockedFile) -> Result<T>,
-) -> Result<T> {
-    with_open_file(file_path, LockedFile::open_exclusive, f)
+/// Writes the contents of `file_path` to a temporary file in the same directory, which is renamed
+/// into place once completely written. A cache file is thus never modified in place: readers see
+/// either the previous file, which stays intact for as long as they have it open or mapped, or
+/// the complete new one.
+fn with_replaced_file<T>(file_path: &PathBuf, f: impl FnOnce(&mut File) -> Result<T>) -> Result<T> {
+    ensure_parent(&file_path)?;
+    let dir = match file_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    let value = f(file.as_file_mut())?;
+    file.as_file().sync_all()?;
+    file.persist(file_path).map_err(|err| err.error)?;
+
+    Ok(value)
 }
 
 fn with_exclusive_read_lock<T>(
@@ -334,3 +418,35 @@ fn with_open_file<'a, T>(
     ensure_parent(&file_path)?;
     f(&mut open_file(&file_path)?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameter_cache_entry_version() {
+        assert_eq!(
+            parameter_cache_entry_version("v20-stacked-proof-of-replication-abc.params"),
+            Some(20)
+        );
+        assert_eq!(parameter_cache_entry_version("v9-abc.vk"), Some(9));
+        assert_eq!(parameter_cache_entry_version("vx-abc.vk"), None);
+        assert_eq!(parameter_cache_entry_version("abc.vk"), None);
+    }
+
+    #[test]
+    fn test_remove_cache_entry_honors_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("v1-abc.vk");
+        File::create(&path).unwrap();
+
+        {
+            let _locked = LockedFile::open_exclusive_read(&path).unwrap();
+            assert!(!remove_cache_entry(&path).unwrap());
+            assert!(path.exists());
+        }
+
+        assert!(remove_cache_entry(&path).unwrap());
+        assert!(!path.exists());
+    }
+}
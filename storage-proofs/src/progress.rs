@@ -0,0 +1,161 @@
+use crate::cancellation::CancellationToken;
+use crate::error::Result;
+use crate::sector::SectorId;
+
+/// A stage of sealing or proving a sector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Building the merkle tree over the original data (tree_d).
+    TreeD,
+    /// Labeling all layers of all windows and encoding the data.
+    Labeling,
+    /// Hashing the label columns, the leaves of tree_c.
+    ColumnHashes,
+    TreeQ,
+    TreeRLast,
+    TreeC,
+    /// Generating the vanilla proofs of all partitions.
+    VanillaProofs,
+    /// Generating the circuit proofs of all partitions.
+    SnarkProofs,
+    /// Loading the merkle trees of the challenged replicas.
+    PostTrees,
+    /// Generating the PoSt candidates.
+    PostCandidates,
+    /// Generating the circuit proofs of the winning PoSt candidates.
+    PostProofs,
+}
+
+/// A progress event, emitted while sealing or proving.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Progress {
+    /// A stage has started. `steps` is the number of step events (`WindowLayerLabeled`,
+    /// `PartitionProven`, `SectorTreeLoaded` or `SectorProven`) that the stage will emit, zero for
+    /// stages which only report their start and end.
+    StageStarted {
+        stage: Stage,
+        steps: usize,
+    },
+    StageFinished {
+        stage: Stage,
+    },
+    /// A layer of a window has been labeled, `nodes` being the number of nodes in the window.
+    WindowLayerLabeled {
+        window_index: usize,
+        layer: usize,
+        nodes: usize,
+    },
+    /// A whole merkle tree has been built and stored, with `height` levels including the leaves.
+    /// Emitted once per tree: the merkle tree builder does not report its levels one by one, so
+    /// the tree stages have no step events.
+    TreeCompleted {
+        stage: Stage,
+        leaves: usize,
+        height: usize,
+    },
+    /// The circuit proof of a partition has been generated.
+    PartitionProven {
+        partition: usize,
+        partitions: usize,
+    },
+    /// The merkle tree of a challenged replica has been loaded.
+    SectorTreeLoaded {
+        sector_id: SectorId,
+        leaves: usize,
+    },
+    /// The PoSt circuit proof of a winning sector has been generated.
+    SectorProven {
+        sector_id: SectorId,
+    },
+}
+
+/// Receives progress events from long-running operations. Events may be emitted concurrently from
+/// several threads, so implementations should return quickly.
+pub trait ProgressObserver: Sync {
+    fn on_progress(&self, progress: Progress);
+}
+
+impl<F: Fn(Progress) + Sync> ProgressObserver for F {
+    fn on_progress(&self, progress: Progress) {
+        self(progress)
+    }
+}
+
+/// An observer which ignores all events.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressObserver for NoProgress {
+    fn on_progress(&self, _progress: Progress) {}
+}
+
+/// Options of a long-running sealing or proving operation, taken by the `*_with_options`
+/// variants of those operations. The default reports no progress and runs to completion.
+#[derive(Clone, Copy)]
+pub struct RunOptions<'a> {
+    progress: &'a dyn ProgressObserver,
+    cancel: Option<&'a CancellationToken>,
+}
+
+impl Default for RunOptions<'_> {
+    fn default() -> Self {
+        RunOptions {
+            progress: &NoProgress,
+            cancel: None,
+        }
+    }
+}
+
+impl<'a> RunOptions<'a> {
+    /// Reports the progress of the operation to `progress`.
+    pub fn with_progress(self, progress: &'a dyn ProgressObserver) -> Self {
+        RunOptions { progress, ..self }
+    }
+
+    /// Stops the operation at its next checkpoint once `cancel` is cancelled, failing with
+    /// `Error::Cancelled`.
+    pub fn with_cancel(self, cancel: &'a CancellationToken) -> Self {
+        RunOptions {
+            cancel: Some(cancel),
+            ..self
+        }
+    }
+
+    /// The same options, but reporting no progress. For operations nested in another one which
+    /// reports its own, coarser progress.
+    pub fn without_progress(self) -> Self {
+        RunOptions {
+            progress: &NoProgress,
+            ..self
+        }
+    }
+
+    pub fn progress(&self) -> &'a dyn ProgressObserver {
+        self.progress
+    }
+
+    /// Returns `Error::Cancelled` if the operation has been cancelled.
+    pub fn check_cancelled(&self) -> Result<()> {
+        match self.cancel {
+            Some(cancel) => cancel.check(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Runs `f` as `stage`, reporting the start and, if `f` succeeds, the end of the stage.
+pub fn report_stage<T, E, F>(
+    progress: &dyn ProgressObserver,
+    stage: Stage,
+    steps: usize,
+    f: F,
+) -> std::result::Result<T, E>
+where
+    F: FnOnce() -> std::result::Result<T, E>,
+{
+    progress.on_progress(Progress::StageStarted { stage, steps });
+    let res = f()?;
+    progress.on_progress(Progress::StageFinished { stage });
+
+    Ok(res)
+}
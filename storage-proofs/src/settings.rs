@@ -17,8 +17,28 @@ pub struct Settings {
     pub maximize_caching: bool,
     pub merkle_tree_path: String,
     pub num_proving_threads: usize,
+    /// Cores the proving threads are pinned to, e.g. `"0-3,8"`. Empty for no pinning.
+    pub proving_cores: String,
+    /// Threads used to label the windows, `0` for the global rayon pool (or one per core in
+    /// `labeling_cores`). The `merkle_*`, `column_hash_*` and `post_read_*` settings likewise
+    /// size the pools building the trees, hashing the label columns and reading the replicas
+    /// challenged by PoSt. See `thread_pools`.
+    pub labeling_threads: usize,
+    pub labeling_cores: String,
+    pub merkle_threads: usize,
+    pub merkle_cores: String,
+    pub column_hash_threads: usize,
+    pub column_hash_cores: String,
+    pub post_read_threads: usize,
+    pub post_read_cores: String,
     pub replicated_trees_dir: String,
     pub pedersen_hash_exp_window_size: u32,
+    /// Number of Groth parameter sets kept mapped in memory before the least recently used one
+    /// is dropped. Zero means no limit.
+    pub max_cached_groth_params: usize,
+    /// Upper bound on the bytes of Groth parameters kept mapped in memory before the least
+    /// recently used set is dropped. Zero means no limit.
+    pub max_cached_groth_params_bytes: u64,
     // Generating MTs in parallel optimizes for speed while generating them
     // in sequence (`false`) optimizes for memory.
 }
@@ -29,8 +49,19 @@ impl Default for Settings {
             maximize_caching: false,
             merkle_tree_path: "/tmp/merkle-trees".into(),
             num_proving_threads: 1,
+            proving_cores: "".into(),
+            labeling_threads: 0,
+            labeling_cores: "".into(),
+            merkle_threads: 0,
+            merkle_cores: "".into(),
+            column_hash_threads: 0,
+            column_hash_cores: "".into(),
+            post_read_threads: 0,
+            post_read_cores: "".into(),
             replicated_trees_dir: "".into(),
             pedersen_hash_exp_window_size: 16,
+            max_cached_groth_params: 4,
+            max_cached_groth_params_bytes: 0,
         }
     }
 }
@@ -9,9 +9,12 @@ mod graph;
 pub(crate) mod hash;
 mod labeling_proof;
 mod params;
+#[cfg(feature = "prover")]
 mod porep;
 mod proof;
 mod proof_scheme;
+#[cfg(feature = "prover")]
+mod replicate;
 
 pub const OPENINGS_PER_WINDOW: usize = 1;
 
@@ -21,9 +24,9 @@ pub use self::column_proof::ColumnProof;
 pub use self::encoding_proof::EncodingProof;
 pub use self::graph::{StackedBucketGraph, StackedGraph, EXP_DEGREE};
 pub use self::params::{
-    generate_replica_id, CacheKey, PersistentAux, PrivateInputs, Proof, PublicInputs, PublicParams,
-    ReplicaColumnProof, SetupParams, Tau, TemporaryAux, TemporaryAuxCache, WindowProof,
-    WrapperProof,
+    generate_replica_id, BatchReplica, CacheKey, PersistentAux, PrivateInputs, Proof, PublicInputs,
+    PublicParams, ReplicaColumnProof, SetupParams, Tau, TemporaryAux, TemporaryAuxCache,
+    WindowProof, WrapperProof,
 };
 pub use self::proof::{create_key, StackedConfig, StackedDrg};
 pub use labeling_proof::LabelingProof;
@@ -463,6 +463,20 @@ pub type TransformedLayers<H, G> = (
     TemporaryAux<H, G>,
 );
 
+/// One of the replicas replicated together by `StackedDrg::replicate_many`.
+pub struct BatchReplica<'d, H: Hasher, G: Hasher> {
+    pub replica_id: H::Domain,
+    /// The original data, zero padded to the length of `data`. If `None`, `data` holds the
+    /// original data and is replicated in place.
+    pub source: Option<&'d [u8]>,
+    /// Receives the replica.
+    pub data: &'d mut [u8],
+    /// The tree over the original data, built if not given.
+    pub data_tree: Option<Tree<G>>,
+    /// Where the trees and labels of this replica are written.
+    pub config: StoreConfig,
+}
+
 /// Tau for a single parition.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Tau<D: Domain, E: Domain> {
@@ -3,14 +3,67 @@ use crate::hasher::Hasher;
 use crate::measurements::measure_op;
 use crate::measurements::Operation::PorepCommitTime;
 use crate::porep::PoRep;
+use crate::progress::RunOptions;
 use crate::stacked::{
-    params::{PersistentAux, PublicParams, Tau, TemporaryAux, Tree},
+    params::{BatchReplica, PersistentAux, PublicParams, Tau, TemporaryAux, Tree},
     proof::StackedDrg,
 };
 use crate::util::NODE_SIZE;
 
 use merkletree::store::StoreConfig;
 
+impl<'a, H: 'static + Hasher, G: 'static + Hasher> StackedDrg<'a, H, G> {
+    /// Same as `PoRep::replicate`, reporting the progress of each stage and stopping once
+    /// cancelled as set in `options`. A cancelled replication fails with `Error::Cancelled`.
+    ///
+    /// If `source` is given, the original data is read from it rather than from `data`, which
+    /// only receives the replica. `source` may be shorter than `data`, the rest of the original
+    /// data being zeros.
+    #[allow(clippy::too_many_arguments)]
+    pub fn replicate_with_options(
+        pp: &'a PublicParams<H>,
+        replica_id: &H::Domain,
+        source: Option<&[u8]>,
+        data: &mut [u8],
+        data_tree: Option<Tree<G>>,
+        config: Option<StoreConfig>,
+        options: RunOptions,
+    ) -> Result<(
+        Tau<H::Domain, G::Domain>,
+        (PersistentAux<H::Domain>, TemporaryAux<H, G>),
+    )> {
+        let (tau, p_aux, t_aux) = measure_op(PorepCommitTime, || {
+            Self::transform_and_replicate_layers(
+                pp, replica_id, source, data, data_tree, config, options,
+            )
+        })?;
+
+        Ok((tau, (p_aux, t_aux)))
+    }
+
+    /// Replicates several sectors at once. The windows of all replicas are labeled together,
+    /// computing the parents of each node once instead of once per replica.
+    pub fn replicate_many(
+        pp: &'a PublicParams<H>,
+        replicas: Vec<BatchReplica<'_, H, G>>,
+        options: RunOptions,
+    ) -> Result<
+        Vec<(
+            Tau<H::Domain, G::Domain>,
+            (PersistentAux<H::Domain>, TemporaryAux<H, G>),
+        )>,
+    > {
+        let replicas = measure_op(PorepCommitTime, || {
+            Self::transform_and_replicate_layers_many(pp, replicas, options)
+        })?;
+
+        Ok(replicas
+            .into_iter()
+            .map(|(tau, p_aux, t_aux)| (tau, (p_aux, t_aux)))
+            .collect())
+    }
+}
+
 impl<'a, 'c, H: 'static + Hasher, G: 'static + Hasher> PoRep<'a, H, G> for StackedDrg<'a, H, G> {
     type Tau = Tau<<H as Hasher>::Domain, <G as Hasher>::Domain>;
     type ProverAux = (PersistentAux<H::Domain>, TemporaryAux<H, G>);
@@ -22,11 +75,15 @@ impl<'a, 'c, H: 'static + Hasher, G: 'static + Hasher> PoRep<'a, H, G> for Stack
         data_tree: Option<Tree<G>>,
         config: Option<StoreConfig>,
     ) -> Result<(Self::Tau, Self::ProverAux)> {
-        let (tau, p_aux, t_aux) = measure_op(PorepCommitTime, || {
-            Self::transform_and_replicate_layers(pp, replica_id, data, data_tree, config)
-        })?;
-
-        Ok((tau, (p_aux, t_aux)))
+        Self::replicate_with_options(
+            pp,
+            replica_id,
+            None,
+            data,
+            data_tree,
+            config,
+            RunOptions::default(),
+        )
     }
 
     fn extract_all<'b>(
@@ -1,35 +1,28 @@
 use std::collections::HashMap;
 use std::marker::PhantomData;
-use std::sync::Mutex;
 
 use anyhow::{ensure, Context};
 use generic_array::GenericArray;
 use log::{info, trace};
-use merkletree::merkle::FromIndexedParallelIterator;
-use merkletree::store::{DiskStore, StoreConfig};
+use merkletree::store::StoreConfig;
 use paired::bls12_381::Fr;
-use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
 use crate::drgraph::Graph;
-use crate::encode::{decode, encode};
+use crate::encode::decode;
 use crate::error::Result;
 use crate::hasher::{Domain, Hasher};
-use crate::measurements::measure_op;
-use crate::measurements::Operation::{
-    CommD, EncodeWindowTimeAll, GenerateTreeC, GenerateTreeRLast, WindowCommLeavesTime,
-};
-use crate::merkle::{MerkleProof, MerkleTree, Store};
+use crate::merkle::MerkleProof;
+use crate::par::*;
 use crate::stacked::{
     challenges::LayerChallenges,
     column::Column,
     graph::StackedBucketGraph,
     hash::hash3,
     params::{
-        get_node, CacheKey, Labels, LabelsCache, PersistentAux, Proof, PublicInputs, PublicParams,
-        ReplicaColumnProof, Tau, TemporaryAux, TemporaryAuxCache, TransformedLayers, Tree,
-        WindowProof, WrapperProof,
+        Proof, PublicInputs, PublicParams, ReplicaColumnProof, TemporaryAuxCache, WindowProof,
+        WrapperProof,
     },
     EncodingProof, LabelingProof, OPENINGS_PER_WINDOW,
 };
@@ -155,6 +148,32 @@ impl<'a, H: 'static + Hasher, G: 'static + Hasher> StackedDrg<'a, H, G> {
         })
     }
 
+    /// The leaves of tree_d whose inclusion proofs the partitions `0..partition_count` open,
+    /// which are all a prover needs of a tree_d it can compute any node of.
+    pub fn tree_d_openings(
+        pub_params: &PublicParams<H>,
+        pub_inputs: &PublicInputs<<H as Hasher>::Domain, <G as Hasher>::Domain>,
+        partition_count: usize,
+    ) -> Result<Vec<usize>> {
+        let mut openings = Vec::new();
+        for k in 0..partition_count {
+            let window_challenges = pub_inputs.all_challenges(
+                &pub_params.config.window_challenges,
+                pub_params.window_graph.size(),
+                Some(k),
+            )?;
+            for challenge in window_challenges {
+                openings.extend(
+                    (0..OPENINGS_PER_WINDOW).map(|window_index| {
+                        window_index * pub_params.window_size_nodes() + challenge
+                    }),
+                );
+            }
+        }
+
+        Ok(openings)
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn prove_window_challenge(
         challenge: usize,
@@ -613,352 +632,6 @@ impl<'a, H: 'static + Hasher, G: 'static + Hasher> StackedDrg<'a, H, G> {
 
         Ok(())
     }
-
-    fn label_encode_all_windows(
-        pub_params: &PublicParams<H>,
-        replica_id: &<H as Hasher>::Domain,
-        data: &mut [u8],
-        config: StoreConfig,
-    ) -> Result<(LabelsCache<H>, Labels<H>)> {
-        trace!("encode_all_windows");
-        let window_graph = &pub_params.window_graph;
-        let layers = pub_params.config.layers();
-
-        ensure!(
-            window_graph.size() == pub_params.window_size_nodes(),
-            "Invalid window size."
-        );
-
-        let layer_size = data.len();
-        let num_windows = pub_params.num_windows();
-
-        let labels: Vec<Mutex<(DiskStore<_>, _)>> = (0..layers)
-            .map(|layer| -> Result<_> {
-                let layer_config = StoreConfig::from_config(
-                    &config,
-                    CacheKey::label_layer(layer),
-                    Some(layer_size / NODE_SIZE),
-                );
-
-                let layer_store: DiskStore<H::Domain> =
-                    DiskStore::new_with_config(layer_size / NODE_SIZE, layer_config.clone())?;
-
-                let r = Mutex::new((layer_store, layer_config));
-                Ok(r)
-            })
-            .collect::<Result<_>>()?;
-
-        (0..num_windows)
-            .into_par_iter()
-            .zip(data.par_chunks_mut(pub_params.window_size_bytes()))
-            .try_for_each(|(window_index, data_chunk)| -> Result<()> {
-                let mut layer_labels = vec![0u8; pub_params.window_size_bytes()];
-                let mut parents = vec![0; window_graph.degree()];
-                let mut exp_parents_data: Option<Vec<u8>> = None;
-
-                // setup hasher to reuse
-                let mut base_hasher = Sha256::new();
-
-                // hash replica id
-                base_hasher.input(AsRef::<[u8]>::as_ref(replica_id));
-
-                for layer in 1..=layers {
-                    trace!("generating layer: {}", layer);
-
-                    Self::label_encode_window_layer(
-                        layer,
-                        layers,
-                        window_graph,
-                        base_hasher.clone(),
-                        &mut parents,
-                        exp_parents_data.as_ref(),
-                        &mut layer_labels,
-                        data_chunk,
-                        window_index,
-                    )?;
-
-                    if layer < layers {
-                        if let Some(ref mut exp_parents_data) = exp_parents_data {
-                            exp_parents_data.copy_from_slice(&layer_labels);
-                        } else {
-                            exp_parents_data = Some(layer_labels.clone());
-                        }
-                    }
-                    // write result to disk
-                    labels[layer - 1].lock().unwrap().0.copy_from_slice(
-                        &layer_labels,
-                        window_index * pub_params.window_size_nodes(),
-                    )?;
-                }
-                Ok(())
-            })?;
-
-        let (labels, configs) = labels.into_iter().map(|v| v.into_inner().unwrap()).unzip();
-
-        Ok((
-            LabelsCache::<H>::from_stores(labels),
-            Labels::<H>::new(configs),
-        ))
-    }
-
-    #[allow(clippy::too_many_arguments)]
-    fn label_encode_window_layer(
-        layer: usize,
-        layers: usize,
-        window_graph: &StackedBucketGraph<H>,
-        base_hasher: Sha256,
-        parents: &mut [u32],
-        exp_parents_data: Option<&Vec<u8>>,
-        layer_labels: &mut [u8],
-        data_chunk: &mut [u8],
-        window_index: usize,
-    ) -> Result<()> {
-        for node in 0..window_graph.size() {
-            window_graph.parents(node, parents)?;
-
-            let key = create_key(
-                window_graph,
-                base_hasher.clone(),
-                parents,
-                exp_parents_data,
-                &layer_labels,
-                window_index,
-                node,
-            )?;
-            let start = node * NODE_SIZE;
-            let end = (node + 1) * NODE_SIZE;
-
-            // store the newly generated key
-            layer_labels[start..end].copy_from_slice(&key[..]);
-
-            if layer == layers {
-                // on the last layer we encode the data
-                let keyd = H::Domain::try_from_bytes(&key)?;
-                let data_node = H::Domain::try_from_bytes(&data_chunk[start..end])?;
-                let encoded_node = encode(keyd, data_node);
-                data_chunk[start..end].copy_from_slice(AsRef::<[u8]>::as_ref(&encoded_node));
-            }
-        }
-
-        Ok(())
-    }
-
-    fn build_tree<K: Hasher>(tree_data: &[u8], config: Option<StoreConfig>) -> Result<Tree<K>> {
-        trace!("building tree (size: {})", tree_data.len());
-
-        let leafs = tree_data.len() / NODE_SIZE;
-        ensure!(tree_data.len() % NODE_SIZE == 0, "Invalid tree data.");
-        if let Some(config) = config {
-            MerkleTree::from_par_iter_with_config(
-                (0..leafs)
-                    .into_par_iter()
-                    // TODO proper error handling instead of `unwrap()`
-                    .map(|i| get_node::<K>(tree_data, i).unwrap()),
-                config,
-            )
-        } else {
-            MerkleTree::from_par_iter(
-                (0..leafs)
-                    .into_par_iter()
-                    // TODO proper error handling instead of `unwrap()`
-                    .map(|i| get_node::<K>(tree_data, i).unwrap()),
-            )
-        }
-    }
-
-    fn build_column_hashes(
-        pub_params: &PublicParams<H>,
-        labels: &LabelsCache<H>,
-    ) -> Result<Vec<[u8; 32]>> {
-        (0..pub_params.window_size_nodes())
-            .into_par_iter()
-            .map(|i| Self::build_column_hash(pub_params, i, labels))
-            .collect()
-    }
-
-    fn build_column_hash(
-        pub_params: &PublicParams<H>,
-        column_index: usize,
-        labels: &LabelsCache<H>,
-    ) -> Result<[u8; 32]> {
-        let num_windows = pub_params.num_windows();
-        let layers = pub_params.config.layers();
-
-        let first_label = labels.labels_for_layer(1)?.read_at(column_index)?;
-        let mut hasher = crate::crypto::pedersen::Hasher::new(AsRef::<[u8]>::as_ref(&first_label))?;
-
-        for window_index in 0..num_windows {
-            for layer in 1..layers {
-                if window_index == 0 && layer == 1 {
-                    // first label
-                    continue;
-                }
-
-                let label = labels
-                    .labels_for_layer(layer)?
-                    .read_at(window_index * pub_params.window_size_nodes() + column_index)?;
-
-                hasher.update(AsRef::<[u8]>::as_ref(&label))?;
-            }
-        }
-
-        Ok(hasher.finalize_bytes())
-    }
-
-    pub(crate) fn transform_and_replicate_layers(
-        pub_params: &PublicParams<H>,
-        replica_id: &<H as Hasher>::Domain,
-        data: &mut [u8],
-        data_tree: Option<Tree<G>>,
-        config: Option<StoreConfig>,
-    ) -> Result<TransformedLayers<H, G>> {
-        trace!("transform_and_replicate_layers");
-        let window_graph = &pub_params.window_graph;
-        let wrapper_graph = &pub_params.wrapper_graph;
-
-        ensure!(
-            window_graph.size() == pub_params.window_size_nodes(),
-            "Invalid window size."
-        );
-
-        let wrapper_nodes_count = wrapper_graph.size();
-        ensure!(
-            data.len() == wrapper_nodes_count * NODE_SIZE,
-            "Invalid data size."
-        );
-
-        let layers = pub_params.config.layers();
-        ensure!(layers > 0, "No layer found.");
-
-        // Generate all store configs that we need based on the
-        // cache_path in the specified config.
-        let config = config.context("missing config")?;
-        let mut tree_d_config =
-            StoreConfig::from_config(&config, CacheKey::CommDTree.to_string(), None);
-        let mut tree_r_last_config =
-            StoreConfig::from_config(&config, CacheKey::CommRLastTree.to_string(), None);
-        let mut tree_c_config =
-            StoreConfig::from_config(&config, CacheKey::CommCTree.to_string(), None);
-        let mut tree_q_config =
-            StoreConfig::from_config(&config, CacheKey::CommQTree.to_string(), None);
-
-        // Build the MerkleTree over the original data (if needed).
-        let tree_d = measure_op(CommD, || match data_tree {
-            Some(t) => {
-                trace!("using existing original data merkle tree");
-                ensure!(
-                    t.len() == 2 * (data.len() / NODE_SIZE) - 1,
-                    "Invalid data tree."
-                );
-
-                Ok(t)
-            }
-            None => {
-                trace!("building merkle tree for the original data");
-                Ok(Self::build_tree::<G>(&data, Some(tree_d_config.clone()))?)
-            }
-        })?;
-
-        info!(
-            "encoding {} windows",
-            data.len() / pub_params.window_size_bytes()
-        );
-
-        let (labels, label_configs) = measure_op(EncodeWindowTimeAll, || {
-            Self::label_encode_all_windows(pub_params, replica_id, data, config)
-        })?;
-
-        // construct column hashes
-        info!("building column hashes");
-        let column_hashes = measure_op(WindowCommLeavesTime, || {
-            Self::build_column_hashes(pub_params, &labels)
-        })?;
-
-        info!("building tree_q");
-        let tree_q: Tree<H> = Self::build_tree::<H>(&data, Some(tree_q_config.clone()))?;
-
-        info!("building tree_r_last");
-        let tree_r_last: Tree<H> = measure_op(GenerateTreeRLast, || {
-            MerkleTree::from_par_iter_with_config(
-                (0..wrapper_nodes_count).into_par_iter().map(|node| {
-                    // 1 Wrapping Layer
-
-                    let mut hasher = Sha256::new();
-                    hasher.input(AsRef::<[u8]>::as_ref(replica_id));
-                    hasher.input(&(node as u64).to_be_bytes()[..]);
-
-                    // Only expansion parents
-                    let mut exp_parents = vec![0; wrapper_graph.expansion_degree()];
-                    // TODO Do proper error handling and not just `expect()`.
-                    wrapper_graph
-                        .expanded_parents(node, &mut exp_parents)
-                        .expect("cannot expand parents");
-
-                    let wrapper_layer = &data;
-                    for parent in &exp_parents {
-                        // TODO Do proper error handling and not just `expect()`.
-                        hasher.input(
-                            data_at_node(wrapper_layer, *parent as usize)
-                                .expect("invalid node math"),
-                        );
-                    }
-
-                    // finalize key
-                    let mut val = hasher.result();
-                    // strip last two bits, to ensure result is in Fr.
-                    val[31] &= 0b0011_1111;
-
-                    // TODO Do proper error handling and not just `expect()`.
-                    H::Domain::try_from_bytes(&val).expect("invalid node created")
-                }),
-                tree_r_last_config.clone(),
-            )
-        })?;
-
-        let tree_c = measure_op(GenerateTreeC, || {
-            let column_hashes_flat = unsafe {
-                // Column_hashes is of type Vec<[u8; 32]>, so this is safe to do.
-                // We do this to avoid unnecessary allocations.
-                std::slice::from_raw_parts(
-                    column_hashes.as_ptr() as *const _,
-                    column_hashes.len() * 32,
-                )
-            };
-            Self::build_tree::<H>(column_hashes_flat, Some(tree_c_config.clone()))
-        })?;
-
-        // comm_r = H(comm_c || comm_q || comm_r_last)
-        let comm_r: H::Domain =
-            Fr::from(hash3(tree_c.root(), tree_q.root(), tree_r_last.root())).into();
-
-        ensure!(tree_d.len() == tree_r_last.len(), "Invalid tree_r.");
-        ensure!(tree_d.len() == tree_q.len(), "Invlaid tree_q.");
-
-        tree_d_config.size = Some(tree_d.len());
-        tree_r_last_config.size = Some(tree_r_last.len());
-        tree_c_config.size = Some(tree_c.len());
-        tree_q_config.size = Some(tree_q.len());
-
-        Ok((
-            Tau {
-                comm_d: tree_d.root(),
-                comm_r,
-            },
-            PersistentAux {
-                comm_c: tree_c.root(),
-                comm_q: tree_q.root(),
-                comm_r_last: tree_r_last.root(),
-            },
-            TemporaryAux {
-                labels: label_configs,
-                tree_d_config,
-                tree_r_last_config,
-                tree_c_config,
-                tree_q_config,
-                _g: PhantomData,
-            },
-        ))
-    }
 }
 
 pub fn create_key<H: Hasher>(
@@ -1004,25 +677,67 @@ pub fn create_key<H: Hasher>(
     Ok(key)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "prover"))]
 mod tests {
     use super::*;
 
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
     use ff::Field;
     use merkletree::store::DEFAULT_CACHED_ABOVE_BASE_LAYER;
     use paired::bls12_381::Bls12;
     use rand::{Rng, SeedableRng};
     use rand_xorshift::XorShiftRng;
 
+    use crate::cancellation::{is_cancelled, CancellationToken};
     use crate::drgraph::{new_seed, BASE_DEGREE};
     use crate::fr32::fr_into_bytes;
     use crate::hasher::{Blake2sHasher, PedersenHasher, Sha256Hasher};
     use crate::porep::PoRep;
+    use crate::progress::{Progress, RunOptions, Stage};
     use crate::proof::ProofScheme;
-    use crate::stacked::{PrivateInputs, SetupParams, EXP_DEGREE};
+    use crate::stacked::{
+        BatchReplica, CacheKey, PrivateInputs, SetupParams, TemporaryAux, EXP_DEGREE,
+    };
 
     const DEFAULT_STACKED_LAYERS: usize = 4;
 
+    /// The public params of a sector of `nodes` nodes in four windows.
+    fn four_window_public_params(nodes: usize) -> PublicParams<PedersenHasher> {
+        let sp = SetupParams {
+            nodes,
+            window_drg_degree: BASE_DEGREE,
+            window_expansion_degree: EXP_DEGREE,
+            wrapper_expansion_degree: EXP_DEGREE,
+            seed: new_seed(),
+            config: StackedConfig::new(DEFAULT_STACKED_LAYERS, 5, 8).unwrap(),
+            window_size_nodes: nodes / 4,
+        };
+
+        StackedDrg::<PedersenHasher, Blake2sHasher>::setup(&sp).expect("setup failed")
+    }
+
+    /// A store config in a new cache directory, which is removed when the returned `TempDir` is
+    /// dropped.
+    fn temp_store_config() -> (tempfile::TempDir, StoreConfig) {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let config = StoreConfig::new(
+            cache_dir.path(),
+            CacheKey::CommDTree.to_string(),
+            DEFAULT_CACHED_ABOVE_BASE_LAYER,
+        );
+
+        (cache_dir, config)
+    }
+
+    /// `nodes` random field elements.
+    fn random_data<R: Rng>(rng: &mut R, nodes: usize) -> Vec<u8> {
+        (0..nodes)
+            .flat_map(|_| fr_into_bytes::<Bls12>(&Fr::random(rng)))
+            .collect()
+    }
+
     #[test]
     fn test_calculate_fixed_challenges() {
         let layer_challenges = LayerChallenges::new(10, 333).unwrap();
@@ -1110,6 +825,193 @@ mod tests {
         assert_eq!(data, decoded_data);
     }
 
+    #[test]
+    fn replicate_reports_progress() {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let replica_id = <PedersenHasher as Hasher>::Domain::random(rng);
+        let nodes = 8 * 32;
+
+        let mut data = random_data(rng, nodes);
+
+        let pp = four_window_public_params(nodes);
+
+        let (_cache_dir, config) = temp_store_config();
+
+        let events = Mutex::new(Vec::new());
+        StackedDrg::<PedersenHasher, Blake2sHasher>::replicate_with_options(
+            &pp,
+            &replica_id,
+            None,
+            &mut data,
+            None,
+            Some(config),
+            RunOptions::default()
+                .with_progress(&|progress: Progress| events.lock().unwrap().push(progress)),
+        )
+        .expect("replication failed");
+
+        let events = events.into_inner().unwrap();
+        let labeled = events
+            .iter()
+            .filter(|e| match e {
+                Progress::WindowLayerLabeled { nodes: n, .. } => *n == nodes / 4,
+                _ => false,
+            })
+            .count();
+        assert_eq!(labeled, 4 * DEFAULT_STACKED_LAYERS);
+
+        for stage in &[Stage::TreeD, Stage::TreeQ, Stage::TreeRLast, Stage::TreeC] {
+            assert!(events.contains(&Progress::StageFinished { stage: *stage }));
+            assert!(events.iter().any(|e| match e {
+                Progress::TreeCompleted { stage: s, .. } => s == stage,
+                _ => false,
+            }));
+        }
+        assert!(events.contains(&Progress::StageStarted {
+            stage: Stage::Labeling,
+            steps: 4 * DEFAULT_STACKED_LAYERS,
+        }));
+    }
+
+    #[test]
+    fn replicate_from_source() {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let replica_id = <PedersenHasher as Hasher>::Domain::random(rng);
+        let nodes = 8 * 32;
+
+        // the source covers a window and a half, the rest of the sector is zeros
+        let source = random_data(rng, nodes / 4 + nodes / 8);
+        let mut data = source.clone();
+        data.resize(nodes * NODE_SIZE, 0);
+
+        let pp = four_window_public_params(nodes);
+
+        let replicate = |source: Option<&[u8]>, data: &mut [u8]| {
+            let (_cache_dir, config) = temp_store_config();
+
+            let (tau, _) = StackedDrg::<PedersenHasher, Blake2sHasher>::replicate_with_options(
+                &pp,
+                &replica_id,
+                source,
+                data,
+                None,
+                Some(config),
+                RunOptions::default(),
+            )
+            .expect("replication failed");
+
+            tau
+        };
+
+        // the replica starts out as garbage, which must be ignored
+        let mut replica = vec![0xff; nodes * NODE_SIZE];
+        let tau = replicate(Some(&source), &mut replica);
+        let tau_in_place = replicate(None, &mut data);
+
+        assert_eq!(tau, tau_in_place);
+        assert_eq!(replica, data);
+    }
+
+    #[test]
+    fn replicate_many_matches_replicate() {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let nodes = 8 * 32;
+
+        let pp = four_window_public_params(nodes);
+
+        let replica_ids: Vec<_> = (0..2)
+            .map(|_| <PedersenHasher as Hasher>::Domain::random(rng))
+            .collect();
+        let sources: Vec<Vec<u8>> = (0..2).map(|_| random_data(rng, nodes)).collect();
+        let (_cache_dirs, configs): (Vec<_>, Vec<_>) = (0..2).map(|_| temp_store_config()).unzip();
+
+        // the second replica is replicated in place
+        let mut replica = vec![0; nodes * NODE_SIZE];
+        let mut data = sources[1].clone();
+        let replicas = vec![
+            BatchReplica {
+                replica_id: replica_ids[0],
+                source: Some(&sources[0][..]),
+                data: &mut replica,
+                data_tree: None,
+                config: configs[0].clone(),
+            },
+            BatchReplica {
+                replica_id: replica_ids[1],
+                source: None,
+                data: &mut data,
+                data_tree: None,
+                config: configs[1].clone(),
+            },
+        ];
+        let taus: Vec<_> = StackedDrg::<PedersenHasher, Blake2sHasher>::replicate_many(
+            &pp,
+            replicas,
+            RunOptions::default(),
+        )
+        .expect("replication failed")
+        .into_iter()
+        .map(|(tau, _)| tau)
+        .collect();
+
+        for (i, (tau, replica)) in taus.into_iter().zip(vec![replica, data]).enumerate() {
+            let (_cache_dir, config) = temp_store_config();
+            let mut expected = sources[i].clone();
+            let (expected_tau, _) = StackedDrg::<PedersenHasher, Blake2sHasher>::replicate(
+                &pp,
+                &replica_ids[i],
+                &mut expected,
+                None,
+                Some(config),
+            )
+            .expect("replication failed");
+
+            assert_eq!(tau, expected_tau);
+            assert_eq!(replica, expected);
+        }
+    }
+
+    #[test]
+    fn cancelled_labeling_removes_artifacts() {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+        let replica_id = <PedersenHasher as Hasher>::Domain::random(rng);
+        let nodes = 8 * 32;
+
+        let mut data = random_data(rng, nodes);
+
+        let pp = four_window_public_params(nodes);
+
+        let (cache_dir, config) = temp_store_config();
+
+        // Cancel as soon as the first layer of a window has been labeled.
+        let cancel = CancellationToken::new();
+        let labeled = AtomicUsize::new(0);
+        let observer = |progress: Progress| {
+            if let Progress::WindowLayerLabeled { .. } = progress {
+                labeled.fetch_add(1, Ordering::SeqCst);
+                cancel.cancel();
+            }
+        };
+
+        let err = StackedDrg::<PedersenHasher, Blake2sHasher>::replicate_with_options(
+            &pp,
+            &replica_id,
+            None,
+            &mut data,
+            None,
+            Some(config),
+            RunOptions::default()
+                .with_progress(&observer)
+                .with_cancel(&cancel),
+        )
+        .err()
+        .expect("replication was not cancelled");
+
+        assert!(is_cancelled(&err));
+        assert!(labeled.load(Ordering::SeqCst) < 4 * DEFAULT_STACKED_LAYERS);
+        assert_eq!(std::fs::read_dir(cache_dir.path()).unwrap().count(), 0);
+    }
+
     #[test]
     #[ignore]
     fn extract_nodes_pedersen() {
@@ -1,9 +1,9 @@
 use anyhow::ensure;
 use log::trace;
-use rayon::prelude::*;
 
 use crate::error::Result;
 use crate::hasher::Hasher;
+use crate::par::*;
 use crate::proof::ProofScheme;
 use crate::stacked::{
     challenges::ChallengeRequirements,
@@ -0,0 +1,648 @@
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+use anyhow::{ensure, Context};
+use log::{info, trace, warn};
+use merkletree::merkle::FromIndexedParallelIterator;
+use merkletree::store::{DiskStore, StoreConfig};
+use paired::bls12_381::Fr;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::cancellation::is_cancelled;
+use crate::drgraph::Graph;
+use crate::encode::encode;
+use crate::error::Result;
+use crate::hasher::{Domain, Hasher};
+use crate::measurements::measure_op;
+use crate::measurements::Operation::{
+    CommD, EncodeWindowTimeAll, GenerateTreeC, GenerateTreeRLast, WindowCommLeavesTime,
+};
+use crate::merkle::{copy_zero_padded, create_merkle_tree_zero_padded, MerkleTree, Store};
+use crate::progress::{report_stage, Progress, ProgressObserver, RunOptions, Stage};
+use crate::stacked::{
+    graph::StackedBucketGraph,
+    hash::hash3,
+    params::{
+        get_node, BatchReplica, CacheKey, Labels, LabelsCache, PersistentAux, PublicParams, Tau,
+        TemporaryAux, TransformedLayers, Tree,
+    },
+    proof::{create_key, StackedDrg},
+};
+use crate::thread_pools::{self, Pool};
+use crate::util::{data_at_node, NODE_SIZE};
+
+impl<'a, H: 'static + Hasher, G: 'static + Hasher> StackedDrg<'a, H, G> {
+    /// Labels and encodes the windows of several replicas in lock-step, so that the parents of
+    /// each node are computed once for all of them. `sources` and `configs` hold the source data
+    /// (see `transform_and_replicate_layers`) and the store config of each replica in `data`.
+    #[allow(clippy::too_many_arguments)]
+    fn label_encode_all_windows(
+        pub_params: &PublicParams<H>,
+        replica_ids: &[<H as Hasher>::Domain],
+        sources: &[Option<&[u8]>],
+        data: &mut [&mut [u8]],
+        configs: &[StoreConfig],
+        options: RunOptions,
+    ) -> Result<Vec<(LabelsCache<H>, Labels<H>)>> {
+        trace!("encode_all_windows");
+        let progress = options.progress();
+        let window_graph = &pub_params.window_graph;
+        let layers = pub_params.config.layers();
+        let window_size_bytes = pub_params.window_size_bytes();
+
+        ensure!(
+            window_graph.size() == pub_params.window_size_nodes(),
+            "Invalid window size."
+        );
+
+        let num_windows = pub_params.num_windows();
+
+        let labels: Vec<Vec<Mutex<(DiskStore<_>, _)>>> = configs
+            .iter()
+            .zip(data.iter())
+            .map(|(config, data)| {
+                let layer_size = data.len();
+
+                (0..layers)
+                    .map(|layer| -> Result<_> {
+                        let layer_config = StoreConfig::from_config(
+                            config,
+                            CacheKey::label_layer(layer),
+                            Some(layer_size / NODE_SIZE),
+                        );
+
+                        let layer_store: DiskStore<H::Domain> = DiskStore::new_with_config(
+                            layer_size / NODE_SIZE,
+                            layer_config.clone(),
+                        )?;
+
+                        let r = Mutex::new((layer_store, layer_config));
+                        Ok(r)
+                    })
+                    .collect::<Result<_>>()
+            })
+            .collect::<Result<_>>()?;
+
+        // Group the chunks of all replicas by window.
+        let mut windows: Vec<Vec<&mut [u8]>> = (0..num_windows)
+            .map(|_| Vec::with_capacity(data.len()))
+            .collect();
+        for replica in data.iter_mut() {
+            for (window, data_chunk) in windows
+                .iter_mut()
+                .zip(replica.chunks_mut(window_size_bytes))
+            {
+                window.push(data_chunk);
+            }
+        }
+
+        thread_pools::install(Pool::Labeling, || {
+            windows.into_par_iter().enumerate().try_for_each(
+                |(window_index, data_chunks)| -> Result<()> {
+                    let mut states: Vec<_> = replica_ids
+                        .iter()
+                        .zip(sources)
+                        .zip(data_chunks)
+                        .map(|((replica_id, source), data_chunk)| {
+                            if let Some(source) = source {
+                                copy_zero_padded(
+                                    source,
+                                    window_index * window_size_bytes,
+                                    data_chunk,
+                                );
+                            }
+
+                            // setup hasher to reuse
+                            let mut base_hasher = Sha256::new();
+
+                            // hash replica id
+                            base_hasher.input(AsRef::<[u8]>::as_ref(replica_id));
+
+                            WindowLabels {
+                                base_hasher,
+                                layer_labels: vec![0u8; window_size_bytes],
+                                exp_parents_data: None,
+                                data_chunk,
+                            }
+                        })
+                        .collect();
+                    let mut parents = vec![0; window_graph.degree()];
+
+                    for layer in 1..=layers {
+                        options.check_cancelled()?;
+                        trace!("generating layer: {}", layer);
+
+                        Self::label_encode_window_layer(
+                            layer,
+                            layers,
+                            window_graph,
+                            &mut parents,
+                            &mut states,
+                            window_index,
+                        )?;
+
+                        for (state, labels) in states.iter_mut().zip(&labels) {
+                            if layer < layers {
+                                if let Some(ref mut exp_parents_data) = state.exp_parents_data {
+                                    exp_parents_data.copy_from_slice(&state.layer_labels);
+                                } else {
+                                    state.exp_parents_data = Some(state.layer_labels.clone());
+                                }
+                            }
+                            // write result to disk
+                            labels[layer - 1].lock().unwrap().0.copy_from_slice(
+                                &state.layer_labels,
+                                window_index * pub_params.window_size_nodes(),
+                            )?;
+                        }
+
+                        progress.on_progress(Progress::WindowLayerLabeled {
+                            window_index,
+                            layer,
+                            nodes: window_graph.size(),
+                        });
+                    }
+                    Ok(())
+                },
+            )
+        })?;
+
+        Ok(labels
+            .into_iter()
+            .map(|labels| {
+                let (labels, configs) = labels.into_iter().map(|v| v.into_inner().unwrap()).unzip();
+
+                (
+                    LabelsCache::<H>::from_stores(labels),
+                    Labels::<H>::new(configs),
+                )
+            })
+            .collect())
+    }
+
+    /// Labels a layer of the same window of each replica in `windows`, computing the parents of
+    /// each node once for all of them. On the last layer the data is encoded as well.
+    fn label_encode_window_layer(
+        layer: usize,
+        layers: usize,
+        window_graph: &StackedBucketGraph<H>,
+        parents: &mut [u32],
+        windows: &mut [WindowLabels<'_>],
+        window_index: usize,
+    ) -> Result<()> {
+        for node in 0..window_graph.size() {
+            window_graph.parents(node, parents)?;
+
+            let start = node * NODE_SIZE;
+            let end = (node + 1) * NODE_SIZE;
+
+            for window in windows.iter_mut() {
+                let key = create_key(
+                    window_graph,
+                    window.base_hasher.clone(),
+                    parents,
+                    window.exp_parents_data.as_ref(),
+                    &window.layer_labels,
+                    window_index,
+                    node,
+                )?;
+
+                // store the newly generated key
+                window.layer_labels[start..end].copy_from_slice(&key[..]);
+
+                if layer == layers {
+                    // on the last layer we encode the data
+                    let keyd = H::Domain::try_from_bytes(&key)?;
+                    let data_node = H::Domain::try_from_bytes(&window.data_chunk[start..end])?;
+                    let encoded_node = encode(keyd, data_node);
+                    window.data_chunk[start..end]
+                        .copy_from_slice(AsRef::<[u8]>::as_ref(&encoded_node));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn build_tree<K: Hasher>(tree_data: &[u8], config: Option<StoreConfig>) -> Result<Tree<K>> {
+        trace!("building tree (size: {})", tree_data.len());
+
+        let leafs = tree_data.len() / NODE_SIZE;
+        ensure!(tree_data.len() % NODE_SIZE == 0, "Invalid tree data.");
+        thread_pools::install(Pool::Merkle, || {
+            if let Some(config) = config {
+                MerkleTree::from_par_iter_with_config(
+                    (0..leafs)
+                        .into_par_iter()
+                        // TODO proper error handling instead of `unwrap()`
+                        .map(|i| get_node::<K>(tree_data, i).unwrap()),
+                    config,
+                )
+            } else {
+                MerkleTree::from_par_iter(
+                    (0..leafs)
+                        .into_par_iter()
+                        // TODO proper error handling instead of `unwrap()`
+                        .map(|i| get_node::<K>(tree_data, i).unwrap()),
+                )
+            }
+        })
+    }
+
+    fn build_column_hashes(
+        pub_params: &PublicParams<H>,
+        labels: &LabelsCache<H>,
+    ) -> Result<Vec<[u8; 32]>> {
+        (0..pub_params.window_size_nodes())
+            .into_par_iter()
+            .map(|i| Self::build_column_hash(pub_params, i, labels))
+            .collect()
+    }
+
+    fn build_column_hash(
+        pub_params: &PublicParams<H>,
+        column_index: usize,
+        labels: &LabelsCache<H>,
+    ) -> Result<[u8; 32]> {
+        let num_windows = pub_params.num_windows();
+        let layers = pub_params.config.layers();
+
+        let first_label = labels.labels_for_layer(1)?.read_at(column_index)?;
+        let mut hasher = crate::crypto::pedersen::Hasher::new(AsRef::<[u8]>::as_ref(&first_label))?;
+
+        for window_index in 0..num_windows {
+            for layer in 1..layers {
+                if window_index == 0 && layer == 1 {
+                    // first label
+                    continue;
+                }
+
+                let label = labels
+                    .labels_for_layer(layer)?
+                    .read_at(window_index * pub_params.window_size_nodes() + column_index)?;
+
+                hasher.update(AsRef::<[u8]>::as_ref(&label))?;
+            }
+        }
+
+        Ok(hasher.finalize_bytes())
+    }
+
+    /// Replicates `data`, removing the trees and labels written to the cache directory of
+    /// `config` if replication is cancelled.
+    ///
+    /// If `source` is given, the original data is read from it, zero padded to the length of
+    /// `data`, and `data` only receives the replica. Otherwise `data` is replicated in place.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn transform_and_replicate_layers(
+        pub_params: &PublicParams<H>,
+        replica_id: &<H as Hasher>::Domain,
+        source: Option<&[u8]>,
+        data: &mut [u8],
+        data_tree: Option<Tree<G>>,
+        config: Option<StoreConfig>,
+        options: RunOptions,
+    ) -> Result<TransformedLayers<H, G>> {
+        let replica = BatchReplica {
+            replica_id: *replica_id,
+            source,
+            data,
+            data_tree,
+            config: config.context("missing config")?,
+        };
+
+        let mut replicas =
+            Self::transform_and_replicate_layers_many(pub_params, vec![replica], options)?;
+
+        Ok(replicas.remove(0))
+    }
+
+    /// Replicates each of `replicas`, labeling them together. Removes the trees and labels
+    /// written to the cache directories of the replicas if replication is cancelled.
+    pub(crate) fn transform_and_replicate_layers_many(
+        pub_params: &PublicParams<H>,
+        replicas: Vec<BatchReplica<'_, H, G>>,
+        options: RunOptions,
+    ) -> Result<Vec<TransformedLayers<H, G>>> {
+        let configs: Vec<_> = replicas.iter().map(|r| r.config.clone()).collect();
+
+        let res = Self::transform_and_replicate_layers_inner(pub_params, replicas, options);
+
+        if let Err(err) = &res {
+            if is_cancelled(err) {
+                info!("replication cancelled, removing partial artifacts");
+                for config in &configs {
+                    let keys = (0..pub_params.config.layers())
+                        .map(CacheKey::label_layer)
+                        .chain(vec![
+                            CacheKey::CommDTree.to_string(),
+                            CacheKey::CommCTree.to_string(),
+                            CacheKey::CommQTree.to_string(),
+                            CacheKey::CommRLastTree.to_string(),
+                        ]);
+                    for key in keys {
+                        let path = StoreConfig::data_path(&config.path, &key);
+                        // a failure to remove one file must not hide the cancellation
+                        if path.exists() {
+                            if let Err(err) = std::fs::remove_file(&path) {
+                                warn!("could not remove {:?}: {}", path, err);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        res
+    }
+
+    fn transform_and_replicate_layers_inner(
+        pub_params: &PublicParams<H>,
+        replicas: Vec<BatchReplica<'_, H, G>>,
+        options: RunOptions,
+    ) -> Result<Vec<TransformedLayers<H, G>>> {
+        trace!("transform_and_replicate_layers");
+        let progress = options.progress();
+        let window_graph = &pub_params.window_graph;
+        let wrapper_graph = &pub_params.wrapper_graph;
+
+        ensure!(
+            window_graph.size() == pub_params.window_size_nodes(),
+            "Invalid window size."
+        );
+        ensure!(!replicas.is_empty(), "No replica found.");
+
+        let wrapper_nodes_count = wrapper_graph.size();
+        for replica in &replicas {
+            ensure!(
+                replica.data.len() == wrapper_nodes_count * NODE_SIZE,
+                "Invalid data size."
+            );
+            if let Some(source) = replica.source {
+                ensure!(
+                    source.len() <= replica.data.len(),
+                    "Invalid source data size."
+                );
+            }
+        }
+
+        let layers = pub_params.config.layers();
+        ensure!(layers > 0, "No layer found.");
+
+        let mut replica_ids = Vec::with_capacity(replicas.len());
+        let mut sources = Vec::with_capacity(replicas.len());
+        let mut data = Vec::with_capacity(replicas.len());
+        let mut configs = Vec::with_capacity(replicas.len());
+        let mut trees_d = Vec::with_capacity(replicas.len());
+
+        for replica in replicas {
+            let BatchReplica {
+                replica_id,
+                source,
+                data: replica_data,
+                data_tree,
+                config,
+            } = replica;
+            let tree_d_config =
+                StoreConfig::from_config(&config, CacheKey::CommDTree.to_string(), None);
+
+            // Build the MerkleTree over the original data (if needed).
+            let tree_d = measure_op(CommD, || match data_tree {
+                Some(t) => {
+                    trace!("using existing original data merkle tree");
+                    ensure!(
+                        t.len() == 2 * (replica_data.len() / NODE_SIZE) - 1,
+                        "Invalid data tree."
+                    );
+
+                    Ok(t)
+                }
+                None => {
+                    trace!("building merkle tree for the original data");
+                    let tree = report_stage(progress, Stage::TreeD, 0, || match source {
+                        Some(source) => create_merkle_tree_zero_padded::<G>(
+                            Some(tree_d_config.clone()),
+                            wrapper_nodes_count,
+                            source,
+                        ),
+                        None => Self::build_tree::<G>(&replica_data, Some(tree_d_config.clone())),
+                    })?;
+                    report_tree_completed(progress, Stage::TreeD, &tree);
+
+                    Ok(tree)
+                }
+            })?;
+
+            replica_ids.push(replica_id);
+            sources.push(source);
+            data.push(replica_data);
+            configs.push(config);
+            trees_d.push(tree_d);
+        }
+
+        info!(
+            "encoding {} windows of {} replicas",
+            pub_params.num_windows(),
+            data.len()
+        );
+
+        let labels = measure_op(EncodeWindowTimeAll, || {
+            report_stage(
+                progress,
+                Stage::Labeling,
+                pub_params.num_windows() * layers,
+                || {
+                    Self::label_encode_all_windows(
+                        pub_params,
+                        &replica_ids,
+                        &sources,
+                        &mut data,
+                        &configs,
+                        options,
+                    )
+                },
+            )
+        })?;
+
+        replica_ids
+            .iter()
+            .zip(data)
+            .zip(configs)
+            .zip(trees_d.into_iter().zip(labels))
+            .map(
+                |(((replica_id, data), config), (tree_d, (labels, label_configs)))| {
+                    Self::build_replica_trees(
+                        pub_params,
+                        replica_id,
+                        data,
+                        &config,
+                        tree_d,
+                        labels,
+                        label_configs,
+                        options,
+                    )
+                },
+            )
+            .collect()
+    }
+
+    /// Builds the column hashes and the trees of a labeled and encoded replica.
+    #[allow(clippy::too_many_arguments)]
+    fn build_replica_trees(
+        pub_params: &PublicParams<H>,
+        replica_id: &<H as Hasher>::Domain,
+        data: &[u8],
+        config: &StoreConfig,
+        tree_d: Tree<G>,
+        labels: LabelsCache<H>,
+        label_configs: Labels<H>,
+        options: RunOptions,
+    ) -> Result<TransformedLayers<H, G>> {
+        let progress = options.progress();
+        let wrapper_graph = &pub_params.wrapper_graph;
+        let wrapper_nodes_count = wrapper_graph.size();
+
+        // Generate all store configs that we need based on the
+        // cache_path in the specified config.
+        let mut tree_d_config =
+            StoreConfig::from_config(config, CacheKey::CommDTree.to_string(), None);
+        let mut tree_r_last_config =
+            StoreConfig::from_config(config, CacheKey::CommRLastTree.to_string(), None);
+        let mut tree_c_config =
+            StoreConfig::from_config(config, CacheKey::CommCTree.to_string(), None);
+        let mut tree_q_config =
+            StoreConfig::from_config(config, CacheKey::CommQTree.to_string(), None);
+
+        // construct column hashes
+        options.check_cancelled()?;
+        info!("building column hashes");
+        let column_hashes = measure_op(WindowCommLeavesTime, || {
+            report_stage(progress, Stage::ColumnHashes, 0, || {
+                thread_pools::install(Pool::ColumnHashes, || {
+                    Self::build_column_hashes(pub_params, &labels)
+                })
+            })
+        })?;
+
+        options.check_cancelled()?;
+        info!("building tree_q");
+        let tree_q: Tree<H> = report_stage(progress, Stage::TreeQ, 0, || {
+            Self::build_tree::<H>(&data, Some(tree_q_config.clone()))
+        })?;
+        report_tree_completed(progress, Stage::TreeQ, &tree_q);
+
+        options.check_cancelled()?;
+        info!("building tree_r_last");
+        progress.on_progress(Progress::StageStarted {
+            stage: Stage::TreeRLast,
+            steps: 0,
+        });
+        let tree_r_last: Tree<H> = measure_op(GenerateTreeRLast, || {
+            thread_pools::install(Pool::Merkle, || {
+                MerkleTree::from_par_iter_with_config(
+                    (0..wrapper_nodes_count).into_par_iter().map(|node| {
+                        // 1 Wrapping Layer
+
+                        let mut hasher = Sha256::new();
+                        hasher.input(AsRef::<[u8]>::as_ref(replica_id));
+                        hasher.input(&(node as u64).to_be_bytes()[..]);
+
+                        // Only expansion parents
+                        let mut exp_parents = vec![0; wrapper_graph.expansion_degree()];
+                        // TODO Do proper error handling and not just `expect()`.
+                        wrapper_graph
+                            .expanded_parents(node, &mut exp_parents)
+                            .expect("cannot expand parents");
+
+                        let wrapper_layer = &data;
+                        for parent in &exp_parents {
+                            // TODO Do proper error handling and not just `expect()`.
+                            hasher.input(
+                                data_at_node(wrapper_layer, *parent as usize)
+                                    .expect("invalid node math"),
+                            );
+                        }
+
+                        // finalize key
+                        let mut val = hasher.result();
+                        // strip last two bits, to ensure result is in Fr.
+                        val[31] &= 0b0011_1111;
+
+                        // TODO Do proper error handling and not just `expect()`.
+                        H::Domain::try_from_bytes(&val).expect("invalid node created")
+                    }),
+                    tree_r_last_config.clone(),
+                )
+            })
+        })?;
+        progress.on_progress(Progress::StageFinished {
+            stage: Stage::TreeRLast,
+        });
+        report_tree_completed(progress, Stage::TreeRLast, &tree_r_last);
+
+        options.check_cancelled()?;
+        let tree_c = measure_op(GenerateTreeC, || {
+            let column_hashes_flat = unsafe {
+                // Column_hashes is of type Vec<[u8; 32]>, so this is safe to do.
+                // We do this to avoid unnecessary allocations.
+                std::slice::from_raw_parts(
+                    column_hashes.as_ptr() as *const _,
+                    column_hashes.len() * 32,
+                )
+            };
+            report_stage(progress, Stage::TreeC, 0, || {
+                Self::build_tree::<H>(column_hashes_flat, Some(tree_c_config.clone()))
+            })
+        })?;
+        report_tree_completed(progress, Stage::TreeC, &tree_c);
+
+        // comm_r = H(comm_c || comm_q || comm_r_last)
+        let comm_r: H::Domain =
+            Fr::from(hash3(tree_c.root(), tree_q.root(), tree_r_last.root())).into();
+
+        ensure!(tree_d.len() == tree_r_last.len(), "Invalid tree_r.");
+        ensure!(tree_d.len() == tree_q.len(), "Invlaid tree_q.");
+
+        tree_d_config.size = Some(tree_d.len());
+        tree_r_last_config.size = Some(tree_r_last.len());
+        tree_c_config.size = Some(tree_c.len());
+        tree_q_config.size = Some(tree_q.len());
+
+        Ok((
+            Tau {
+                comm_d: tree_d.root(),
+                comm_r,
+            },
+            PersistentAux {
+                comm_c: tree_c.root(),
+                comm_q: tree_q.root(),
+                comm_r_last: tree_r_last.root(),
+            },
+            TemporaryAux {
+                labels: label_configs,
+                tree_d_config,
+                tree_r_last_config,
+                tree_c_config,
+                tree_q_config,
+                _g: PhantomData,
+            },
+        ))
+    }
+}
+
+/// The labeling state of a window of one replica, see `label_encode_window_layer`.
+struct WindowLabels<'d> {
+    base_hasher: Sha256,
+    layer_labels: Vec<u8>,
+    exp_parents_data: Option<Vec<u8>>,
+    data_chunk: &'d mut [u8],
+}
+
+fn report_tree_completed<K: Hasher>(progress: &dyn ProgressObserver, stage: Stage, tree: &Tree<K>) {
+    progress.on_progress(Progress::TreeCompleted {
+        stage,
+        leaves: tree.leafs(),
+        height: tree.height(),
+    });
+}
@@ -0,0 +1,423 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+
+use anyhow::{bail, ensure, Context};
+use lazy_static::lazy_static;
+use log::warn;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::error::Result;
+use crate::settings::{Settings, SETTINGS};
+
+/// The stages of sealing and proving which can run on their own thread pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pool {
+    /// Labeling and encoding the windows.
+    Labeling,
+    /// Building tree_d, tree_q, tree_r_last and tree_c.
+    Merkle,
+    /// Hashing the label columns.
+    ColumnHashes,
+    /// Generating the circuit proofs of the partitions.
+    Proving,
+    /// Loading the trees and reading the replicas challenged by PoSt.
+    PostReads,
+}
+
+/// The size of a thread pool. A pool of the settings with neither threads nor cores is not built,
+/// its stage running on the global rayon pool instead. Pools given to `with_thread_pools` are
+/// always built, see `ThreadPools::new`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolConfig {
+    /// Number of threads, zero meaning one thread per core in `cores`.
+    pub num_threads: usize,
+    /// Cores the threads are pinned to, round robin. Empty for no pinning.
+    pub cores: Vec<usize>,
+}
+
+impl PoolConfig {
+    pub fn new(num_threads: usize) -> Self {
+        PoolConfig {
+            num_threads,
+            cores: Vec::new(),
+        }
+    }
+
+    /// Parses a pool from a thread count and a list of cores such as `"0-3,8,10-11"`, which must
+    /// all exist on this host.
+    pub fn parse(num_threads: usize, cores: &str) -> Result<Self> {
+        let cores = parse_core_ranges(cores)?;
+        if !cores.is_empty() {
+            match &*AVAILABLE_CORES {
+                Some(available) => check_cores(&cores, available)?,
+                None => bail!("pinning threads to cores is not supported on this platform"),
+            }
+        }
+
+        Ok(PoolConfig { num_threads, cores })
+    }
+
+    /// Number of threads of the pool, `None` if it is not built and its stage runs on the
+    /// global rayon pool.
+    pub fn threads(&self) -> Option<usize> {
+        match (self.num_threads, self.cores.len()) {
+            (0, 0) => None,
+            (0, cores) => Some(cores),
+            (num_threads, _) => Some(num_threads),
+        }
+    }
+
+    /// Builds the pool, unless it has no size and `always` is not set. A pool without a size has
+    /// the default number of threads of rayon.
+    fn build(&self, pool: Pool, owner: Owner, always: bool) -> Result<Option<ThreadPool>> {
+        let num_threads = match self.threads() {
+            Some(num_threads) => num_threads,
+            None if always => 0,
+            None => return Ok(None),
+        };
+
+        let cores = self.cores.clone();
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(move |index| format!("{:?}-{}", pool, index).to_lowercase())
+            .start_handler(move |index| {
+                if !cores.is_empty() {
+                    pin_current_thread(pool, cores[index % cores.len()]);
+                }
+                WORKER_POOLS.with(|worker| worker.replace(Some(owner.clone())));
+            })
+            .build()
+            .with_context(|| format!("failed to build the {:?} thread pool", pool))?;
+
+        Ok(Some(pool))
+    }
+}
+
+/// Parses a list of cores such as `"0-3,8,10-11"`.
+fn parse_core_ranges(cores: &str) -> Result<Vec<usize>> {
+    let mut parsed = Vec::new();
+    for range in cores.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+        let mut bounds = range.splitn(2, '-');
+        let start: usize = bounds
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid core range {:?}", range))?;
+        let end: usize = match bounds.next() {
+            Some(end) => end
+                .trim()
+                .parse()
+                .with_context(|| format!("invalid core range {:?}", range))?,
+            None => start,
+        };
+        ensure!(start <= end, "invalid core range {:?}", range);
+        parsed.extend(start..=end);
+    }
+
+    Ok(parsed)
+}
+
+/// Fails if a core of `cores` is not in `available`.
+fn check_cores(cores: &[usize], available: &[usize]) -> Result<()> {
+    for core in cores {
+        ensure!(
+            available.contains(core),
+            "unknown core {}, the cores of this host are {:?}",
+            core,
+            available
+        );
+    }
+
+    Ok(())
+}
+
+/// Pins the calling thread to core `id`, logging a warning if that fails.
+fn pin_current_thread(pool: Pool, id: usize) {
+    core_affinity::set_for_current(core_affinity::CoreId { id });
+
+    // `set_for_current` does not report failures; on Linux the cores of the calling thread are
+    // those of its affinity mask, which shows whether the pinning took effect
+    if cfg!(target_os = "linux") {
+        let pinned = core_affinity::get_core_ids()
+            .map(|ids| ids.len() == 1 && ids[0].id == id)
+            .unwrap_or(false);
+        if !pinned {
+            warn!("failed to pin a {:?} thread to core {}", pool, id);
+        }
+    }
+}
+
+/// The pool of each stage, see `ThreadPools`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThreadPoolsConfig {
+    pub labeling: PoolConfig,
+    pub merkle: PoolConfig,
+    pub column_hashes: PoolConfig,
+    pub proving: PoolConfig,
+    pub post_reads: PoolConfig,
+}
+
+impl ThreadPoolsConfig {
+    /// Reads the pools from the `*_threads` and `*_cores` settings.
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        Ok(ThreadPoolsConfig {
+            labeling: PoolConfig::parse(settings.labeling_threads, &settings.labeling_cores)?,
+            merkle: PoolConfig::parse(settings.merkle_threads, &settings.merkle_cores)?,
+            column_hashes: PoolConfig::parse(
+                settings.column_hash_threads,
+                &settings.column_hash_cores,
+            )?,
+            proving: PoolConfig::parse(settings.num_proving_threads, &settings.proving_cores)?,
+            post_reads: PoolConfig::parse(settings.post_read_threads, &settings.post_read_cores)?,
+        })
+    }
+}
+
+type PoolMap = HashMap<Pool, Arc<ThreadPool>>;
+
+/// The pools a worker thread belongs to, set once they are all built. Weak, as the workers are
+/// owned by the pools.
+type Owner = Arc<Mutex<Weak<PoolMap>>>;
+
+/// A thread pool per stage. Stages without a pool run on the global rayon pool.
+#[derive(Debug, Clone, Default)]
+pub struct ThreadPools {
+    pools: Arc<PoolMap>,
+}
+
+impl ThreadPools {
+    /// Builds a pool for every stage, to be given to `with_thread_pools`. The stages `config`
+    /// gives no size have the default number of threads of rayon. As no stage runs on the global
+    /// rayon pool, the stages started from any stage use these pools as well.
+    pub fn new(config: &ThreadPoolsConfig) -> Result<Self> {
+        Self::build(config, true)
+    }
+
+    /// Builds the pools of the settings, where stages without a size run on the global pool.
+    fn from_settings(config: &ThreadPoolsConfig) -> Result<Self> {
+        Self::build(config, false)
+    }
+
+    fn build(config: &ThreadPoolsConfig, always: bool) -> Result<Self> {
+        let configs = vec![
+            (Pool::Labeling, &config.labeling),
+            (Pool::Merkle, &config.merkle),
+            (Pool::ColumnHashes, &config.column_hashes),
+            (Pool::Proving, &config.proving),
+            (Pool::PostReads, &config.post_reads),
+        ];
+
+        let owner = Owner::default();
+        let mut pools = HashMap::new();
+        for (pool, config) in configs {
+            if let Some(thread_pool) = config.build(pool, owner.clone(), always)? {
+                pools.insert(pool, Arc::new(thread_pool));
+            }
+        }
+
+        let pools = Arc::new(pools);
+        *owner.lock().unwrap() = Arc::downgrade(&pools);
+
+        Ok(ThreadPools { pools })
+    }
+
+    /// Runs `op` on the thread pool of `pool`, or on the current one if it has none.
+    pub fn install<OP, R>(&self, pool: Pool, op: OP) -> R
+    where
+        OP: FnOnce() -> R + Send,
+        R: Send,
+    {
+        match self.pools.get(&pool) {
+            Some(thread_pool) => thread_pool.install(op),
+            None => op(),
+        }
+    }
+}
+
+lazy_static! {
+    static ref SETTINGS_POOLS: Result<ThreadPools> =
+        ThreadPoolsConfig::from_settings(&SETTINGS.lock().unwrap())
+            .and_then(|config| ThreadPools::from_settings(&config));
+    /// The cores threads can be pinned to, `None` where pinning is not supported.
+    static ref AVAILABLE_CORES: Option<Vec<usize>> = core_affinity::get_core_ids()
+        .map(|ids| ids.into_iter().map(|core| core.id).collect());
+}
+
+thread_local! {
+    /// The pools given to `with_thread_pools` on this thread.
+    static CURRENT_POOLS: RefCell<Option<ThreadPools>> = RefCell::new(None);
+    /// The pools this thread is a worker of.
+    static WORKER_POOLS: RefCell<Option<Owner>> = RefCell::new(None);
+}
+
+/// Puts back the pools replaced by `with_thread_pools`, also when `f` panics.
+struct RestorePools(Option<ThreadPools>);
+
+impl Drop for RestorePools {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        let _ = CURRENT_POOLS.try_with(|current| current.replace(previous));
+    }
+}
+
+/// Runs `f` with `pools` in place of the pools configured in the settings, for the stages
+/// started from the calling thread. This is how a single sealing or proving call is given its own
+/// pools, e.g. to keep several jobs on one host from sharing cores:
+///
+/// ```ignore
+/// let pools = ThreadPools::new(&config)?;
+/// with_thread_pools(&pools, || seal_pre_commit(...))?;
+/// ```
+///
+/// Stages started from the workers of `pools` use `pools` as well, which has a pool for every
+/// stage. Only stages started from rayon tasks that `f` runs outside of any stage, on the global
+/// rayon pool, use the pools of the settings.
+pub fn with_thread_pools<F, R>(pools: &ThreadPools, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let previous = CURRENT_POOLS.with(|current| current.replace(Some(pools.clone())));
+    let _restore = RestorePools(previous);
+
+    f()
+}
+
+/// The pools of the calling thread: those given to `with_thread_pools`, those it is a worker of,
+/// or otherwise those configured in the settings.
+fn current_pools() -> Result<ThreadPools> {
+    if let Some(pools) = CURRENT_POOLS.with(|current| current.borrow().clone()) {
+        return Ok(pools);
+    }
+
+    let worker_pools = WORKER_POOLS.with(|worker| {
+        worker
+            .borrow()
+            .as_ref()
+            .and_then(|owner| owner.lock().unwrap().upgrade())
+    });
+    if let Some(pools) = worker_pools {
+        return Ok(ThreadPools { pools });
+    }
+
+    match &*SETTINGS_POOLS {
+        Ok(pools) => Ok(pools.clone()),
+        Err(err) => bail!("invalid thread pool settings: {:#}", err),
+    }
+}
+
+/// Runs `op` on the thread pool of `pool`, as given to `with_thread_pools` or otherwise
+/// configured in the settings. Fails if the pools of the settings are invalid.
+pub fn install<OP, R>(pool: Pool, op: OP) -> Result<R>
+where
+    OP: FnOnce() -> Result<R> + Send,
+    R: Send,
+{
+    current_pools()?.install(pool, op)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cores() {
+        assert_eq!(PoolConfig::parse(0, "").unwrap(), PoolConfig::new(0));
+        assert_eq!(
+            parse_core_ranges("0-3, 8,10-11").unwrap(),
+            vec![0, 1, 2, 3, 8, 10, 11]
+        );
+        assert!(PoolConfig::parse(0, "3-1").is_err());
+        assert!(PoolConfig::parse(0, "a").is_err());
+
+        assert!(check_cores(&[0, 2], &[0, 1, 2, 3]).is_ok());
+        assert!(check_cores(&[0, 4], &[0, 1, 2, 3]).is_err());
+        assert!(PoolConfig::parse(0, &usize::max_value().to_string()).is_err());
+
+        let pinned = |num_threads, cores: Vec<usize>| PoolConfig { num_threads, cores };
+        assert_eq!(PoolConfig::new(0).threads(), None);
+        assert_eq!(pinned(0, vec![0, 1, 2, 3]).threads(), Some(4));
+        assert_eq!(pinned(2, vec![0, 1, 2, 3]).threads(), Some(2));
+    }
+
+    fn on_global_pool() -> Result<bool> {
+        Ok(rayon::current_thread_index().is_none())
+    }
+
+    #[test]
+    fn test_with_thread_pools() {
+        let pools = ThreadPools::new(&ThreadPoolsConfig {
+            labeling: PoolConfig::new(3),
+            merkle: PoolConfig::new(2),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let threads = with_thread_pools(&pools, || -> Result<_> {
+            Ok((
+                install(Pool::Labeling, || Ok(rayon::current_num_threads()))?,
+                install(Pool::Proving, on_global_pool)?,
+            ))
+        })
+        .unwrap();
+        // stages without a size get a pool of their own as well
+        assert_eq!(threads, (3, false));
+
+        // the pools are only used inside `with_thread_pools`
+        assert!(install(Pool::Labeling, on_global_pool).unwrap());
+    }
+
+    #[test]
+    fn test_thread_pools_nested_in_workers() {
+        use rayon::prelude::*;
+
+        let pools = ThreadPools::new(&ThreadPoolsConfig {
+            labeling: PoolConfig::new(3),
+            merkle: PoolConfig::new(2),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // stages started from the tasks of another stage, on other workers than the one
+        // `with_thread_pools` handed the work to, still run on `pools`
+        let threads: Vec<usize> = with_thread_pools(&pools, || {
+            install(Pool::Labeling, || {
+                (0..16)
+                    .into_par_iter()
+                    .map(|_| install(Pool::Merkle, || Ok(rayon::current_num_threads())))
+                    .collect()
+            })
+        })
+        .unwrap();
+        assert_eq!(threads, vec![2; 16]);
+
+        // including from the tasks of a stage without a size
+        let threads: Vec<usize> = with_thread_pools(&pools, || {
+            install(Pool::Proving, || {
+                (0..16)
+                    .into_par_iter()
+                    .map(|_| install(Pool::Labeling, || Ok(rayon::current_num_threads())))
+                    .collect()
+            })
+        })
+        .unwrap();
+        assert_eq!(threads, vec![3; 16]);
+    }
+
+    #[test]
+    fn test_with_thread_pools_restores_on_panic() {
+        let pools = ThreadPools::new(&ThreadPoolsConfig {
+            labeling: PoolConfig::new(3),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            with_thread_pools(&pools, || panic!("stage failed"));
+        }));
+        assert!(res.is_err());
+
+        assert!(install(Pool::Labeling, on_global_pool).unwrap());
+    }
+}
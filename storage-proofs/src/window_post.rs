@@ -0,0 +1,498 @@
+use std::marker::PhantomData;
+
+use anyhow::ensure;
+use byteorder::{ByteOrder, LittleEndian};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::drgraph::graph_height;
+use crate::error::{Error, Result};
+use crate::hasher::{Domain, Hasher};
+use crate::merkle::{MerkleProof, MerkleTree};
+use crate::parameter_cache::ParameterSetMetadata;
+use crate::proof::{NoRequirements, ProofScheme};
+use crate::sector::*;
+use crate::stacked::hash::hash3;
+use crate::util::NODE_SIZE;
+
+#[derive(Debug, Clone)]
+pub struct SetupParams {
+    /// Size of the sector in bytes.
+    pub sector_size: u64,
+    /// How many leafs are challenged in every sector.
+    pub challenge_count: usize,
+    /// How many sectors are proven in a single partition.
+    pub sector_count: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct PublicParams {
+    /// Size of the sector in bytes.
+    pub sector_size: u64,
+    /// How many leafs are challenged in every sector.
+    pub challenge_count: usize,
+    /// How many sectors are proven in a single partition.
+    pub sector_count: usize,
+}
+
+impl ParameterSetMetadata for PublicParams {
+    fn identifier(&self) -> String {
+        format!(
+            "WindowPoSt::PublicParams{{sector_size: {}, challenge_count: {}, sector_count: {}}}",
+            self.sector_size(),
+            self.challenge_count,
+            self.sector_count,
+        )
+    }
+
+    fn sector_size(&self) -> u64 {
+        self.sector_size
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PublicSector<T: Domain> {
+    pub id: SectorId,
+    pub comm_r: T,
+}
+
+#[derive(Debug, Clone)]
+pub struct PublicInputs<T: Domain> {
+    pub randomness: [u8; 32],
+    /// All proven sectors, split into partitions of `sector_count` sectors.
+    pub sectors: Vec<PublicSector<T>>,
+    /// Partition index
+    pub k: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct PrivateSector<'a, H: 'a + Hasher> {
+    pub tree: &'a MerkleTree<H::Domain, H::Function>,
+    pub comm_c: H::Domain,
+    pub comm_q: H::Domain,
+}
+
+#[derive(Debug)]
+pub struct PrivateInputs<'a, H: 'a + Hasher> {
+    /// The private data of the proven sectors, in the same order as `PublicInputs::sectors`.
+    pub sectors: &'a [PrivateSector<'a, H>],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectorProof<H: Hasher> {
+    #[serde(bound(
+        serialize = "MerkleProof<H>: Serialize",
+        deserialize = "MerkleProof<H>: Deserialize<'de>"
+    ))]
+    inclusion_proofs: Vec<MerkleProof<H>>,
+    pub comm_c: H::Domain,
+    pub comm_q: H::Domain,
+}
+
+impl<H: Hasher> SectorProof<H> {
+    pub fn leafs(&self) -> Vec<&H::Domain> {
+        self.inclusion_proofs
+            .iter()
+            .map(MerkleProof::leaf)
+            .collect()
+    }
+
+    pub fn comm_r_last(&self) -> H::Domain {
+        *self.inclusion_proofs[0].root()
+    }
+
+    pub fn paths(&self) -> Vec<&Vec<(H::Domain, bool)>> {
+        self.inclusion_proofs
+            .iter()
+            .map(MerkleProof::path)
+            .collect()
+    }
+}
+
+/// The proof of a single partition.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Proof<H: Hasher> {
+    #[serde(bound(
+        serialize = "SectorProof<H>: Serialize",
+        deserialize = "SectorProof<H>: Deserialize<'de>"
+    ))]
+    pub sectors: Vec<SectorProof<H>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WindowPoSt<'a, H>
+where
+    H: 'a + Hasher,
+{
+    _h: PhantomData<&'a H>,
+}
+
+/// The number of partitions needed to prove `sector_total` sectors.
+pub fn partition_count(pub_params: &PublicParams, sector_total: usize) -> usize {
+    (sector_total + pub_params.sector_count - 1) / pub_params.sector_count
+}
+
+/// Indices into the proven sectors which are covered by partition `k`. The last partition is
+/// padded by repeating its last sector, so that every partition proves `sector_count` sectors.
+pub fn partition_sector_indices(
+    pub_params: &PublicParams,
+    sector_total: usize,
+    k: usize,
+) -> Result<Vec<usize>> {
+    ensure!(pub_params.sector_count > 0, "sector_count must not be zero");
+    ensure!(sector_total > 0, "no sectors to prove");
+
+    let start = k * pub_params.sector_count;
+    ensure!(
+        start < sector_total,
+        "partition {} is out of range for {} sectors",
+        k,
+        sector_total
+    );
+
+    let end = std::cmp::min(start + pub_params.sector_count, sector_total);
+    let mut indices: Vec<usize> = (start..end).collect();
+    indices.resize(pub_params.sector_count, end - 1);
+
+    Ok(indices)
+}
+
+/// Generates the leaf challenges for a single sector.
+pub fn generate_leaf_challenges(
+    pub_params: &PublicParams,
+    randomness: &[u8; 32],
+    sector_id: SectorId,
+) -> Vec<u64> {
+    (0..pub_params.challenge_count)
+        .map(|n| generate_leaf_challenge(pub_params, randomness, sector_id, n as u64))
+        .collect()
+}
+
+/// Generates the `leaf_challenge_index`th challenged leaf of the given sector.
+pub fn generate_leaf_challenge(
+    pub_params: &PublicParams,
+    randomness: &[u8; 32],
+    sector_id: SectorId,
+    leaf_challenge_index: u64,
+) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.input(&randomness[..]);
+    hasher.input(&u64::from(sector_id).to_le_bytes()[..]);
+    hasher.input(&leaf_challenge_index.to_le_bytes()[..]);
+    let hash = hasher.result();
+
+    let leaf_challenge = LittleEndian::read_u64(&hash.as_ref()[..8]);
+
+    leaf_challenge % (pub_params.sector_size / NODE_SIZE as u64)
+}
+
+impl<'a, H: 'a + Hasher> ProofScheme<'a> for WindowPoSt<'a, H> {
+    type PublicParams = PublicParams;
+    type SetupParams = SetupParams;
+    type PublicInputs = PublicInputs<H::Domain>;
+    type PrivateInputs = PrivateInputs<'a, H>;
+    type Proof = Proof<H>;
+    type Requirements = NoRequirements;
+
+    fn setup(sp: &Self::SetupParams) -> Result<Self::PublicParams> {
+        ensure!(sp.sector_count > 0, "sector_count must not be zero");
+        ensure!(sp.challenge_count > 0, "challenge_count must not be zero");
+
+        Ok(PublicParams {
+            sector_size: sp.sector_size,
+            challenge_count: sp.challenge_count,
+            sector_count: sp.sector_count,
+        })
+    }
+
+    fn prove<'b>(
+        pub_params: &'b Self::PublicParams,
+        pub_inputs: &'b Self::PublicInputs,
+        priv_inputs: &'b Self::PrivateInputs,
+    ) -> Result<Self::Proof> {
+        ensure!(
+            pub_inputs.sectors.len() == priv_inputs.sectors.len(),
+            "mismatched public and private sectors"
+        );
+
+        let indices = partition_sector_indices(
+            pub_params,
+            pub_inputs.sectors.len(),
+            pub_inputs.k.unwrap_or(0),
+        )?;
+
+        let sectors = indices
+            .into_iter()
+            .map(|i| {
+                let pub_sector = &pub_inputs.sectors[i];
+                let priv_sector = &priv_inputs.sectors[i];
+                let tree = priv_sector.tree;
+
+                ensure!(
+                    AsRef::<[u8]>::as_ref(&hash3(
+                        priv_sector.comm_c,
+                        priv_sector.comm_q,
+                        tree.root()
+                    )) == AsRef::<[u8]>::as_ref(&pub_sector.comm_r),
+                    Error::InvalidCommitment
+                );
+
+                let inclusion_proofs =
+                    generate_leaf_challenges(pub_params, &pub_inputs.randomness, pub_sector.id)
+                        .into_iter()
+                        .map(|challenge| {
+                            Ok(MerkleProof::new_from_proof(
+                                &tree.gen_proof(challenge as usize)?,
+                            ))
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+
+                Ok(SectorProof {
+                    inclusion_proofs,
+                    comm_c: priv_sector.comm_c,
+                    comm_q: priv_sector.comm_q,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Proof { sectors })
+    }
+
+    fn verify(
+        pub_params: &Self::PublicParams,
+        pub_inputs: &Self::PublicInputs,
+        proof: &Self::Proof,
+    ) -> Result<bool> {
+        ensure!(
+            pub_params.challenge_count > 0,
+            "challenge_count must not be zero"
+        );
+
+        let indices = partition_sector_indices(
+            pub_params,
+            pub_inputs.sectors.len(),
+            pub_inputs.k.unwrap_or(0),
+        )?;
+
+        ensure!(indices.len() == proof.sectors.len(), Error::MalformedInput);
+
+        for (i, sector_proof) in indices.into_iter().zip(proof.sectors.iter()) {
+            let pub_sector = &pub_inputs.sectors[i];
+
+            // there is at least one inclusion proof, as challenge_count is not zero
+            if sector_proof.inclusion_proofs.len() != pub_params.challenge_count {
+                return Ok(false);
+            }
+
+            // verify that H(Comm_c || comm_q || Comm_r_last) == Comm_R
+            // comm_r_last is the root of the proof
+            let comm_r_last = sector_proof.inclusion_proofs[0].root();
+
+            if AsRef::<[u8]>::as_ref(&hash3(
+                sector_proof.comm_c,
+                sector_proof.comm_q,
+                comm_r_last,
+            )) != AsRef::<[u8]>::as_ref(&pub_sector.comm_r)
+            {
+                return Ok(false);
+            }
+
+            let challenges =
+                generate_leaf_challenges(pub_params, &pub_inputs.randomness, pub_sector.id);
+
+            for (merkle_proof, challenge) in
+                sector_proof.inclusion_proofs.iter().zip(challenges.iter())
+            {
+                // validate all comm_r_lasts match
+                if merkle_proof.root() != comm_r_last {
+                    return Ok(false);
+                }
+
+                // validate the path length
+                if graph_height(pub_params.sector_size as usize / NODE_SIZE)
+                    != merkle_proof.path().len()
+                {
+                    return Ok(false);
+                }
+
+                if !merkle_proof.validate(*challenge as usize) {
+                    return Ok(false);
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn with_partition(pub_in: Self::PublicInputs, k: Option<usize>) -> Self::PublicInputs {
+        PublicInputs {
+            randomness: pub_in.randomness,
+            sectors: pub_in.sectors,
+            k,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ff::Field;
+    use paired::bls12_381::{Bls12, Fr};
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use crate::drgraph::{new_seed, BucketGraph, Graph, BASE_DEGREE};
+    use crate::fr32::fr_into_bytes;
+    use crate::hasher::{Blake2sHasher, PedersenHasher, Sha256Hasher};
+
+    #[test]
+    fn test_partition_sector_indices() {
+        let pub_params = PublicParams {
+            sector_size: 1024,
+            challenge_count: 2,
+            sector_count: 3,
+        };
+
+        assert_eq!(partition_count(&pub_params, 1), 1);
+        assert_eq!(partition_count(&pub_params, 3), 1);
+        assert_eq!(partition_count(&pub_params, 7), 3);
+
+        assert_eq!(
+            partition_sector_indices(&pub_params, 7, 0).unwrap(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(
+            partition_sector_indices(&pub_params, 7, 2).unwrap(),
+            vec![6, 6, 6]
+        );
+        assert_eq!(
+            partition_sector_indices(&pub_params, 5, 1).unwrap(),
+            vec![3, 4, 4]
+        );
+        assert!(partition_sector_indices(&pub_params, 6, 2).is_err());
+        assert!(partition_sector_indices(&pub_params, 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_zero_challenges() {
+        let setup_params = SetupParams {
+            sector_size: 1024,
+            challenge_count: 0,
+            sector_count: 1,
+        };
+        assert!(WindowPoSt::<PedersenHasher>::setup(&setup_params).is_err());
+
+        // a sector proof without inclusion proofs is rejected rather than indexed
+        let pub_params = PublicParams {
+            sector_size: 1024,
+            challenge_count: 0,
+            sector_count: 1,
+        };
+        let pub_inputs = PublicInputs {
+            randomness: [0; 32],
+            sectors: vec![PublicSector {
+                id: SectorId::from(0),
+                comm_r: Default::default(),
+            }],
+            k: None,
+        };
+        let proof = Proof::<PedersenHasher> {
+            sectors: vec![SectorProof {
+                inclusion_proofs: Vec::new(),
+                comm_c: Default::default(),
+                comm_q: Default::default(),
+            }],
+        };
+        assert!(WindowPoSt::<PedersenHasher>::verify(&pub_params, &pub_inputs, &proof).is_err());
+    }
+
+    fn test_window_post<H: Hasher>() {
+        let rng = &mut XorShiftRng::from_seed(crate::TEST_SEED);
+
+        let leaves = 32;
+        let sector_size = leaves * 32;
+
+        let pub_params = PublicParams {
+            sector_size,
+            challenge_count: 10,
+            sector_count: 2,
+        };
+
+        let randomness: [u8; 32] = rng.gen();
+
+        let mut trees = Vec::new();
+        for _ in 0..5 {
+            let data: Vec<u8> = (0..leaves)
+                .flat_map(|_| fr_into_bytes::<Bls12>(&Fr::random(rng)))
+                .collect();
+
+            let graph = BucketGraph::<H>::new(32, BASE_DEGREE, 0, new_seed()).unwrap();
+            trees.push(graph.merkle_tree(data.as_slice()).unwrap());
+        }
+
+        let mut pub_sectors = Vec::new();
+        let mut priv_sectors = Vec::new();
+        for (i, tree) in trees.iter().enumerate() {
+            let comm_c = H::Domain::random(rng);
+            let comm_q = H::Domain::random(rng);
+            let comm_r = Fr::from(hash3(comm_c, comm_q, tree.root())).into();
+
+            pub_sectors.push(PublicSector {
+                id: (i as u64 * 3).into(),
+                comm_r,
+            });
+            priv_sectors.push(PrivateSector::<H> {
+                tree,
+                comm_c,
+                comm_q,
+            });
+        }
+
+        let pub_inputs = PublicInputs {
+            randomness,
+            sectors: pub_sectors,
+            k: None,
+        };
+
+        let priv_inputs = PrivateInputs::<H> {
+            sectors: &priv_sectors,
+        };
+
+        let partitions = partition_count(&pub_params, pub_inputs.sectors.len());
+        assert_eq!(partitions, 3);
+
+        let proofs = WindowPoSt::<H>::prove_all_partitions(
+            &pub_params,
+            &pub_inputs,
+            &priv_inputs,
+            partitions,
+        )
+        .expect("proving failed");
+        assert_eq!(proofs.len(), partitions);
+
+        let is_valid = WindowPoSt::<H>::verify_all_partitions(&pub_params, &pub_inputs, &proofs)
+            .expect("verification failed");
+        assert!(is_valid);
+
+        // A proof of one partition does not verify for another.
+        let swapped = vec![proofs[1].clone(), proofs[0].clone(), proofs[2].clone()];
+        let is_valid = WindowPoSt::<H>::verify_all_partitions(&pub_params, &pub_inputs, &swapped)
+            .expect("verification failed");
+        assert!(!is_valid);
+    }
+
+    #[test]
+    fn window_post_pedersen() {
+        test_window_post::<PedersenHasher>();
+    }
+
+    #[test]
+    fn window_post_sha256() {
+        test_window_post::<Sha256Hasher>();
+    }
+
+    #[test]
+    fn window_post_blake2s() {
+        test_window_post::<Blake2sHasher>();
+    }
+}